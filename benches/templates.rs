@@ -0,0 +1,36 @@
+//! Per-frame template cost: parsing on every render vs precompiled
+//!
+//! Run with `cargo bench --bench templates`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use dualsense_cmd::config::{Config, TemplateContext};
+use dualsense_cmd::dualsense::ControllerState;
+use dualsense_cmd::helpers;
+use dualsense_cmd::templates::TemplateSet;
+
+fn state_format_render(c: &mut Criterion) {
+    let config: Config =
+        serde_json::from_str(include_str!("../config/websocket-spatial-streaming.json")).unwrap();
+    let format = config
+        .websocket
+        .as_ref()
+        .and_then(|ws| ws.state_format.clone())
+        .expect("example config has a state_format");
+    let ctx = TemplateContext::from(&ControllerState::default());
+
+    let registry = helpers::registry();
+    let templates = TemplateSet::compile(&config).unwrap();
+
+    let mut group = c.benchmark_group("state_format");
+    group.bench_function("render_template", |b| {
+        b.iter(|| registry.render_template(black_box(&format), &ctx).unwrap())
+    });
+    group.bench_function("precompiled", |b| {
+        b.iter(|| templates.render(black_box(&format), &ctx).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, state_format_render);
+criterion_main!(benches);
@@ -0,0 +1,457 @@
+//! Config composition
+//!
+//! Resolves `extends` and `include` references and merges config
+//! directories into a single document before it is deserialized into
+//! [`Config`](crate::config::Config).
+//!
+//! ## Merge semantics
+//!
+//! Documents are merged in order, later documents overriding earlier ones:
+//! - Objects are merged key by key, recursively
+//! - Arrays and scalars replace the previous value entirely
+//! - An explicit `null` removes the key, restoring its default
+//!
+//! For a single file the order is: every `extends` base (in listed order),
+//! then every `include` fragment, then the file's own keys.
+//! For a directory the order is the main `config` file first, then the
+//! remaining config files (JSON, TOML or YAML) sorted by file name.
+//! Every file is merged once: a file already pulled in through `extends`
+//! or `include` is skipped when it comes up again.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::format::FileFormat;
+
+/// Key naming base config files to inherit from
+pub const EXTENDS_KEY: &str = "extends";
+/// Key naming fragment files to merge in
+pub const INCLUDE_KEY: &str = "include";
+/// File stem loaded first when merging a directory
+pub const MAIN_CONFIG_STEM: &str = "config";
+
+/// A merged config document with the source file of every leaf value
+#[derive(Debug, Clone)]
+pub struct ResolvedConfig {
+    /// Merged document (without `extends`/`include` keys)
+    pub value: Value,
+    /// Dotted key path -> file that set it
+    pub origins: BTreeMap<String, PathBuf>,
+    /// Every file read while resolving
+    pub files: Vec<PathBuf>,
+}
+
+impl Default for ResolvedConfig {
+    fn default() -> Self {
+        Self {
+            value: Value::Object(Map::new()),
+            origins: BTreeMap::new(),
+            files: Vec::new(),
+        }
+    }
+}
+
+impl ResolvedConfig {
+    /// Wrap a single document, attributing every leaf to `source`
+    pub fn from_value(value: Value, source: &Path) -> Self {
+        let mut origins = BTreeMap::new();
+        collect_leaves(&value, "", &mut |path| {
+            origins.insert(path.to_string(), source.to_path_buf());
+        });
+        Self {
+            value,
+            origins,
+            files: vec![source.to_path_buf()],
+        }
+    }
+
+    /// Merge another resolved document on top of this one
+    pub fn merge(&mut self, overlay: ResolvedConfig) {
+        for file in &overlay.files {
+            if !self.files.contains(file) {
+                self.files.push(file.clone());
+            }
+        }
+        merge_value(
+            &mut self.value,
+            overlay.value,
+            "",
+            &mut self.origins,
+            &overlay.origins,
+        );
+    }
+}
+
+/// Resolve a config file, following `extends` and `include` references
+pub fn resolve_file(path: &Path) -> Result<ResolvedConfig> {
+    resolve_file_inner(path, &mut Vec::new(), &mut HashSet::new())
+}
+
+/// Resolve a config directory by merging every config file in order
+pub fn resolve_dir(dir: &Path) -> Result<ResolvedConfig> {
+    let mut resolved = ResolvedConfig::default();
+    let mut visited = HashSet::new();
+    for file in dir_files(dir)? {
+        resolved.merge(resolve_file_inner(&file, &mut Vec::new(), &mut visited)?);
+    }
+    Ok(resolved)
+}
+
+/// Resolve a path that may be a file or a directory
+pub fn resolve_path(path: &Path) -> Result<ResolvedConfig> {
+    if path.is_dir() {
+        resolve_dir(path)
+    } else {
+        resolve_file(path)
+    }
+}
+
+/// Config files of a directory in merge order
+pub fn dir_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read config directory: {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && is_config_file(p))
+        .collect();
+
+    files.sort_by_key(|p| {
+        let is_main = p.file_stem().map(|s| s == MAIN_CONFIG_STEM).unwrap_or(false);
+        let name = p.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+        (!is_main, name)
+    });
+    Ok(files)
+}
+
+fn is_config_file(path: &Path) -> bool {
+    FileFormat::is_supported(path)
+}
+
+/// `stack` holds the files being resolved (for cycle detection), `visited`
+/// every file merged so far
+fn resolve_file_inner(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    visited: &mut HashSet<PathBuf>,
+) -> Result<ResolvedConfig> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+    if stack.contains(&canonical) {
+        bail!(
+            "Config reference cycle: {}",
+            stack
+                .iter()
+                .chain(std::iter::once(&canonical))
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        );
+    }
+    if !visited.insert(canonical.clone()) {
+        return Ok(ResolvedConfig::default());
+    }
+
+    let mut value = read_document(path)?;
+    let Some(object) = value.as_object_mut() else {
+        bail!("Config file must contain an object: {}", path.display());
+    };
+
+    let extends = take_path_list(object, EXTENDS_KEY, path)?;
+    let includes = take_path_list(object, INCLUDE_KEY, path)?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+
+    stack.push(canonical);
+    let mut resolved = ResolvedConfig::default();
+    for reference in extends.iter().chain(includes.iter()) {
+        let referenced = resolve_file_inner(&base_dir.join(reference), stack, visited)
+            .with_context(|| format!("Referenced from {}", path.display()))?;
+        resolved.merge(referenced);
+    }
+    stack.pop();
+
+    resolved.merge(ResolvedConfig::from_value(value, path));
+    Ok(resolved)
+}
+
+/// Read a config document in any supported format
+pub fn read_document(path: &Path) -> Result<Value> {
+    let format = FileFormat::from_path(path)?;
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+    format
+        .parse(&content)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))
+}
+
+/// Remove a string-or-array-of-strings key and return its paths
+fn take_path_list(object: &mut Map<String, Value>, key: &str, file: &Path) -> Result<Vec<String>> {
+    match object.remove(key) {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::String(s)) => Ok(vec![s]),
+        Some(Value::Array(items)) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::String(s) => Ok(s),
+                other => bail!("`{}` entries must be strings, got {} in {}", key, other, file.display()),
+            })
+            .collect(),
+        Some(other) => bail!(
+            "`{}` must be a string or array of strings, got {} in {}",
+            key,
+            other,
+            file.display()
+        ),
+    }
+}
+
+fn merge_value(
+    base: &mut Value,
+    overlay: Value,
+    path: &str,
+    origins: &mut BTreeMap<String, PathBuf>,
+    overlay_origins: &BTreeMap<String, PathBuf>,
+) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                let child_path = join_path(path, &key);
+                if value.is_null() {
+                    base_map.remove(&key);
+                    remove_origins(origins, &child_path);
+                    continue;
+                }
+                match base_map.get_mut(&key) {
+                    Some(existing) if existing.is_object() && value.is_object() => {
+                        merge_value(existing, value, &child_path, origins, overlay_origins);
+                    }
+                    _ => {
+                        remove_origins(origins, &child_path);
+                        copy_origins(origins, overlay_origins, &child_path);
+                        let mut value = value;
+                        strip_nulls(&mut value, &child_path, origins);
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => {
+            remove_origins(origins, path);
+            copy_origins(origins, overlay_origins, path);
+            let mut overlay = overlay;
+            strip_nulls(&mut overlay, path, origins);
+            *base = overlay;
+        }
+    }
+}
+
+/// Apply the `null` removal rule inside a value that is inserted whole
+fn strip_nulls(value: &mut Value, path: &str, origins: &mut BTreeMap<String, PathBuf>) {
+    let Value::Object(map) = value else {
+        return;
+    };
+    map.retain(|key, child| {
+        let child_path = join_path(path, key);
+        if child.is_null() {
+            remove_origins(origins, &child_path);
+            return false;
+        }
+        strip_nulls(child, &child_path, origins);
+        true
+    });
+}
+
+fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn is_under(path: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || path == prefix
+        || (path.starts_with(prefix) && path[prefix.len()..].starts_with('.'))
+}
+
+fn remove_origins(origins: &mut BTreeMap<String, PathBuf>, prefix: &str) {
+    origins.retain(|path, _| !is_under(path, prefix));
+}
+
+fn copy_origins(
+    origins: &mut BTreeMap<String, PathBuf>,
+    overlay_origins: &BTreeMap<String, PathBuf>,
+    prefix: &str,
+) {
+    for (path, source) in overlay_origins {
+        if is_under(path, prefix) {
+            origins.insert(path.clone(), source.clone());
+        }
+    }
+}
+
+fn collect_leaves(value: &Value, path: &str, f: &mut impl FnMut(&str)) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, child) in map {
+                collect_leaves(child, &join_path(path, key), f);
+            }
+        }
+        _ if path.is_empty() => {}
+        _ => f(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write(dir: &Path, name: &str, value: Value) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, serde_json::to_string_pretty(&value).unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_deep_merge_semantics() {
+        let a = Path::new("a.json");
+        let b = Path::new("b.json");
+        let mut resolved = ResolvedConfig::from_value(
+            json!({
+                "name": "base",
+                "buttons": { "cross": { "command": "a", "debounce_ms": 100 } },
+                "shell": { "env": { "A": "1" } },
+                "touchpad": { "regions": [1, 2] }
+            }),
+            a,
+        );
+        resolved.merge(ResolvedConfig::from_value(
+            json!({
+                "buttons": { "cross": { "command": "b" }, "circle": { "command": "c" } },
+                "shell": null,
+                "touchpad": { "regions": [3] }
+            }),
+            b,
+        ));
+
+        assert_eq!(
+            resolved.value,
+            json!({
+                "name": "base",
+                "buttons": {
+                    "cross": { "command": "b", "debounce_ms": 100 },
+                    "circle": { "command": "c" }
+                },
+                "touchpad": { "regions": [3] }
+            })
+        );
+        assert_eq!(resolved.origins["name"], a);
+        assert_eq!(resolved.origins["buttons.cross.command"], b);
+        assert_eq!(resolved.origins["buttons.cross.debounce_ms"], a);
+        assert_eq!(resolved.origins["touchpad.regions"], b);
+        assert!(!resolved.origins.contains_key("shell.env.A"));
+    }
+
+    #[test]
+    fn test_null_removed_inside_new_object() {
+        let mut resolved =
+            ResolvedConfig::from_value(json!({ "name": "base" }), Path::new("a.json"));
+        resolved.merge(ResolvedConfig::from_value(
+            json!({ "buttons": { "cross": { "command": "x", "led": null }, "circle": null } }),
+            Path::new("b.json"),
+        ));
+
+        assert_eq!(
+            resolved.value,
+            json!({ "name": "base", "buttons": { "cross": { "command": "x" } } })
+        );
+        assert!(!resolved.origins.contains_key("buttons.cross.led"));
+        assert!(!resolved.origins.contains_key("buttons.circle"));
+    }
+
+    #[test]
+    fn test_extends_and_include() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = scratch.path();
+        write(dir, "base.json", json!({ "name": "base", "poll_rate": 60 }));
+        write(
+            dir,
+            "buttons.json",
+            json!({ "buttons": { "cross": { "command": "echo x" } } }),
+        );
+        let main = write(
+            dir,
+            "main.json",
+            json!({ "extends": "base.json", "include": ["buttons.json"], "name": "main" }),
+        );
+
+        let resolved = resolve_file(&main).unwrap();
+        assert_eq!(resolved.value["name"], "main");
+        assert_eq!(resolved.value["poll_rate"], 60);
+        assert_eq!(resolved.value["buttons"]["cross"]["command"], "echo x");
+        assert!(resolved.value.get(EXTENDS_KEY).is_none());
+        assert!(resolved.origins["poll_rate"].ends_with("base.json"));
+        assert!(resolved.origins["buttons.cross.command"].ends_with("buttons.json"));
+    }
+
+    #[test]
+    fn test_extends_cycle_is_error() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = scratch.path();
+        write(dir, "a.json", json!({ "extends": "b.json" }));
+        let b = write(dir, "b.json", json!({ "extends": "a.json" }));
+
+        let err = resolve_file(&b).unwrap_err();
+        assert!(format!("{:#}", err).contains("cycle"));
+    }
+
+    #[test]
+    fn test_directory_order() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = scratch.path();
+        write(dir, "a-buttons.json", json!({ "name": "from-a", "poll_rate": 30 }));
+        write(dir, "config.json", json!({ "name": "main", "poll_rate": 100, "deadzone": 0.2 }));
+        write(dir, "z-override.json", json!({ "poll_rate": 250 }));
+
+        let files: Vec<_> = dir_files(dir)
+            .unwrap()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(files, vec!["config.json", "a-buttons.json", "z-override.json"]);
+
+        let resolved = resolve_dir(dir).unwrap();
+        assert_eq!(resolved.value["name"], "from-a");
+        assert_eq!(resolved.value["poll_rate"], 250);
+        assert_eq!(resolved.value["deadzone"], 0.2);
+    }
+
+    #[test]
+    fn test_directory_merges_included_file_once() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = scratch.path();
+        write(
+            dir,
+            "config.json",
+            json!({ "include": "shared.json", "poll_rate": 120 }),
+        );
+        write(
+            dir,
+            "shared.json",
+            json!({ "name": "shared", "poll_rate": 60 }),
+        );
+
+        // `shared.json` sorts after `config.json` but was already included,
+        // so it doesn't override the main file
+        let resolved = resolve_dir(dir).unwrap();
+        assert_eq!(resolved.value["name"], "shared");
+        assert_eq!(resolved.value["poll_rate"], 120);
+    }
+}
@@ -3,15 +3,26 @@
 //! Defines the configuration schema for mapping controller inputs
 //! to shell commands and WebSocket messages.
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+
+use crate::compose::{self, ResolvedConfig};
+use crate::curve::{AxisCurves, ResponseCurve};
+use crate::format::FileFormat;
+use crate::pipeline::OverflowPolicy;
+use crate::templates::Template;
+use crate::process::LimitPolicy;
+use crate::schema::ValidationError;
+use crate::touchpad::TouchRect;
 
 /// Root configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     /// Configuration name/description
     #[serde(default)]
@@ -37,6 +48,30 @@ pub struct Config {
     #[serde(default)]
     pub http: Option<HttpConfig>,
 
+    /// OSC (Open Sound Control) output over UDP
+    #[serde(default)]
+    pub osc: Option<OscConfig>,
+
+    /// MQTT broker connection
+    #[serde(default)]
+    pub mqtt: Option<MqttConfig>,
+
+    /// Raw socket outputs by name
+    #[serde(default)]
+    pub sockets: BTreeMap<String, SocketConfig>,
+
+    /// MIDI output: buttons to notes, analog inputs to controllers
+    #[serde(default)]
+    pub midi: Option<MidiConfig>,
+
+    /// Move the virtual mouse with a stick or the gyro
+    #[serde(default)]
+    pub mouse: Option<MouseConfig>,
+
+    /// Virtual Xbox-style gamepad following the controller
+    #[serde(default)]
+    pub gamepad: Option<GamepadConfig>,
+
     /// Button mappings
     #[serde(default)]
     pub buttons: ButtonMappings,
@@ -49,21 +84,43 @@ pub struct Config {
     #[serde(default)]
     pub motion: MotionMappings,
 
+    /// Touchpad gesture and region mappings
+    #[serde(default)]
+    pub touchpad: TouchpadMappings,
+
     /// LED configuration
     #[serde(default)]
     pub led: LedConfig,
 
+    /// Battery and connection lifecycle hooks
+    #[serde(default)]
+    pub lifecycle: LifecycleConfig,
+
+    /// Runtime variables (`{{vars.name}}` in templates)
+    #[serde(default)]
+    pub variables: VariablesConfig,
+
+    /// Action queue between input detection and execution
+    #[serde(default)]
+    pub pipeline: PipelineConfig,
+
     /// Spatial integration settings
     #[serde(default)]
     pub integration: Option<IntegrationConfig>,
 }
 
 /// Spatial integration configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct IntegrationConfig {
-    /// Velocity curve: "linear", "quadratic", "cubic"
-    #[serde(default = "default_velocity_curve")]
-    pub velocity_curve: String,
+    /// Response curve for sticks and triggers: "linear", "quadratic", "cubic"
+    /// or a shape such as `{"type": "exponential", "expo": 0.4}`
+    #[serde(default)]
+    pub velocity_curve: ResponseCurve,
+
+    /// Per-axis curves, overriding `velocity_curve`
+    #[serde(default)]
+    pub curves: AxisCurves,
 
     /// Maximum linear speed in mm/s
     #[serde(default = "default_max_linear_speed")]
@@ -95,21 +152,18 @@ pub struct IntegrationConfig {
 }
 
 /// Orientation filter configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct OrientationFilterConfig {
     /// Filter type: "complementary", "madgwick"
-    #[serde(default = "default_filter_type")]
-    pub r#type: String,
+    #[serde(default)]
+    pub r#type: OrientationFilterType,
 
     /// Gyro weight for complementary filter (0.0-1.0)
     #[serde(default = "default_gyro_weight")]
     pub gyro_weight: f32,
 }
 
-fn default_velocity_curve() -> String {
-    "linear".to_string()
-}
-
 fn default_max_linear_speed() -> f32 {
     200.0
 }
@@ -134,8 +188,13 @@ fn default_position_units() -> String {
     "mm".to_string()
 }
 
-fn default_filter_type() -> String {
-    "complementary".to_string()
+/// Orientation filter algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OrientationFilterType {
+    #[default]
+    Complementary,
+    Madgwick,
 }
 
 fn default_gyro_weight() -> f32 {
@@ -151,7 +210,8 @@ fn default_deadzone() -> f32 {
 }
 
 /// Shell execution configuration
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ShellConfig {
     /// Shell to use (default: /bin/sh on Unix, cmd on Windows)
     #[serde(default)]
@@ -167,7 +227,8 @@ pub struct ShellConfig {
 }
 
 /// WebSocket configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct WebSocketConfig {
     /// WebSocket URL to connect to
     pub url: String,
@@ -186,7 +247,7 @@ pub struct WebSocketConfig {
 
     /// Message format for state updates
     #[serde(default)]
-    pub state_format: Option<String>,
+    pub state_format: Option<Template>,
 
     /// Interval for state updates in milliseconds (0 = disabled)
     #[serde(default)]
@@ -205,11 +266,16 @@ fn default_reconnect_delay() -> u64 {
     1000
 }
 
+fn default_connect_timeout() -> u64 {
+    2000
+}
+
 /// HTTP configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct HttpConfig {
     /// Base URL for HTTP requests
-    pub base_url: String,
+    pub base_url: Template,
 
     /// Default headers
     #[serde(default)]
@@ -218,14 +284,182 @@ pub struct HttpConfig {
     /// Request timeout in milliseconds
     #[serde(default = "default_timeout")]
     pub timeout_ms: u64,
+
+    /// Authentication for every request (requests can override it)
+    #[serde(default)]
+    pub auth: Option<HttpAuth>,
 }
 
 fn default_timeout() -> u64 {
     5000
 }
 
+/// OSC configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct OscConfig {
+    /// Where to send messages (`host:port`)
+    pub target: String,
+
+    /// Local address to send from
+    #[serde(default = "default_osc_bind")]
+    pub bind: String,
+
+    /// Interval for state updates in milliseconds (0 = disabled)
+    #[serde(default)]
+    pub state_interval_ms: u64,
+
+    /// Messages sent as one bundle on each state update
+    #[serde(default)]
+    pub state_messages: Vec<OscMessageConfig>,
+}
+
+fn default_osc_bind() -> String {
+    "0.0.0.0:0".to_string()
+}
+
+/// MQTT configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MqttConfig {
+    /// Broker host name or address
+    pub host: String,
+
+    /// Broker port (default: 1883)
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+
+    /// Client identifier
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+
+    /// Environment variable holding the user name
+    #[serde(default)]
+    pub username_env: Option<String>,
+
+    /// Environment variable holding the password
+    #[serde(default)]
+    pub password_env: Option<String>,
+
+    /// Connect over TLS
+    #[serde(default)]
+    pub tls: bool,
+
+    /// Keepalive interval in seconds
+    #[serde(default = "default_mqtt_keepalive")]
+    pub keepalive_s: u64,
+
+    /// Topic for state updates (supports templates)
+    #[serde(default)]
+    pub state_topic: Option<Template>,
+
+    /// Payload format for state updates
+    #[serde(default)]
+    pub state_format: Option<Template>,
+
+    /// Interval for state updates in milliseconds (0 = disabled)
+    #[serde(default)]
+    pub state_interval_ms: u64,
+
+    /// QoS of state updates (0-2)
+    #[serde(default)]
+    #[schemars(range(max = 2))]
+    pub state_qos: u8,
+
+    /// Message the broker publishes if the connection drops
+    #[serde(default)]
+    pub last_will: Option<MqttLastWill>,
+}
+
+/// MQTT last will, with a matching message on connect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MqttLastWill {
+    /// Status topic
+    pub topic: String,
+
+    /// Published by the broker when the connection drops, and on a clean exit
+    #[serde(default = "default_offline_payload")]
+    pub payload: String,
+
+    /// Published on every (re)connect
+    #[serde(default)]
+    pub online_payload: Option<String>,
+
+    /// QoS (0-2)
+    #[serde(default)]
+    #[schemars(range(max = 2))]
+    pub qos: u8,
+
+    /// Keep the status on the broker for new subscribers
+    #[serde(default = "default_true")]
+    pub retain: bool,
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+fn default_mqtt_client_id() -> String {
+    "dualsense-cmd".to_string()
+}
+
+fn default_mqtt_keepalive() -> u64 {
+    30
+}
+
+fn default_offline_payload() -> String {
+    "controller offline".to_string()
+}
+
+/// Raw socket output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SocketConfig {
+    /// Socket type
+    #[serde(rename = "type")]
+    pub kind: SocketKind,
+
+    /// `host:port` for `udp` and `tcp`, a path for Unix sockets
+    pub address: String,
+
+    /// Wait before reconnecting a `tcp` or `unix` stream, in milliseconds
+    #[serde(default = "default_reconnect_delay")]
+    pub reconnect_delay_ms: u64,
+
+    /// Give up on resolving and connecting after this many milliseconds
+    /// (then wait `reconnect_delay_ms` like after any failed attempt)
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout_ms: u64,
+
+    /// Message format for state updates
+    #[serde(default)]
+    pub state_format: Option<Template>,
+
+    /// Interval for state updates in milliseconds (0 = disabled)
+    #[serde(default)]
+    pub state_interval_ms: u64,
+
+    /// Send state updates as binary (no newline on streams)
+    #[serde(default)]
+    pub binary: bool,
+}
+
+/// Socket type of a raw output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SocketKind {
+    Udp,
+    /// TCP client, reconnecting after errors
+    Tcp,
+    /// Unix stream socket
+    Unix,
+    UnixDatagram,
+}
+
 /// Button mappings
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ButtonMappings {
     // Face buttons
     #[serde(default)]
@@ -276,8 +510,246 @@ pub struct ButtonMappings {
     pub mute: Option<ActionConfig>,
 }
 
+impl ButtonMappings {
+    /// Mapped actions by field name
+    pub fn actions(&self) -> impl Iterator<Item = (&'static str, &ActionConfig)> + '_ {
+        mapped([
+            ("cross", &self.cross),
+            ("circle", &self.circle),
+            ("square", &self.square),
+            ("triangle", &self.triangle),
+            ("dpad_up", &self.dpad_up),
+            ("dpad_down", &self.dpad_down),
+            ("dpad_left", &self.dpad_left),
+            ("dpad_right", &self.dpad_right),
+            ("l1", &self.l1),
+            ("r1", &self.r1),
+            ("l2_button", &self.l2_button),
+            ("r2_button", &self.r2_button),
+            ("l3", &self.l3),
+            ("r3", &self.r3),
+            ("options", &self.options),
+            ("create", &self.create),
+            ("ps", &self.ps),
+            ("touchpad", &self.touchpad),
+            ("mute", &self.mute),
+        ])
+    }
+}
+
+/// `(field name, action)` pairs of the actions that are set
+fn mapped<'a, const N: usize>(
+    fields: [(&'static str, &'a Option<ActionConfig>); N],
+) -> impl Iterator<Item = (&'static str, &'a ActionConfig)> {
+    fields
+        .into_iter()
+        .filter_map(|(name, action)| Some((name, action.as_ref()?)))
+}
+
+/// MIDI output configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MidiConfig {
+    /// Name of the virtual port to create (ALSA sequencer on Linux)
+    #[serde(default = "default_midi_port_name")]
+    pub port_name: String,
+
+    /// Connect to the existing output port whose name contains this,
+    /// instead of creating a virtual port
+    #[serde(default)]
+    pub connect_to: Option<String>,
+
+    /// Default MIDI channel (1-16)
+    #[serde(default = "default_midi_channel")]
+    #[schemars(range(min = 1, max = 16))]
+    pub channel: u8,
+
+    /// Notes played by buttons
+    #[serde(default)]
+    pub notes: MidiNoteMappings,
+
+    /// Controllers driven by analog inputs
+    #[serde(default)]
+    pub controls: MidiControlMappings,
+}
+
+impl MidiConfig {
+    /// Whether a change from `other` needs the port reopened
+    pub fn same_port(&self, other: &MidiConfig) -> bool {
+        self.port_name == other.port_name && self.connect_to == other.connect_to
+    }
+
+    /// Report 14-bit controllers whose LSB (`cc + 32`) would leave 0-63
+    fn check(&self, problems: &mut Vec<ValidationError>) {
+        let c = &self.controls;
+        for (name, control) in [
+            ("left_stick_x", &c.left_stick_x),
+            ("left_stick_y", &c.left_stick_y),
+            ("right_stick_x", &c.right_stick_x),
+            ("right_stick_y", &c.right_stick_y),
+            ("l2_trigger", &c.l2_trigger),
+            ("r2_trigger", &c.r2_trigger),
+            ("tilt_roll", &c.tilt_roll),
+            ("tilt_pitch", &c.tilt_pitch),
+            ("touch_x", &c.touch_x),
+            ("touch_y", &c.touch_y),
+        ] {
+            let Some(control) = control else { continue };
+            if control.high_resolution && control.cc.is_some_and(|cc| cc > 31) {
+                problems.push(ValidationError::at(
+                    format!("$.midi.controls.{}.cc", name),
+                    "a high_resolution controller needs cc 0-31 (the LSB goes on cc + 32)",
+                ));
+            }
+        }
+    }
+}
+
+fn default_midi_port_name() -> String {
+    "DualSense".to_string()
+}
+
+fn default_midi_channel() -> u8 {
+    1
+}
+
+/// Button to note mappings
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MidiNoteMappings {
+    #[serde(default)]
+    pub cross: Option<MidiNote>,
+    #[serde(default)]
+    pub circle: Option<MidiNote>,
+    #[serde(default)]
+    pub square: Option<MidiNote>,
+    #[serde(default)]
+    pub triangle: Option<MidiNote>,
+    #[serde(default)]
+    pub dpad_up: Option<MidiNote>,
+    #[serde(default)]
+    pub dpad_down: Option<MidiNote>,
+    #[serde(default)]
+    pub dpad_left: Option<MidiNote>,
+    #[serde(default)]
+    pub dpad_right: Option<MidiNote>,
+    #[serde(default)]
+    pub l1: Option<MidiNote>,
+    #[serde(default)]
+    pub r1: Option<MidiNote>,
+    #[serde(default)]
+    pub l2_button: Option<MidiNote>,
+    #[serde(default)]
+    pub r2_button: Option<MidiNote>,
+    #[serde(default)]
+    pub l3: Option<MidiNote>,
+    #[serde(default)]
+    pub r3: Option<MidiNote>,
+    #[serde(default)]
+    pub options: Option<MidiNote>,
+    #[serde(default)]
+    pub create: Option<MidiNote>,
+    #[serde(default)]
+    pub ps: Option<MidiNote>,
+    #[serde(default)]
+    pub touchpad: Option<MidiNote>,
+    #[serde(default)]
+    pub mute: Option<MidiNote>,
+}
+
+/// Note played while a button is held
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MidiNote {
+    /// Note number (0-127, 60 = middle C)
+    #[schemars(range(max = 127))]
+    pub note: u8,
+
+    /// Fixed velocity (1-127)
+    #[serde(default = "default_midi_velocity")]
+    #[schemars(range(min = 1, max = 127))]
+    pub velocity: u8,
+
+    /// Take the velocity from a trigger's pressure instead
+    #[serde(default)]
+    pub velocity_from: Option<MidiPressure>,
+
+    /// Channel (1-16), overriding `midi.channel`
+    #[serde(default)]
+    #[schemars(range(min = 1, max = 16))]
+    pub channel: Option<u8>,
+}
+
+fn default_midi_velocity() -> u8 {
+    100
+}
+
+/// Trigger whose pressure sets a note's velocity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiPressure {
+    L2,
+    R2,
+}
+
+/// Analog input to controller mappings
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MidiControlMappings {
+    #[serde(default)]
+    pub left_stick_x: Option<MidiControl>,
+    #[serde(default)]
+    pub left_stick_y: Option<MidiControl>,
+    #[serde(default)]
+    pub right_stick_x: Option<MidiControl>,
+    #[serde(default)]
+    pub right_stick_y: Option<MidiControl>,
+    #[serde(default)]
+    pub l2_trigger: Option<MidiControl>,
+    #[serde(default)]
+    pub r2_trigger: Option<MidiControl>,
+
+    /// Tilt left/right (roll, +-90 degrees over the full range)
+    #[serde(default)]
+    pub tilt_roll: Option<MidiControl>,
+    /// Tilt forward/back (pitch, +-90 degrees over the full range)
+    #[serde(default)]
+    pub tilt_pitch: Option<MidiControl>,
+
+    /// First finger on the touchpad (sent only while touching)
+    #[serde(default)]
+    pub touch_x: Option<MidiControl>,
+    #[serde(default)]
+    pub touch_y: Option<MidiControl>,
+}
+
+/// Controller (CC) or pitch bend driven by an analog input
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MidiControl {
+    /// Controller number (0-119); leave out for pitch bend
+    #[serde(default)]
+    #[schemars(range(max = 119))]
+    pub cc: Option<u8>,
+
+    /// 14-bit value: `cc` (0-31) carries the MSB and `cc + 32` the LSB.
+    /// Pitch bend is always 14-bit.
+    #[serde(default)]
+    pub high_resolution: bool,
+
+    /// Reverse the direction
+    #[serde(default)]
+    pub invert: bool,
+
+    /// Channel (1-16), overriding `midi.channel`
+    #[serde(default)]
+    #[schemars(range(min = 1, max = 16))]
+    pub channel: Option<u8>,
+}
+
 /// Analog input mappings
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct AnalogMappings {
     #[serde(default)]
     pub left_stick: Option<StickMapping>,
@@ -289,14 +761,40 @@ pub struct AnalogMappings {
     pub r2_trigger: Option<TriggerMapping>,
 }
 
+impl AnalogMappings {
+    /// Response curves of the mapped sticks and triggers
+    pub fn curves(&self) -> AxisCurves {
+        let (left_stick_x, left_stick_y) = self
+            .left_stick
+            .as_ref()
+            .map(StickMapping::axis_curves)
+            .unwrap_or_default();
+        let (right_stick_x, right_stick_y) = self
+            .right_stick
+            .as_ref()
+            .map(StickMapping::axis_curves)
+            .unwrap_or_default();
+
+        AxisCurves {
+            left_stick_x,
+            left_stick_y,
+            right_stick_x,
+            right_stick_y,
+            l2_trigger: self.l2_trigger.as_ref().and_then(|t| t.curve.clone()),
+            r2_trigger: self.r2_trigger.as_ref().and_then(|t| t.curve.clone()),
+        }
+    }
+}
+
 /// Stick mapping configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct StickMapping {
     /// Action when stick is moved (continuous)
     #[serde(default)]
     pub on_move: Option<ActionConfig>,
 
-    /// Action when stick crosses threshold in positive X
+    /// Action when stick enters the right sector
     #[serde(default)]
     pub on_right: Option<ActionConfig>,
     #[serde(default)]
@@ -306,13 +804,118 @@ pub struct StickMapping {
     #[serde(default)]
     pub on_down: Option<ActionConfig>,
 
-    /// Threshold for directional triggers (0.0 - 1.0)
+    /// Diagonal actions (only fire with `diagonals` enabled)
+    #[serde(default)]
+    pub on_up_left: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_up_right: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_down_left: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_down_right: Option<ActionConfig>,
+
+    /// Action when stick returns to center after a direction
+    #[serde(default)]
+    pub on_center: Option<ActionConfig>,
+
+    /// Magnitude that enters a direction (0.0 - 1.0)
     #[serde(default = "default_threshold")]
     pub threshold: f32,
 
+    /// Magnitude below which the stick is centered again (default: threshold - 0.1)
+    #[serde(default)]
+    pub release_threshold: Option<f32>,
+
+    /// Recognize diagonals (8-way) instead of 4-way
+    #[serde(default)]
+    pub diagonals: bool,
+
+    /// Degrees past a sector edge needed to switch direction
+    #[serde(default = "default_stick_angle_hysteresis_deg")]
+    pub angle_hysteresis_deg: f32,
+
     /// Update rate limiting in milliseconds
     #[serde(default)]
     pub rate_limit_ms: u64,
+
+    /// Response curve for both axes
+    #[serde(default)]
+    pub curve: Option<ResponseCurve>,
+
+    /// Response curve for the X axis, overriding `curve`
+    #[serde(default)]
+    pub curve_x: Option<ResponseCurve>,
+
+    /// Response curve for the Y axis, overriding `curve`
+    #[serde(default)]
+    pub curve_y: Option<ResponseCurve>,
+}
+
+impl StickMapping {
+    /// Mapped actions by field name
+    pub fn actions(&self) -> impl Iterator<Item = (&'static str, &ActionConfig)> + '_ {
+        mapped([
+            ("on_move", &self.on_move),
+            ("on_right", &self.on_right),
+            ("on_left", &self.on_left),
+            ("on_up", &self.on_up),
+            ("on_down", &self.on_down),
+            ("on_up_left", &self.on_up_left),
+            ("on_up_right", &self.on_up_right),
+            ("on_down_left", &self.on_down_left),
+            ("on_down_right", &self.on_down_right),
+            ("on_center", &self.on_center),
+        ])
+    }
+
+    /// Direction recognizer tuning for this stick
+    pub fn stick_settings(&self) -> crate::stick::StickSettings {
+        crate::stick::StickSettings {
+            press_threshold: self.threshold,
+            release_threshold: self
+                .release_threshold
+                .unwrap_or((self.threshold - 0.1).max(0.0)),
+            diagonals: self.diagonals,
+            angle_hysteresis_deg: self.angle_hysteresis_deg,
+        }
+    }
+
+    /// Report settings of the mapping at `at` that contradict each other
+    fn check(&self, at: &str, problems: &mut Vec<ValidationError>) {
+        if let Some(release) = self.release_threshold {
+            if release > self.threshold {
+                problems.push(ValidationError::at(
+                    format!("{}.release_threshold", at),
+                    format!("{} is above threshold {}", release, self.threshold),
+                ));
+            }
+        }
+    }
+
+    /// Get the action mapped to a stick direction event
+    pub fn event_action(&self, event: crate::stick::StickEvent) -> Option<&ActionConfig> {
+        use crate::stick::{StickDirection, StickEvent};
+
+        match event {
+            StickEvent::Direction(StickDirection::Right) => self.on_right.as_ref(),
+            StickEvent::Direction(StickDirection::UpRight) => self.on_up_right.as_ref(),
+            StickEvent::Direction(StickDirection::Up) => self.on_up.as_ref(),
+            StickEvent::Direction(StickDirection::UpLeft) => self.on_up_left.as_ref(),
+            StickEvent::Direction(StickDirection::Left) => self.on_left.as_ref(),
+            StickEvent::Direction(StickDirection::DownLeft) => self.on_down_left.as_ref(),
+            StickEvent::Direction(StickDirection::Down) => self.on_down.as_ref(),
+            StickEvent::Direction(StickDirection::DownRight) => self.on_down_right.as_ref(),
+            StickEvent::Center => self.on_center.as_ref(),
+        }
+    }
+
+    /// (X, Y) curves after per-axis overrides
+    fn axis_curves(&self) -> (Option<ResponseCurve>, Option<ResponseCurve>) {
+        (
+            self.curve_x.clone().or_else(|| self.curve.clone()),
+            self.curve_y.clone().or_else(|| self.curve.clone()),
+        )
+    }
 }
 
 impl Default for StickMapping {
@@ -323,8 +926,19 @@ impl Default for StickMapping {
             on_left: None,
             on_up: None,
             on_down: None,
+            on_up_left: None,
+            on_up_right: None,
+            on_down_left: None,
+            on_down_right: None,
+            on_center: None,
             threshold: default_threshold(),
+            release_threshold: None,
+            diagonals: false,
+            angle_hysteresis_deg: default_stick_angle_hysteresis_deg(),
             rate_limit_ms: 0,
+            curve: None,
+            curve_x: None,
+            curve_y: None,
         }
     }
 }
@@ -333,147 +947,1579 @@ fn default_threshold() -> f32 {
     0.5
 }
 
+fn default_stick_angle_hysteresis_deg() -> f32 {
+    10.0
+}
+
 /// Trigger mapping configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct TriggerMapping {
     /// Action when trigger value changes
     #[serde(default)]
-    pub on_change: Option<ActionConfig>,
+    pub on_change: Option<ActionConfig>,
+
+    /// Action when trigger crosses threshold
+    #[serde(default)]
+    pub on_press: Option<ActionConfig>,
+
+    /// Action when trigger falls back below the release threshold
+    #[serde(default)]
+    pub on_release: Option<ActionConfig>,
+
+    /// Threshold for press detection (0.0 - 1.0)
+    #[serde(default = "default_threshold")]
+    pub threshold: f32,
+
+    /// Value below which a press is released (default: threshold - hysteresis)
+    #[serde(default)]
+    pub release_threshold: Option<f32>,
+
+    /// Margin below a zone's start (and the press threshold) before leaving it
+    #[serde(default = "default_trigger_hysteresis")]
+    pub hysteresis: f32,
+
+    /// Named pull zones, e.g. light/medium/full (sorted by threshold at load)
+    #[serde(default, deserialize_with = "sorted_zones")]
+    pub zones: Vec<TriggerZone>,
+
+    /// Adaptive trigger force (0-255) of a detent placed at the next zone
+    #[serde(default)]
+    pub detent_force: Option<u8>,
+
+    /// Response curve applied before the threshold
+    #[serde(default)]
+    pub curve: Option<ResponseCurve>,
+}
+
+impl Default for TriggerMapping {
+    fn default() -> Self {
+        Self {
+            on_change: None,
+            on_press: None,
+            on_release: None,
+            threshold: default_threshold(),
+            release_threshold: None,
+            hysteresis: default_trigger_hysteresis(),
+            zones: Vec::new(),
+            detent_force: None,
+            curve: None,
+        }
+    }
+}
+
+impl TriggerMapping {
+    /// Mapped actions by field name (zones have their own)
+    pub fn actions(&self) -> impl Iterator<Item = (&'static str, &ActionConfig)> + '_ {
+        mapped([
+            ("on_change", &self.on_change),
+            ("on_press", &self.on_press),
+            ("on_release", &self.on_release),
+        ])
+    }
+
+    /// Report settings of the mapping at `at` that contradict each other
+    fn check(&self, at: &str, problems: &mut Vec<ValidationError>) {
+        if let Some(release) = self.release_threshold {
+            if release > self.threshold {
+                problems.push(ValidationError::at(
+                    format!("{}.release_threshold", at),
+                    format!("{} is above threshold {}", release, self.threshold),
+                ));
+            }
+        }
+    }
+
+    /// Press and zone recognizer tuning for this trigger
+    pub fn trigger_settings(&self) -> crate::trigger::TriggerSettings {
+        crate::trigger::TriggerSettings {
+            press_threshold: self.threshold,
+            release_threshold: self
+                .release_threshold
+                .unwrap_or((self.threshold - self.hysteresis).max(0.0)),
+            zones: self.zones.iter().map(|z| z.threshold).collect(),
+            hysteresis: self.hysteresis,
+        }
+    }
+
+    /// Get the action mapped to a trigger event
+    pub fn event_action(&self, event: crate::trigger::TriggerEvent) -> Option<&ActionConfig> {
+        use crate::trigger::TriggerEvent;
+
+        match event {
+            TriggerEvent::Press => self.on_press.as_ref(),
+            TriggerEvent::Release => self.on_release.as_ref(),
+            TriggerEvent::ZoneEnter(i) => self.zones.get(i)?.on_enter.as_ref(),
+            TriggerEvent::ZoneLeave(i) => self.zones.get(i)?.on_leave.as_ref(),
+        }
+    }
+
+    /// Event name as seen by templates (`light_enter` for zones)
+    pub fn event_name(&self, event: crate::trigger::TriggerEvent) -> String {
+        use crate::trigger::TriggerEvent;
+
+        let zone_name = |i: usize| {
+            self.zones
+                .get(i)
+                .map_or_else(|| i.to_string(), |z| z.name.clone())
+        };
+        match event {
+            TriggerEvent::ZoneEnter(i) => format!("{}_enter", zone_name(i)),
+            TriggerEvent::ZoneLeave(i) => format!("{}_leave", zone_name(i)),
+            _ => event.name().to_string(),
+        }
+    }
+}
+
+/// A band of trigger travel starting at `threshold`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TriggerZone {
+    /// Zone name, e.g. "light"
+    pub name: String,
+
+    /// Where the zone starts (0.0 - 1.0); it ends where the next one starts
+    pub threshold: f32,
+
+    /// Action when the trigger enters the zone
+    #[serde(default)]
+    pub on_enter: Option<ActionConfig>,
+
+    /// Action when the trigger leaves the zone
+    #[serde(default)]
+    pub on_leave: Option<ActionConfig>,
+}
+
+fn default_trigger_hysteresis() -> f32 {
+    0.05
+}
+
+/// Zones sorted by where they start, once at load
+fn sorted_zones<'de, D>(deserializer: D) -> Result<Vec<TriggerZone>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut zones = Vec::<TriggerZone>::deserialize(deserializer)?;
+    zones.sort_by(|a, b| a.threshold.total_cmp(&b.threshold));
+    Ok(zones)
+}
+
+/// Motion/IMU mappings
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MotionMappings {
+    /// Action on orientation change
+    #[serde(default)]
+    pub on_orientation_change: Option<ActionConfig>,
+
+    /// Action on significant motion
+    #[serde(default)]
+    pub on_shake: Option<ActionConfig>,
+
+    // Tilt past `tilt_threshold_deg` (from the gravity direction)
+    #[serde(default)]
+    pub on_tilt_left: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_tilt_right: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_tilt_forward: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_tilt_back: Option<ActionConfig>,
+
+    // Flicks (fast rotation around X/Y) and twists (around Z)
+    #[serde(default)]
+    pub on_flick_left: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_flick_right: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_flick_up: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_flick_down: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_twist_left: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_twist_right: Option<ActionConfig>,
+
+    /// Shake detection threshold (in G)
+    #[serde(default = "default_shake_threshold")]
+    pub shake_threshold: f32,
+
+    /// Minimum time between shakes in milliseconds
+    #[serde(default = "default_shake_refractory")]
+    pub shake_refractory_ms: u64,
+
+    /// Update rate for orientation in milliseconds
+    #[serde(default)]
+    pub orientation_rate_ms: u64,
+
+    /// Minimum orientation change in degrees before firing
+    #[serde(default = "default_orientation_min_delta")]
+    pub orientation_min_delta_deg: f32,
+
+    /// Tilt angle in degrees
+    #[serde(default = "default_tilt_threshold")]
+    pub tilt_threshold_deg: f32,
+
+    /// Degrees below the tilt threshold needed to re-arm
+    #[serde(default = "default_tilt_hysteresis")]
+    pub tilt_hysteresis_deg: f32,
+
+    /// Gyro rate for flicks (rad/s)
+    #[serde(default = "default_flick_threshold")]
+    pub flick_threshold: f32,
+
+    /// Gyro rate for twists (rad/s)
+    #[serde(default = "default_twist_threshold")]
+    pub twist_threshold: f32,
+
+    /// Minimum time between flicks/twists in milliseconds
+    #[serde(default = "default_flick_refractory")]
+    pub flick_refractory_ms: u64,
+}
+
+impl Default for MotionMappings {
+    fn default() -> Self {
+        Self {
+            on_orientation_change: None,
+            on_shake: None,
+            on_tilt_left: None,
+            on_tilt_right: None,
+            on_tilt_forward: None,
+            on_tilt_back: None,
+            on_flick_left: None,
+            on_flick_right: None,
+            on_flick_up: None,
+            on_flick_down: None,
+            on_twist_left: None,
+            on_twist_right: None,
+            shake_threshold: default_shake_threshold(),
+            shake_refractory_ms: default_shake_refractory(),
+            orientation_rate_ms: 0,
+            orientation_min_delta_deg: default_orientation_min_delta(),
+            tilt_threshold_deg: default_tilt_threshold(),
+            tilt_hysteresis_deg: default_tilt_hysteresis(),
+            flick_threshold: default_flick_threshold(),
+            twist_threshold: default_twist_threshold(),
+            flick_refractory_ms: default_flick_refractory(),
+        }
+    }
+}
+
+impl MotionMappings {
+    /// Mapped actions by field name
+    pub fn actions(&self) -> impl Iterator<Item = (&'static str, &ActionConfig)> + '_ {
+        mapped([
+            ("on_orientation_change", &self.on_orientation_change),
+            ("on_shake", &self.on_shake),
+            ("on_tilt_left", &self.on_tilt_left),
+            ("on_tilt_right", &self.on_tilt_right),
+            ("on_tilt_forward", &self.on_tilt_forward),
+            ("on_tilt_back", &self.on_tilt_back),
+            ("on_flick_left", &self.on_flick_left),
+            ("on_flick_right", &self.on_flick_right),
+            ("on_flick_up", &self.on_flick_up),
+            ("on_flick_down", &self.on_flick_down),
+            ("on_twist_left", &self.on_twist_left),
+            ("on_twist_right", &self.on_twist_right),
+        ])
+    }
+
+    /// Motion detector settings from this mapping
+    pub fn motion_settings(&self) -> crate::motion::MotionSettings {
+        crate::motion::MotionSettings {
+            shake_threshold: self.shake_threshold,
+            shake_refractory_ms: self.shake_refractory_ms,
+            tilt_threshold_deg: self.tilt_threshold_deg,
+            tilt_hysteresis_deg: self.tilt_hysteresis_deg,
+            flick_threshold: self.flick_threshold,
+            twist_threshold: self.twist_threshold,
+            flick_refractory_ms: self.flick_refractory_ms,
+            orientation_min_delta_deg: self.orientation_min_delta_deg,
+            orientation_rate_ms: self.orientation_rate_ms,
+        }
+    }
+
+    /// Get the action mapped to a motion event
+    pub fn event_action(&self, event: crate::motion::MotionEvent) -> Option<&ActionConfig> {
+        use crate::motion::MotionEvent;
+
+        match event {
+            MotionEvent::Shake => self.on_shake.as_ref(),
+            MotionEvent::OrientationChange => self.on_orientation_change.as_ref(),
+            MotionEvent::TiltLeft => self.on_tilt_left.as_ref(),
+            MotionEvent::TiltRight => self.on_tilt_right.as_ref(),
+            MotionEvent::TiltForward => self.on_tilt_forward.as_ref(),
+            MotionEvent::TiltBack => self.on_tilt_back.as_ref(),
+            MotionEvent::FlickLeft => self.on_flick_left.as_ref(),
+            MotionEvent::FlickRight => self.on_flick_right.as_ref(),
+            MotionEvent::FlickUp => self.on_flick_up.as_ref(),
+            MotionEvent::FlickDown => self.on_flick_down.as_ref(),
+            MotionEvent::TwistLeft => self.on_twist_left.as_ref(),
+            MotionEvent::TwistRight => self.on_twist_right.as_ref(),
+        }
+    }
+}
+
+fn default_shake_threshold() -> f32 {
+    2.0
+}
+
+fn default_shake_refractory() -> u64 {
+    500
+}
+
+fn default_orientation_min_delta() -> f32 {
+    2.0
+}
+
+fn default_tilt_threshold() -> f32 {
+    30.0
+}
+
+fn default_tilt_hysteresis() -> f32 {
+    5.0
+}
+
+fn default_flick_threshold() -> f32 {
+    6.0
+}
+
+fn default_twist_threshold() -> f32 {
+    6.0
+}
+
+fn default_flick_refractory() -> u64 {
+    300
+}
+
+/// Touchpad gesture mappings
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TouchpadMappings {
+    /// Single-finger tap
+    #[serde(default)]
+    pub on_tap: Option<ActionConfig>,
+    /// Two-finger tap
+    #[serde(default)]
+    pub on_two_finger_tap: Option<ActionConfig>,
+
+    // Swipes (diagonals only fire when `swipe_diagonals` is enabled)
+    #[serde(default)]
+    pub on_swipe_up: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_swipe_down: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_swipe_left: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_swipe_right: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_swipe_up_left: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_swipe_up_right: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_swipe_down_left: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_swipe_down_right: Option<ActionConfig>,
+
+    // Two-finger gestures (fire once per threshold step)
+    #[serde(default)]
+    pub on_pinch_in: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_pinch_out: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_rotate_cw: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_rotate_ccw: Option<ActionConfig>,
+
+    /// Rectangular regions that act as virtual buttons
+    #[serde(default)]
+    pub regions: Vec<TouchRegion>,
+
+    /// Maximum touch duration for a tap in ms
+    #[serde(default = "default_tap_max_ms")]
+    pub tap_max_ms: u64,
+
+    /// Maximum finger travel for a tap (raw touchpad units, 1920x1080)
+    #[serde(default = "default_tap_max_distance")]
+    pub tap_max_distance: f32,
+
+    /// Minimum finger travel for a swipe (raw touchpad units)
+    #[serde(default = "default_swipe_min_distance")]
+    pub swipe_min_distance: f32,
+
+    /// Maximum swipe duration in ms
+    #[serde(default = "default_swipe_max_ms")]
+    pub swipe_max_ms: u64,
+
+    /// Recognize 8 swipe directions instead of 4
+    #[serde(default)]
+    pub swipe_diagonals: bool,
+
+    /// Finger spacing change per pinch step (raw touchpad units)
+    #[serde(default = "default_pinch_threshold")]
+    pub pinch_threshold: f32,
+
+    /// Finger angle change per rotate step (degrees)
+    #[serde(default = "default_rotate_threshold_deg")]
+    pub rotate_threshold_deg: f32,
+}
+
+impl Default for TouchpadMappings {
+    fn default() -> Self {
+        Self {
+            on_tap: None,
+            on_two_finger_tap: None,
+            on_swipe_up: None,
+            on_swipe_down: None,
+            on_swipe_left: None,
+            on_swipe_right: None,
+            on_swipe_up_left: None,
+            on_swipe_up_right: None,
+            on_swipe_down_left: None,
+            on_swipe_down_right: None,
+            on_pinch_in: None,
+            on_pinch_out: None,
+            on_rotate_cw: None,
+            on_rotate_ccw: None,
+            regions: Vec::new(),
+            tap_max_ms: default_tap_max_ms(),
+            tap_max_distance: default_tap_max_distance(),
+            swipe_min_distance: default_swipe_min_distance(),
+            swipe_max_ms: default_swipe_max_ms(),
+            swipe_diagonals: false,
+            pinch_threshold: default_pinch_threshold(),
+            rotate_threshold_deg: default_rotate_threshold_deg(),
+        }
+    }
+}
+
+impl TouchpadMappings {
+    /// Mapped gesture actions by field name (regions have their own)
+    pub fn actions(&self) -> impl Iterator<Item = (&'static str, &ActionConfig)> + '_ {
+        mapped([
+            ("on_tap", &self.on_tap),
+            ("on_two_finger_tap", &self.on_two_finger_tap),
+            ("on_swipe_up", &self.on_swipe_up),
+            ("on_swipe_down", &self.on_swipe_down),
+            ("on_swipe_left", &self.on_swipe_left),
+            ("on_swipe_right", &self.on_swipe_right),
+            ("on_swipe_up_left", &self.on_swipe_up_left),
+            ("on_swipe_up_right", &self.on_swipe_up_right),
+            ("on_swipe_down_left", &self.on_swipe_down_left),
+            ("on_swipe_down_right", &self.on_swipe_down_right),
+            ("on_pinch_in", &self.on_pinch_in),
+            ("on_pinch_out", &self.on_pinch_out),
+            ("on_rotate_cw", &self.on_rotate_cw),
+            ("on_rotate_ccw", &self.on_rotate_ccw),
+        ])
+    }
+
+    /// Gesture recognizer settings from this mapping
+    pub fn gesture_settings(&self) -> crate::touchpad::GestureSettings {
+        crate::touchpad::GestureSettings {
+            tap_max_ms: self.tap_max_ms,
+            tap_max_distance: self.tap_max_distance,
+            swipe_min_distance: self.swipe_min_distance,
+            swipe_max_ms: self.swipe_max_ms,
+            swipe_diagonals: self.swipe_diagonals,
+            pinch_threshold: self.pinch_threshold,
+            rotate_threshold_deg: self.rotate_threshold_deg,
+        }
+    }
+
+    /// Get the action mapped to a gesture
+    pub fn gesture_action(&self, gesture: crate::touchpad::Gesture) -> Option<&ActionConfig> {
+        use crate::touchpad::{Gesture, SwipeDirection};
+
+        match gesture {
+            Gesture::Tap => self.on_tap.as_ref(),
+            Gesture::TwoFingerTap => self.on_two_finger_tap.as_ref(),
+            Gesture::Swipe(SwipeDirection::Up) => self.on_swipe_up.as_ref(),
+            Gesture::Swipe(SwipeDirection::Down) => self.on_swipe_down.as_ref(),
+            Gesture::Swipe(SwipeDirection::Left) => self.on_swipe_left.as_ref(),
+            Gesture::Swipe(SwipeDirection::Right) => self.on_swipe_right.as_ref(),
+            Gesture::Swipe(SwipeDirection::UpLeft) => self.on_swipe_up_left.as_ref(),
+            Gesture::Swipe(SwipeDirection::UpRight) => self.on_swipe_up_right.as_ref(),
+            Gesture::Swipe(SwipeDirection::DownLeft) => self.on_swipe_down_left.as_ref(),
+            Gesture::Swipe(SwipeDirection::DownRight) => self.on_swipe_down_right.as_ref(),
+            Gesture::PinchIn => self.on_pinch_in.as_ref(),
+            Gesture::PinchOut => self.on_pinch_out.as_ref(),
+            Gesture::RotateClockwise => self.on_rotate_cw.as_ref(),
+            Gesture::RotateCounterClockwise => self.on_rotate_ccw.as_ref(),
+        }
+    }
+}
+
+fn default_tap_max_ms() -> u64 {
+    200
+}
+
+fn default_tap_max_distance() -> f32 {
+    40.0
+}
+
+fn default_swipe_min_distance() -> f32 {
+    300.0
+}
+
+fn default_swipe_max_ms() -> u64 {
+    600
+}
+
+fn default_pinch_threshold() -> f32 {
+    150.0
+}
+
+fn default_rotate_threshold_deg() -> f32 {
+    25.0
+}
+
+/// Touchpad region acting as a virtual button
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TouchRegion {
+    /// Region name (used for debounce and logging)
+    pub name: String,
+
+    /// Left edge in normalized touchpad coordinates (0.0 - 1.0)
+    pub x: f32,
+    /// Top edge in normalized touchpad coordinates (0.0 - 1.0)
+    pub y: f32,
+    /// Width in normalized touchpad coordinates
+    pub width: f32,
+    /// Height in normalized touchpad coordinates
+    pub height: f32,
+
+    /// Require the touchpad to be clicked (otherwise a touch is enough)
+    #[serde(default = "default_true")]
+    pub require_click: bool,
+
+    /// Action to run, using the same trigger semantics as buttons
+    pub action: ActionConfig,
+}
+
+impl TouchRegion {
+    /// Region rectangle
+    pub fn rect(&self) -> TouchRect {
+        TouchRect {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// Action configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ActionConfig {
+    /// When to trigger
+    #[serde(default)]
+    pub trigger: EventType,
+
+    /// Command to run: a string for the shell, or a `[program, args...]`
+    /// list run without one (supports templates)
+    #[serde(default)]
+    pub command: Option<ShellCommand>,
+
+    /// Timeout, output capture and concurrency limit of `command`
+    #[serde(default)]
+    pub process: Option<ProcessConfig>,
+
+    /// WebSocket message to send (supports templates)
+    #[serde(default)]
+    pub websocket: Option<WebSocketMessage>,
+
+    /// HTTP request to make
+    #[serde(default)]
+    pub http: Option<HttpRequest>,
+
+    /// OSC message to send, or a list sent as one bundle
+    #[serde(default)]
+    pub osc: Option<OscAction>,
+
+    /// MQTT message to publish
+    #[serde(default)]
+    pub mqtt: Option<MqttMessage>,
+
+    /// Messages to raw sockets, by socket name (supports templates)
+    #[serde(default)]
+    pub sockets: BTreeMap<String, WebSocketMessage>,
+
+    /// Key on the virtual keyboard
+    #[serde(default)]
+    pub key: Option<KeyAction>,
+
+    /// Button, wheel or movement of the virtual mouse
+    #[serde(default)]
+    pub mouse: Option<MouseAction>,
+
+    /// Rumble feedback
+    #[serde(default)]
+    pub rumble: Option<RumbleConfig>,
+
+    /// LED feedback
+    #[serde(default)]
+    pub led: Option<LedColorConfig>,
+
+    /// Blink the lightbar, then restore the previous color
+    #[serde(default)]
+    pub led_blink: Option<LedBlinkConfig>,
+
+    /// Only run the action while this variable condition holds
+    #[serde(default)]
+    pub when: Option<VarCondition>,
+
+    /// Variable updates, applied before the rest of the action
+    #[serde(default)]
+    pub vars: Vec<VarOp>,
+
+    /// Minimum interval between triggers (debounce) in ms
+    #[serde(default)]
+    pub debounce_ms: u64,
+
+    /// Only trigger if button held for this duration (ms)
+    #[serde(default)]
+    pub hold_time_ms: u64,
+
+    /// Keep firing while a button (or touchpad region) is held
+    #[serde(default)]
+    pub repeat: Option<RepeatConfig>,
+}
+
+impl ActionConfig {
+    /// Report settings of the action at `at` that can't take effect or
+    /// aren't safe; only buttons and touchpad regions are held, so only
+    /// they repeat
+    fn check(&self, at: &str, problems: &mut Vec<ValidationError>) {
+        if let Some(http) = &self.http {
+            http.check(&format!("{}.http", at), problems);
+        }
+        if let Some(key) = &self.key {
+            if crate::uinput::key_code(&key.key).is_none() {
+                problems.push(ValidationError::at(
+                    format!("{}.key.key", at),
+                    format!("unknown key `{}`", key.key),
+                ));
+            }
+        }
+        if self.repeat.is_none() {
+            return;
+        }
+        let held = at.starts_with("$.buttons.") || at.starts_with("$.touchpad.regions[");
+        if !held {
+            problems.push(ValidationError::at(
+                format!("{}.repeat", at),
+                "only button and touchpad region actions repeat",
+            ));
+        } else if self.trigger != EventType::Press {
+            problems.push(ValidationError::at(
+                format!("{}.repeat", at),
+                format!(
+                    "repeat needs trigger `press`, not `{}`",
+                    self.trigger.name()
+                ),
+            ));
+        }
+    }
+}
+
+impl Default for ActionConfig {
+    fn default() -> Self {
+        Self {
+            trigger: EventType::Press,
+            command: None,
+            process: None,
+            websocket: None,
+            http: None,
+            osc: None,
+            mqtt: None,
+            sockets: BTreeMap::new(),
+            key: None,
+            mouse: None,
+            rumble: None,
+            led: None,
+            led_blink: None,
+            when: None,
+            vars: Vec::new(),
+            debounce_ms: 0,
+            hold_time_ms: 0,
+            repeat: None,
+        }
+    }
+}
+
+/// Command of an action
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ShellCommand {
+    /// Command line run through the shell (`sh -c`, `cmd /C` on Windows)
+    Line(Template),
+    /// Program and arguments, each rendered on its own and run without a
+    /// shell, so template values cannot inject shell syntax
+    Argv(Vec<Template>),
+}
+
+impl From<&str> for ShellCommand {
+    fn from(line: &str) -> Self {
+        ShellCommand::Line(line.into())
+    }
+}
+
+/// How an action's command runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessConfig {
+    /// Kill the command (and everything it started) after this many ms
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+
+    /// Store the command's stdout in this variable when it exits
+    #[serde(default)]
+    pub stdout_var: Option<String>,
+
+    /// Store the command's stderr in this variable when it exits
+    #[serde(default)]
+    pub stderr_var: Option<String>,
+
+    /// Log the command's output lines
+    #[serde(default)]
+    pub log_output: bool,
+
+    /// Most runs of this action's command at once; runs are counted per
+    /// input and event (e.g. `cross` `press`), whatever the command renders to
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+
+    /// What to do when `max_concurrent` runs are going
+    #[serde(default)]
+    pub on_limit: LimitPolicy,
+}
+
+impl ProcessConfig {
+    /// Whether output is piped instead of going to the terminal
+    pub fn captures(&self) -> bool {
+        self.log_output || self.stdout_var.is_some() || self.stderr_var.is_some()
+    }
+}
+
+/// Auto-repeat while a digital input is held
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RepeatConfig {
+    /// Delay before the first repeat (ms)
+    #[serde(default = "default_repeat_delay_ms")]
+    pub delay_ms: u64,
+
+    /// Interval between repeats (ms)
+    #[serde(default = "default_repeat_interval_ms")]
+    pub interval_ms: u64,
+
+    /// Factor applied to the interval after each repeat (below 1.0 speeds up)
+    #[serde(default = "default_repeat_acceleration")]
+    pub acceleration: f32,
+
+    /// Shortest interval acceleration can reach (ms)
+    #[serde(default = "default_repeat_min_interval_ms")]
+    pub min_interval_ms: u64,
+
+    /// Stop after this many repeats
+    #[serde(default)]
+    pub max_count: Option<u32>,
+}
+
+impl Default for RepeatConfig {
+    fn default() -> Self {
+        Self {
+            delay_ms: default_repeat_delay_ms(),
+            interval_ms: default_repeat_interval_ms(),
+            acceleration: default_repeat_acceleration(),
+            min_interval_ms: default_repeat_min_interval_ms(),
+            max_count: None,
+        }
+    }
+}
+
+impl RepeatConfig {
+    pub fn repeat_settings(&self) -> crate::repeat::RepeatSettings {
+        crate::repeat::RepeatSettings {
+            delay_ms: self.delay_ms,
+            interval_ms: self.interval_ms,
+            acceleration: self.acceleration,
+            min_interval_ms: self.min_interval_ms,
+            max_count: self.max_count,
+        }
+    }
+}
+
+fn default_repeat_delay_ms() -> u64 {
+    400
+}
+
+fn default_repeat_interval_ms() -> u64 {
+    100
+}
+
+fn default_repeat_acceleration() -> f32 {
+    1.0
+}
+
+fn default_repeat_min_interval_ms() -> u64 {
+    20
+}
+
+/// Event types for action triggering (any case in configs: `press`, `Press`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventType {
+    /// Fire once when the input becomes active
+    #[default]
+    Press,
+    /// Fire once when the input is released
+    Release,
+    /// Fire on every poll while the input is active
+    Hold,
+    /// Fire whenever the input changes
+    Change,
+}
+
+impl EventType {
+    const ALL: [EventType; 4] = [
+        EventType::Press,
+        EventType::Release,
+        EventType::Hold,
+        EventType::Change,
+    ];
+    const NAMES: &'static [&'static str] = &["press", "release", "hold", "change"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventType::Press => "press",
+            EventType::Release => "release",
+            EventType::Hold => "hold",
+            EventType::Change => "change",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for EventType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        EventType::ALL
+            .into_iter()
+            .find(|event| event.name().eq_ignore_ascii_case(&name))
+            .ok_or_else(|| serde::de::Error::unknown_variant(&name, EventType::NAMES))
+    }
+}
+
+impl JsonSchema for EventType {
+    fn schema_name() -> String {
+        "EventType".to_string()
+    }
+
+    /// The lower-case names, matched ignoring case by `config validate`
+    fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        crate::schema::ignore_case_enum("Action trigger (case-insensitive)", EventType::NAMES)
+    }
+}
+
+/// WebSocket (or raw socket) message configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WebSocketMessage {
+    /// Message content (template string)
+    pub message: Template,
+
+    /// Send as binary (on `tcp` and `unix` sockets: without the newline)
+    #[serde(default)]
+    pub binary: bool,
+}
+
+/// MQTT message configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MqttMessage {
+    /// Topic (supports templates)
+    pub topic: Template,
+
+    /// Payload (supports templates)
+    #[serde(default)]
+    pub payload: Template,
+
+    /// QoS (0-2)
+    #[serde(default)]
+    #[schemars(range(max = 2))]
+    pub qos: u8,
+
+    /// Retain the message on the broker
+    #[serde(default)]
+    pub retain: bool,
+}
+
+/// Virtual keyboard key of an action
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KeyAction {
+    /// Key name: `a`, `5`, `enter`, `space`, `f5`, `up`, `volumeup`, ...
+    pub key: String,
+
+    /// Modifiers held around the key
+    #[serde(default)]
+    pub modifiers: Vec<KeyModifier>,
+
+    #[serde(default)]
+    pub mode: PressMode,
+}
+
+/// Modifier key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyModifier {
+    Ctrl,
+    Shift,
+    Alt,
+    /// Super/Windows key
+    Meta,
+}
+
+/// What a key or mouse button action does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PressMode {
+    /// Press and release
+    #[default]
+    Tap,
+    /// Press and keep it down (pair with a `release` trigger)
+    Press,
+    Release,
+}
+
+/// Virtual mouse output of an action
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MouseAction {
+    /// Button to press, release or click (per `mode`)
+    #[serde(default)]
+    pub button: Option<MouseButton>,
+
+    #[serde(default)]
+    pub mode: PressMode,
+
+    /// Wheel clicks, positive scrolls up
+    #[serde(default)]
+    pub scroll: i32,
+
+    /// Horizontal wheel clicks, positive scrolls right
+    #[serde(default)]
+    pub scroll_horizontal: i32,
+
+    /// Relative movement in pixels (positive is right)
+    #[serde(default)]
+    pub move_x: i32,
+
+    /// Relative movement in pixels (positive is down)
+    #[serde(default)]
+    pub move_y: i32,
+}
+
+/// Mouse button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    /// Back
+    Side,
+    /// Forward
+    Extra,
+}
+
+/// Continuous pointer movement
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MouseConfig {
+    pub source: PointerSource,
+
+    /// Pixels per second at full stick deflection
+    #[serde(default = "default_pointer_speed")]
+    pub speed: f32,
+
+    /// Stick deadzone (0.0 - 1.0)
+    #[serde(default = "default_pointer_deadzone")]
+    pub deadzone: f32,
+
+    /// Pixels per degree the controller turns
+    #[serde(default = "default_gyro_sensitivity")]
+    pub gyro_sensitivity: f32,
+
+    /// Gyro rates below this are ignored (degrees per second)
+    #[serde(default = "default_gyro_deadzone_dps")]
+    pub gyro_deadzone_dps: f32,
+
+    /// Curve over stick deflection, or over the gyro rate as a fraction of
+    /// 360 degrees per second. Sticks default to their `analog` curve, the
+    /// gyro to linear.
+    #[serde(default)]
+    pub curve: Option<ResponseCurve>,
+
+    #[serde(default)]
+    pub invert_x: bool,
+
+    #[serde(default)]
+    pub invert_y: bool,
+}
+
+/// Input moving the pointer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PointerSource {
+    LeftStick,
+    RightStick,
+    /// Turn left/right and tilt up/down
+    Gyro,
+}
+
+fn default_pointer_speed() -> f32 {
+    1500.0
+}
+
+fn default_pointer_deadzone() -> f32 {
+    0.1
+}
+
+fn default_gyro_sensitivity() -> f32 {
+    10.0
+}
+
+fn default_gyro_deadzone_dps() -> f32 {
+    2.0
+}
+
+/// Virtual gamepad configuration. Unset inputs keep the Xbox layout:
+/// cross A, circle B, square X, triangle Y, create Back, options Start.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GamepadConfig {
+    /// Device name games see
+    #[serde(default = "default_gamepad_name")]
+    pub name: String,
+
+    /// Gamepad button per controller button
+    #[serde(default)]
+    pub buttons: GamepadButtonMappings,
+
+    #[serde(default)]
+    pub left_stick: GamepadStick,
+
+    #[serde(default)]
+    pub right_stick: GamepadStick,
+
+    #[serde(default)]
+    pub left_trigger: GamepadTrigger,
+
+    #[serde(default)]
+    pub right_trigger: GamepadTrigger,
+
+    /// Add gyro motion to a stick
+    #[serde(default)]
+    pub gyro: Option<GyroStick>,
+}
+
+fn default_gamepad_name() -> String {
+    "Microsoft X-Box 360 pad".to_string()
+}
+
+/// Controller button to gamepad button mappings
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GamepadButtonMappings {
+    #[serde(default)]
+    pub cross: Option<GamepadButton>,
+    #[serde(default)]
+    pub circle: Option<GamepadButton>,
+    #[serde(default)]
+    pub square: Option<GamepadButton>,
+    #[serde(default)]
+    pub triangle: Option<GamepadButton>,
+    #[serde(default)]
+    pub dpad_up: Option<GamepadButton>,
+    #[serde(default)]
+    pub dpad_down: Option<GamepadButton>,
+    #[serde(default)]
+    pub dpad_left: Option<GamepadButton>,
+    #[serde(default)]
+    pub dpad_right: Option<GamepadButton>,
+    #[serde(default)]
+    pub l1: Option<GamepadButton>,
+    #[serde(default)]
+    pub r1: Option<GamepadButton>,
+    #[serde(default)]
+    pub l2_button: Option<GamepadButton>,
+    #[serde(default)]
+    pub r2_button: Option<GamepadButton>,
+    #[serde(default)]
+    pub l3: Option<GamepadButton>,
+    #[serde(default)]
+    pub r3: Option<GamepadButton>,
+    #[serde(default)]
+    pub options: Option<GamepadButton>,
+    #[serde(default)]
+    pub create: Option<GamepadButton>,
+    #[serde(default)]
+    pub ps: Option<GamepadButton>,
+    #[serde(default)]
+    pub touchpad: Option<GamepadButton>,
+    #[serde(default)]
+    pub mute: Option<GamepadButton>,
+}
+
+/// Xbox-style gamepad button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    Lb,
+    Rb,
+    Back,
+    Start,
+    Guide,
+    /// Left stick click
+    Ls,
+    /// Right stick click
+    Rs,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+    /// Not forwarded
+    None,
+}
+
+/// One gamepad stick
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GamepadStick {
+    /// Controller stick feeding it (default: the same side)
+    #[serde(default)]
+    pub source: Option<StickSide>,
+
+    /// Radial deadzone (0.0 - 1.0)
+    #[serde(default = "default_gamepad_deadzone")]
+    pub deadzone: f32,
+
+    /// Response curve (default: the stick's `analog` curve)
+    #[serde(default)]
+    pub curve: Option<ResponseCurve>,
+
+    #[serde(default)]
+    pub invert_x: bool,
+
+    #[serde(default)]
+    pub invert_y: bool,
+}
+
+impl Default for GamepadStick {
+    fn default() -> Self {
+        Self {
+            source: None,
+            deadzone: default_gamepad_deadzone(),
+            curve: None,
+            invert_x: false,
+            invert_y: false,
+        }
+    }
+}
+
+fn default_gamepad_deadzone() -> f32 {
+    0.05
+}
+
+/// One gamepad trigger
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GamepadTrigger {
+    /// Controller trigger feeding it (default: the same side)
+    #[serde(default)]
+    pub source: Option<TriggerSide>,
+
+    /// Response curve (default: the trigger's `analog` curve)
+    #[serde(default)]
+    pub curve: Option<ResponseCurve>,
+
+    /// Released reads as fully pressed
+    #[serde(default)]
+    pub invert: bool,
+}
+
+/// Left or right stick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StickSide {
+    Left,
+    #[default]
+    Right,
+}
+
+/// L2 or R2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerSide {
+    L2,
+    R2,
+}
+
+/// Gyro motion added to a gamepad stick (turn for X, tilt for Y)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GyroStick {
+    /// Gamepad stick to add to
+    #[serde(default)]
+    pub stick: StickSide,
+
+    /// Rate giving full deflection (degrees per second)
+    #[serde(default = "default_gyro_full_scale_dps")]
+    pub full_scale_dps: f32,
+
+    /// Rates below this are ignored (degrees per second)
+    #[serde(default = "default_gyro_deadzone_dps")]
+    pub deadzone_dps: f32,
+
+    /// Smallest deflection once past the deadzone, to get past the game's
+    /// own stick deadzone (0.0 - 1.0)
+    #[serde(default)]
+    pub min_deflection: f32,
+
+    /// Share of the gyro added to the stick (1.0 adds all of it)
+    #[serde(default = "default_gyro_stick_weight")]
+    pub weight: f32,
+
+    /// Response curve over the deflection
+    #[serde(default)]
+    pub curve: Option<ResponseCurve>,
+
+    #[serde(default)]
+    pub invert_x: bool,
 
-    /// Action when trigger crosses threshold
     #[serde(default)]
-    pub on_press: Option<ActionConfig>,
+    pub invert_y: bool,
+}
 
-    /// Threshold for press detection (0.0 - 1.0)
-    #[serde(default = "default_threshold")]
-    pub threshold: f32,
+fn default_gyro_full_scale_dps() -> f32 {
+    180.0
 }
 
-impl Default for TriggerMapping {
-    fn default() -> Self {
-        Self {
-            on_change: None,
-            on_press: None,
-            threshold: default_threshold(),
+fn default_gyro_stick_weight() -> f32 {
+    1.0
+}
+
+/// OSC output of an action
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum OscAction {
+    Message(OscMessageConfig),
+    Bundle(Vec<OscMessageConfig>),
+}
+
+impl OscAction {
+    pub fn messages(&self) -> &[OscMessageConfig] {
+        match self {
+            OscAction::Message(message) => std::slice::from_ref(message),
+            OscAction::Bundle(messages) => messages,
         }
     }
 }
 
-/// Motion/IMU mappings
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct MotionMappings {
-    /// Action on orientation change
-    #[serde(default)]
-    pub on_orientation_change: Option<ActionConfig>,
+/// OSC message configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct OscMessageConfig {
+    /// Address pattern, e.g. `/pad/left_stick` (supports templates)
+    pub address: Template,
 
-    /// Action on significant motion
+    /// Arguments, in order
     #[serde(default)]
-    pub on_shake: Option<ActionConfig>,
+    pub args: Vec<OscArg>,
+}
 
-    /// Shake detection threshold (in G)
-    #[serde(default = "default_shake_threshold")]
-    pub shake_threshold: f32,
+/// OSC message argument
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum OscArg {
+    /// Template context field (`left_stick_x`, `cross`, `vars.mode`); typed
+    /// by its value unless `type` is set: numbers with a fraction are
+    /// floats, whole numbers and buttons ints
+    Field {
+        field: String,
+        #[serde(default, rename = "type")]
+        kind: Option<OscType>,
+    },
+    /// Rendered template, typed as it parses unless `type` is set
+    Template {
+        value: Template,
+        #[serde(default, rename = "type")]
+        kind: Option<OscType>,
+    },
+    /// Constant
+    Literal(OscLiteral),
+}
 
-    /// Update rate for orientation in milliseconds
-    #[serde(default)]
-    pub orientation_rate_ms: u64,
+/// Constant OSC argument
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum OscLiteral {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
 }
 
-fn default_shake_threshold() -> f32 {
-    2.0
+/// OSC argument type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OscType {
+    Int,
+    Float,
+    String,
+    Bool,
 }
 
-/// Action configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ActionConfig {
-    /// When to trigger: "press", "release", "hold", "change"
-    #[serde(default = "default_trigger")]
-    pub trigger: String,
+/// HTTP request configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HttpRequest {
+    /// HTTP method
+    #[serde(default)]
+    pub method: HttpMethod,
 
-    /// Shell command to execute (supports templates)
+    /// Path (appended to base_url)
+    pub path: Template,
+
+    /// Request body (template string)
     #[serde(default)]
-    pub command: Option<String>,
+    pub body: Option<Template>,
 
-    /// WebSocket message to send (supports templates)
+    /// Additional headers
     #[serde(default)]
-    pub websocket: Option<WebSocketMessage>,
+    pub headers: HashMap<String, String>,
 
-    /// HTTP request to make
+    /// Query parameters (values support templates)
     #[serde(default)]
-    pub http: Option<HttpRequest>,
+    pub query: BTreeMap<String, Template>,
 
-    /// Rumble feedback
+    /// Request timeout in milliseconds (default: `http.timeout_ms`)
     #[serde(default)]
-    pub rumble: Option<RumbleConfig>,
+    pub timeout_ms: Option<u64>,
 
-    /// LED feedback
+    /// Retries after a connection error or 5xx response (`GET`, `PUT` and
+    /// `DELETE` only, unless `retry_non_idempotent` is set)
     #[serde(default)]
-    pub led: Option<LedColorConfig>,
+    pub retries: u32,
 
-    /// Minimum interval between triggers (debounce) in ms
+    /// Delay before the first retry in milliseconds, doubled for each further one
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+
+    /// Also retry `POST` and `PATCH`, which may take effect twice
     #[serde(default)]
-    pub debounce_ms: u64,
+    pub retry_non_idempotent: bool,
 
-    /// Only trigger if button held for this duration (ms)
+    /// Authentication (default: `http.auth`)
     #[serde(default)]
-    pub hold_time_ms: u64,
+    pub auth: Option<HttpAuth>,
+
+    /// Feedback and variables from the response
+    #[serde(default)]
+    pub response: Option<HttpResponseConfig>,
 }
 
-impl Default for ActionConfig {
+impl Default for HttpRequest {
     fn default() -> Self {
         Self {
-            trigger: default_trigger(),
-            command: None,
-            websocket: None,
-            http: None,
-            rumble: None,
-            led: None,
-            debounce_ms: 0,
-            hold_time_ms: 0,
+            method: HttpMethod::default(),
+            path: Template::default(),
+            body: None,
+            headers: HashMap::new(),
+            query: BTreeMap::new(),
+            timeout_ms: None,
+            retries: 0,
+            retry_backoff_ms: default_retry_backoff_ms(),
+            retry_non_idempotent: false,
+            auth: None,
+            response: None,
+        }
+    }
+}
+
+impl HttpRequest {
+    /// Retries to make; a request that may not be repeated safely gets none
+    pub fn allowed_retries(&self) -> u32 {
+        if self.method.is_idempotent() || self.retry_non_idempotent {
+            self.retries
+        } else {
+            0
+        }
+    }
+
+    fn check(&self, at: &str, problems: &mut Vec<ValidationError>) {
+        if self.retries > 0 && self.allowed_retries() == 0 {
+            problems.push(ValidationError::at(
+                format!("{}.retries", at),
+                format!(
+                    "{} may take effect twice; set `retry_non_idempotent` to retry it",
+                    self.method
+                ),
+            ));
         }
     }
 }
 
-fn default_trigger() -> String {
-    "press".to_string()
+fn default_retry_backoff_ms() -> u64 {
+    200
 }
 
-/// WebSocket message configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WebSocketMessage {
-    /// Message content (template string)
-    pub message: String,
+/// HTTP authentication, with credentials read from environment variables
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum HttpAuth {
+    Basic {
+        /// Variable holding the user name
+        username_env: String,
+        /// Variable holding the password
+        #[serde(default)]
+        password_env: Option<String>,
+    },
+    Bearer {
+        /// Variable holding the token
+        token_env: String,
+    },
+}
 
-    /// Send as binary
+/// What to do with an HTTP response
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HttpResponseConfig {
+    /// Feedback for a 2xx status
     #[serde(default)]
-    pub binary: bool,
+    pub on_success: Option<HttpFeedback>,
+
+    /// Feedback for a 4xx status
+    #[serde(default)]
+    pub on_client_error: Option<HttpFeedback>,
+
+    /// Feedback for a 5xx status
+    #[serde(default)]
+    pub on_server_error: Option<HttpFeedback>,
+
+    /// Feedback when no response arrived (connection error, timeout)
+    #[serde(default)]
+    pub on_failure: Option<HttpFeedback>,
+
+    /// Store the status code in this variable
+    #[serde(default)]
+    pub status_var: Option<String>,
+
+    /// Variables to fill from a 2xx JSON body: variable name to field path
+    /// (`position.x`, `items.0.id` or a JSON pointer like `/position/x`)
+    #[serde(default)]
+    pub extract: BTreeMap<String, String>,
 }
 
-/// HTTP request configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HttpRequest {
-    /// HTTP method
-    #[serde(default = "default_method")]
-    pub method: String,
+impl HttpResponseConfig {
+    /// Feedback for a response status (`None` if no response arrived)
+    pub fn feedback(&self, status: Option<u16>) -> Option<&HttpFeedback> {
+        match status {
+            None => self.on_failure.as_ref(),
+            Some(200..=299) => self.on_success.as_ref(),
+            Some(400..=499) => self.on_client_error.as_ref(),
+            Some(500..=599) => self.on_server_error.as_ref(),
+            Some(_) => None,
+        }
+    }
+}
 
-    /// Path (appended to base_url)
-    pub path: String,
+/// Controller feedback for an HTTP response
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HttpFeedback {
+    #[serde(default)]
+    pub led: Option<LedColorConfig>,
 
-    /// Request body (template string)
     #[serde(default)]
-    pub body: Option<String>,
+    pub led_blink: Option<LedBlinkConfig>,
 
-    /// Additional headers
     #[serde(default)]
-    pub headers: HashMap<String, String>,
+    pub rumble: Option<RumbleConfig>,
+}
+
+/// HTTP request method (any case in configs: `GET`, `get`, `Get`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    Get,
+    #[default]
+    Post,
+    Put,
+    Delete,
+    Patch,
+}
+
+impl HttpMethod {
+    const ALL: [HttpMethod; 5] = [
+        HttpMethod::Get,
+        HttpMethod::Post,
+        HttpMethod::Put,
+        HttpMethod::Delete,
+        HttpMethod::Patch,
+    ];
+    const NAMES: &'static [&'static str] = &["GET", "POST", "PUT", "DELETE", "PATCH"];
+
+    /// Whether sending the request twice has the same effect as once
+    pub fn is_idempotent(self) -> bool {
+        matches!(self, HttpMethod::Get | HttpMethod::Put | HttpMethod::Delete)
+    }
+}
+
+impl<'de> Deserialize<'de> for HttpMethod {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        HttpMethod::ALL
+            .into_iter()
+            .find(|method| method.to_string().eq_ignore_ascii_case(&name))
+            .ok_or_else(|| serde::de::Error::unknown_variant(&name, HttpMethod::NAMES))
+    }
+}
+
+impl JsonSchema for HttpMethod {
+    fn schema_name() -> String {
+        "HttpMethod".to_string()
+    }
+
+    /// The upper-case names, matched ignoring case by `config validate`
+    fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        crate::schema::ignore_case_enum("HTTP request method (case-insensitive)", HttpMethod::NAMES)
+    }
 }
 
-fn default_method() -> String {
-    "POST".to_string()
+impl std::fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Delete => "DELETE",
+            HttpMethod::Patch => "PATCH",
+        })
+    }
 }
 
 /// Rumble configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct RumbleConfig {
     /// Left motor intensity (0-255)
     pub left: u8,
@@ -484,15 +2530,40 @@ pub struct RumbleConfig {
 }
 
 /// LED color configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct LedColorConfig {
     pub r: u8,
     pub g: u8,
     pub b: u8,
 }
 
+/// LED blink configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LedBlinkConfig {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// Number of blinks
+    #[serde(default = "default_blink_count")]
+    pub count: u32,
+    /// On/off interval in milliseconds
+    #[serde(default = "default_blink_interval")]
+    pub interval_ms: u64,
+}
+
+fn default_blink_count() -> u32 {
+    3
+}
+
+fn default_blink_interval() -> u64 {
+    250
+}
+
 /// LED configuration
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct LedConfig {
     /// Default/idle color
     #[serde(default)]
@@ -507,6 +2578,106 @@ pub struct LedConfig {
     pub error_color: Option<LedColorConfig>,
 }
 
+/// Battery and connection lifecycle hooks
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LifecycleConfig {
+    /// Controller connected at startup
+    #[serde(default)]
+    pub on_connect: Option<ActionConfig>,
+
+    /// Controller connection lost
+    #[serde(default)]
+    pub on_disconnect: Option<ActionConfig>,
+
+    /// Controller connected again after a disconnect
+    #[serde(default)]
+    pub on_reconnect: Option<ActionConfig>,
+
+    /// Battery dropped to `low_battery_percent`
+    #[serde(default)]
+    pub on_low_battery: Option<ActionConfig>,
+
+    #[serde(default)]
+    pub on_charging_start: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_charging_stop: Option<ActionConfig>,
+    #[serde(default)]
+    pub on_fully_charged: Option<ActionConfig>,
+
+    /// Low battery threshold in percent
+    #[serde(default = "default_low_battery_percent")]
+    pub low_battery_percent: u8,
+
+    /// Battery must climb this many percent above the threshold to re-arm
+    #[serde(default = "default_battery_hysteresis")]
+    pub battery_hysteresis_percent: u8,
+
+    /// Try to reconnect when the controller is lost (otherwise exit)
+    #[serde(default = "default_true")]
+    pub reconnect: bool,
+
+    /// Delay between reconnect attempts in milliseconds
+    #[serde(default = "default_reconnect_delay")]
+    pub reconnect_delay_ms: u64,
+}
+
+impl Default for LifecycleConfig {
+    fn default() -> Self {
+        Self {
+            on_connect: None,
+            on_disconnect: None,
+            on_reconnect: None,
+            on_low_battery: None,
+            on_charging_start: None,
+            on_charging_stop: None,
+            on_fully_charged: None,
+            low_battery_percent: default_low_battery_percent(),
+            battery_hysteresis_percent: default_battery_hysteresis(),
+            reconnect: true,
+            reconnect_delay_ms: default_reconnect_delay(),
+        }
+    }
+}
+
+impl LifecycleConfig {
+    /// Mapped actions by field name
+    pub fn actions(&self) -> impl Iterator<Item = (&'static str, &ActionConfig)> + '_ {
+        mapped([
+            ("on_connect", &self.on_connect),
+            ("on_disconnect", &self.on_disconnect),
+            ("on_reconnect", &self.on_reconnect),
+            ("on_low_battery", &self.on_low_battery),
+            ("on_charging_start", &self.on_charging_start),
+            ("on_charging_stop", &self.on_charging_stop),
+            ("on_fully_charged", &self.on_fully_charged),
+        ])
+    }
+
+    /// Get the action mapped to a lifecycle event
+    pub fn event_action(&self, event: crate::lifecycle::LifecycleEvent) -> Option<&ActionConfig> {
+        use crate::lifecycle::LifecycleEvent;
+
+        match event {
+            LifecycleEvent::Connect => self.on_connect.as_ref(),
+            LifecycleEvent::Disconnect => self.on_disconnect.as_ref(),
+            LifecycleEvent::Reconnect => self.on_reconnect.as_ref(),
+            LifecycleEvent::LowBattery => self.on_low_battery.as_ref(),
+            LifecycleEvent::ChargingStart => self.on_charging_start.as_ref(),
+            LifecycleEvent::ChargingStop => self.on_charging_stop.as_ref(),
+            LifecycleEvent::FullyCharged => self.on_fully_charged.as_ref(),
+        }
+    }
+}
+
+fn default_low_battery_percent() -> u8 {
+    20
+}
+
+fn default_battery_hysteresis() -> u8 {
+    10
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -516,54 +2687,305 @@ impl Default for Config {
             shell: ShellConfig::default(),
             websocket: None,
             http: None,
+            osc: None,
+            mqtt: None,
+            sockets: BTreeMap::new(),
+            midi: None,
+            mouse: None,
+            gamepad: None,
             buttons: ButtonMappings::default(),
             analog: AnalogMappings::default(),
             motion: MotionMappings::default(),
+            touchpad: TouchpadMappings::default(),
             led: LedConfig::default(),
+            lifecycle: LifecycleConfig::default(),
+            variables: VariablesConfig::default(),
+            pipeline: PipelineConfig::default(),
             integration: None,
         }
     }
 }
 
 impl Config {
-    /// Load configuration from a JSON file
+    /// Load configuration from a JSON, TOML or YAML file, resolving `extends` and `include`
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
-        let content = fs::read_to_string(path)
-            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
-
-        let config: Config = serde_json::from_str(&content)
-            .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
-
-        Ok(config)
+        Self::from_resolved(compose::resolve_file(path)?)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))
     }
 
-    /// Load configuration from a directory (merges all JSON files)
+    /// Load configuration from a directory (merges all config files, `config.*` first)
     pub fn load_dir<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
-        let mut config = Config::default();
+        Self::from_resolved(compose::resolve_path(path)?)
+            .with_context(|| format!("Failed to load config from {}", path.display()))
+    }
+
+    /// Build a configuration from a resolved (merged) document
+    pub fn from_resolved(resolved: ResolvedConfig) -> Result<Self> {
+        let problems = crate::schema::check_document(&resolved.value);
+        if !problems.is_empty() {
+            let lines: Vec<String> = problems.iter().map(ToString::to_string).collect();
+            bail!("{}", lines.join("\n"));
+        }
+        Ok(serde_json::from_value(resolved.value)?)
+    }
 
-        if !path.is_dir() {
-            return Self::load(path);
+    /// Every mapped action with its JSON path
+    pub fn actions(&self) -> Vec<(String, &ActionConfig)> {
+        let mut actions: Vec<(String, &ActionConfig)> = Vec::new();
+        let mut add =
+            |at: &str, name: &str, action| actions.push((format!("{}.{}", at, name), action));
+
+        for (name, action) in self.buttons.actions() {
+            add("$.buttons", name, action);
+        }
+        for (side, stick) in [
+            ("left_stick", &self.analog.left_stick),
+            ("right_stick", &self.analog.right_stick),
+        ] {
+            for (name, action) in stick.iter().flat_map(StickMapping::actions) {
+                add(&format!("$.analog.{}", side), name, action);
+            }
+        }
+        for (side, trigger) in [
+            ("l2_trigger", &self.analog.l2_trigger),
+            ("r2_trigger", &self.analog.r2_trigger),
+        ] {
+            let Some(trigger) = trigger else { continue };
+            for (name, action) in trigger.actions() {
+                add(&format!("$.analog.{}", side), name, action);
+            }
+            for (i, zone) in trigger.zones.iter().enumerate() {
+                let at = format!("$.analog.{}.zones[{}]", side, i);
+                let zone_actions = [("on_enter", &zone.on_enter), ("on_leave", &zone.on_leave)];
+                for (name, action) in mapped(zone_actions) {
+                    add(&at, name, action);
+                }
+            }
+        }
+        for (name, action) in self.motion.actions() {
+            add("$.motion", name, action);
+        }
+        for (name, action) in self.touchpad.actions() {
+            add("$.touchpad", name, action);
+        }
+        for (i, region) in self.touchpad.regions.iter().enumerate() {
+            add(&format!("$.touchpad.regions[{}]", i), "action", &region.action);
         }
+        for (name, action) in self.lifecycle.actions() {
+            add("$.lifecycle", name, action);
+        }
+        actions
+    }
 
-        // Look for main config file
-        let main_config = path.join("config.json");
-        if main_config.exists() {
-            config = Self::load(&main_config)?;
+    /// Settings that parse but can't work together
+    pub fn problems(&self) -> Vec<ValidationError> {
+        let mut problems = Vec::new();
+        for (at, action) in self.actions() {
+            action.check(&at, &mut problems);
+        }
+        for (name, stick) in [
+            ("left_stick", &self.analog.left_stick),
+            ("right_stick", &self.analog.right_stick),
+        ] {
+            if let Some(stick) = stick {
+                stick.check(&format!("$.analog.{}", name), &mut problems);
+            }
+        }
+        for (name, trigger) in [
+            ("l2_trigger", &self.analog.l2_trigger),
+            ("r2_trigger", &self.analog.r2_trigger),
+        ] {
+            if let Some(trigger) = trigger {
+                trigger.check(&format!("$.analog.{}", name), &mut problems);
+            }
+        }
+        if let Some(midi) = &self.midi {
+            midi.check(&mut problems);
         }
 
-        Ok(config)
+        // A deadzone of 1.0 or more leaves no travel to rescale into
+        let mut deadzones = vec![("$.deadzone", self.deadzone)];
+        if let Some(mouse) = &self.mouse {
+            deadzones.push(("$.mouse.deadzone", mouse.deadzone));
+        }
+        if let Some(gamepad) = &self.gamepad {
+            deadzones.push(("$.gamepad.left_stick.deadzone", gamepad.left_stick.deadzone));
+            deadzones.push((
+                "$.gamepad.right_stick.deadzone",
+                gamepad.right_stick.deadzone,
+            ));
+        }
+        for (at, deadzone) in deadzones {
+            if !(0.0..1.0).contains(&deadzone) {
+                problems.push(ValidationError::at(
+                    at,
+                    format!("{} must be at least 0.0 and below 1.0", deadzone),
+                ));
+            }
+        }
+        problems
     }
 
-    /// Save configuration to a JSON file
+    /// Save configuration, picking JSON, TOML or YAML from the extension
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let content = serde_json::to_string_pretty(self)?;
+        let path = path.as_ref();
+        let content = FileFormat::from_path(path)?.to_string(self)?;
         fs::write(path, content)?;
         Ok(())
     }
 }
 
+/// Action queue settings
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    /// Jobs the queue holds before the overflow policy applies
+    #[serde(default = "default_queue_size")]
+    pub queue_size: usize,
+
+    /// What to do when the queue is full
+    #[serde(default)]
+    pub overflow: OverflowPolicy,
+
+    /// Jobs of different inputs and streams that may run at once (jobs of
+    /// one input always run in order)
+    #[serde(default = "default_workers")]
+    #[schemars(range(min = 1))]
+    pub workers: usize,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            queue_size: default_queue_size(),
+            overflow: OverflowPolicy::default(),
+            workers: default_workers(),
+        }
+    }
+}
+
+fn default_queue_size() -> usize {
+    256
+}
+
+fn default_workers() -> usize {
+    4
+}
+
+/// Runtime variable settings
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct VariablesConfig {
+    /// Initial values
+    #[serde(default)]
+    pub initial: BTreeMap<String, serde_json::Value>,
+
+    /// JSON file to persist values in between runs
+    #[serde(default)]
+    pub persist: Option<String>,
+}
+
+/// Variable update applied by an action
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct VarOp {
+    /// Variable name
+    pub name: String,
+
+    /// Operation to apply
+    pub op: VarOpKind,
+
+    /// Value for `set` (strings are rendered as templates)
+    #[serde(default)]
+    pub value: Option<VarValue>,
+
+    /// Step for `inc`/`dec`
+    #[serde(default = "default_var_step")]
+    pub by: f64,
+
+    /// Lower bound for `inc`/`dec`
+    #[serde(default)]
+    pub min: Option<f64>,
+
+    /// Upper bound for `inc`/`dec`
+    #[serde(default)]
+    pub max: Option<f64>,
+
+    /// Wrap around between `min` and `max` instead of clamping
+    #[serde(default)]
+    pub wrap: bool,
+
+    /// Values for `cycle`
+    #[serde(default)]
+    pub values: Vec<serde_json::Value>,
+}
+
+/// Value of a `set` operation
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum VarValue {
+    /// Rendered, then typed as it parses (`"{{mul x 2}}"` stores a number)
+    Template(Template),
+    /// Stored as-is
+    Value(serde_json::Value),
+}
+
+/// Variable operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VarOpKind {
+    Set,
+    #[serde(rename = "inc", alias = "increment")]
+    Increment,
+    #[serde(rename = "dec", alias = "decrement")]
+    Decrement,
+    Toggle,
+    Cycle,
+}
+
+impl JsonSchema for VarOpKind {
+    fn schema_name() -> String {
+        "VarOpKind".to_string()
+    }
+
+    /// Lists the long aliases too, which the derive leaves out
+    fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        serde_json::from_value(serde_json::json!({
+            "description": "Variable operation",
+            "type": "string",
+            "enum": ["set", "inc", "increment", "dec", "decrement", "toggle", "cycle"]
+        }))
+        .expect("valid schema")
+    }
+}
+
+fn default_var_step() -> f64 {
+    1.0
+}
+
+/// Condition on a variable; every comparison given must hold.
+/// With no comparisons the variable must be truthy.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct VarCondition {
+    /// Variable name
+    pub var: String,
+    #[serde(default)]
+    pub eq: Option<serde_json::Value>,
+    #[serde(default)]
+    pub ne: Option<serde_json::Value>,
+    #[serde(default)]
+    pub gt: Option<f64>,
+    #[serde(default)]
+    pub gte: Option<f64>,
+    #[serde(default)]
+    pub lt: Option<f64>,
+    #[serde(default)]
+    pub lte: Option<f64>,
+}
+
 /// Template context for action commands
 #[derive(Debug, Clone, Serialize)]
 pub struct TemplateContext {
@@ -580,6 +3002,13 @@ pub struct TemplateContext {
     pub dpad_down: bool,
     pub dpad_left: bool,
     pub dpad_right: bool,
+    pub l3: bool,
+    pub r3: bool,
+    pub options: bool,
+    pub create: bool,
+    pub ps: bool,
+    pub touchpad: bool,
+    pub mute: bool,
 
     // Analog values (normalized -1.0 to 1.0 for sticks, 0.0 to 1.0 for triggers)
     pub left_stick_x: f32,
@@ -589,6 +3018,12 @@ pub struct TemplateContext {
     pub l2_trigger: f32,
     pub r2_trigger: f32,
 
+    // Stick magnitude (0.0 to 1.0) and angle (degrees, 0 = right, 90 = up)
+    pub left_stick_magnitude: f32,
+    pub left_stick_angle: f32,
+    pub right_stick_magnitude: f32,
+    pub right_stick_angle: f32,
+
     // Orientation (quaternion) - from spatial integration
     pub quat_w: f32,
     pub quat_x: f32,
@@ -600,6 +3035,11 @@ pub struct TemplateContext {
     pub pitch: f32,
     pub yaw: f32,
 
+    // Euler angles (degrees)
+    pub roll_deg: f32,
+    pub pitch_deg: f32,
+    pub yaw_deg: f32,
+
     // Gyroscope (rad/s)
     pub gyro_x: f32,
     pub gyro_y: f32,
@@ -613,18 +3053,37 @@ pub struct TemplateContext {
     // Battery
     pub battery_percent: u8,
     pub battery_charging: bool,
+    pub battery_fully_charged: bool,
 
     // Touchpad
     pub touch1_active: bool,
+    pub touch1_id: u8,
     pub touch1_x: u16,
     pub touch1_y: u16,
     pub touch2_active: bool,
+    pub touch2_id: u8,
     pub touch2_x: u16,
     pub touch2_y: u16,
 
     // Timestamp
     pub timestamp: u32,
 
+    // Host time (ms, monotonic since the process started)
+    pub host_time_ms: u64,
+
+    // === Event that fired the action (empty for state updates) ===
+
+    // Input name (e.g. `cross`, `left_stick`, `touchpad_region_volume`)
+    pub event_source: String,
+    // What happened (`press`, `release`, `hold`, `change`, `up`, `swipe_left`, ...)
+    pub event_type: String,
+    // How long the input has been held (ms, digital inputs only)
+    pub hold_ms: u64,
+    // Times the action already fired during the current hold
+    pub repeat_count: u32,
+    // Time since the same input last fired (ms, 0 on the first fire)
+    pub since_last_ms: u64,
+
     // === Spatial state (integrated) ===
 
     // Position in mm
@@ -649,6 +3108,9 @@ pub struct TemplateContext {
 
     // Buttons as JSON string for WebSocket messages
     pub buttons_json: String,
+
+    // Runtime variables
+    pub vars: serde_json::Map<String, serde_json::Value>,
 }
 
 impl From<&crate::dualsense::ControllerState> for TemplateContext {
@@ -658,6 +3120,33 @@ impl From<&crate::dualsense::ControllerState> for TemplateContext {
 }
 
 impl TemplateContext {
+    /// Zero stick values inside the deadzone, the same way the stick mappings see them
+    pub fn apply_deadzone(&mut self, deadzone: f32) {
+        (self.left_stick_x, self.left_stick_y) =
+            crate::dualsense::radial_deadzone((self.left_stick_x, self.left_stick_y), deadzone);
+        (self.right_stick_x, self.right_stick_y) =
+            crate::dualsense::radial_deadzone((self.right_stick_x, self.right_stick_y), deadzone);
+        (self.left_stick_magnitude, self.left_stick_angle) =
+            polar(self.left_stick_x, self.left_stick_y);
+        (self.right_stick_magnitude, self.right_stick_angle) =
+            polar(self.right_stick_x, self.right_stick_y);
+    }
+
+    /// Reshape stick and trigger values (and the derived stick values)
+    pub fn apply_curves(&mut self, curves: &AxisCurves) {
+        let linear = ResponseCurve::default();
+        (self.left_stick_x, self.left_stick_y) =
+            curves.left_stick((self.left_stick_x, self.left_stick_y), &linear);
+        (self.right_stick_x, self.right_stick_y) =
+            curves.right_stick((self.right_stick_x, self.right_stick_y), &linear);
+        (self.l2_trigger, self.r2_trigger) =
+            curves.triggers((self.l2_trigger, self.r2_trigger), &linear);
+        (self.left_stick_magnitude, self.left_stick_angle) =
+            polar(self.left_stick_x, self.left_stick_y);
+        (self.right_stick_magnitude, self.right_stick_angle) =
+            polar(self.right_stick_x, self.right_stick_y);
+    }
+
     /// Create a template context from controller state and optional spatial state
     pub fn from_controller(
         state: &crate::dualsense::ControllerState,
@@ -668,6 +3157,8 @@ impl TemplateContext {
         let (l2, r2) = state.triggers.normalized();
         let gyro = state.gyroscope.to_rad_per_sec();
         let accel = state.accelerometer.to_g();
+        let (left_stick_magnitude, left_stick_angle) = polar(lx, ly);
+        let (right_stick_magnitude, right_stick_angle) = polar(rx, ry);
 
         // Use spatial orientation if available, otherwise fall back to controller's
         let (quat_w, quat_x, quat_y, quat_z, roll, pitch, yaw) = if let Some(spatial) = spatial {
@@ -753,6 +3244,13 @@ impl TemplateContext {
             dpad_down: state.buttons.dpad_down,
             dpad_left: state.buttons.dpad_left,
             dpad_right: state.buttons.dpad_right,
+            l3: state.buttons.l3,
+            r3: state.buttons.r3,
+            options: state.buttons.options,
+            create: state.buttons.create,
+            ps: state.buttons.ps,
+            touchpad: state.buttons.touchpad,
+            mute: state.buttons.mute,
 
             left_stick_x: lx,
             left_stick_y: ly,
@@ -761,6 +3259,11 @@ impl TemplateContext {
             l2_trigger: l2,
             r2_trigger: r2,
 
+            left_stick_magnitude,
+            left_stick_angle,
+            right_stick_magnitude,
+            right_stick_angle,
+
             quat_w,
             quat_x,
             quat_y,
@@ -770,6 +3273,10 @@ impl TemplateContext {
             pitch,
             yaw,
 
+            roll_deg: roll.to_degrees(),
+            pitch_deg: pitch.to_degrees(),
+            yaw_deg: yaw.to_degrees(),
+
             gyro_x: gyro.x,
             gyro_y: gyro.y,
             gyro_z: gyro.z,
@@ -780,15 +3287,25 @@ impl TemplateContext {
 
             battery_percent: state.battery.percentage(),
             battery_charging: state.battery.charging,
+            battery_fully_charged: state.battery.fully_charged,
 
             touch1_active: state.touchpad.finger1.active,
+            touch1_id: state.touchpad.finger1.id,
             touch1_x: state.touchpad.finger1.x,
             touch1_y: state.touchpad.finger1.y,
             touch2_active: state.touchpad.finger2.active,
+            touch2_id: state.touchpad.finger2.id,
             touch2_x: state.touchpad.finger2.x,
             touch2_y: state.touchpad.finger2.y,
 
             timestamp: state.timestamp,
+            host_time_ms: host_time_ms(),
+
+            event_source: String::new(),
+            event_type: String::new(),
+            hold_ms: 0,
+            repeat_count: 0,
+            since_last_ms: 0,
 
             // Spatial state
             pos_x,
@@ -804,6 +3321,20 @@ impl TemplateContext {
             linacc_y,
             linacc_z,
             buttons_json,
+            vars: serde_json::Map::new(),
         }
     }
 }
+
+/// Milliseconds since the first call, from a monotonic clock
+pub fn host_time_ms() -> u64 {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START.get_or_init(std::time::Instant::now).elapsed().as_millis() as u64
+}
+
+/// Stick position as (magnitude, angle in degrees); Y is inverted so up is 90
+fn polar(x: f32, y: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt().min(1.0);
+    let angle = (-y).atan2(x).to_degrees().rem_euclid(360.0);
+    (magnitude, angle)
+}
@@ -0,0 +1,271 @@
+//! Response curves for analog inputs
+//!
+//! A curve reshapes the magnitude of a normalized stick axis (-1.0 to 1.0)
+//! or trigger (0.0 to 1.0); the sign is kept. The same curves are used for
+//! template values, analog mappings and spatial integration.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Response curve, either a preset name or a parameterised shape
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ResponseCurve {
+    /// `"linear"`, `"quadratic"` or `"cubic"`
+    Preset(CurvePreset),
+    /// `{"type": "exponential", "expo": 0.5}` and the other shapes
+    Shape(CurveShape),
+}
+
+impl Default for ResponseCurve {
+    fn default() -> Self {
+        Self::Preset(CurvePreset::Linear)
+    }
+}
+
+/// Named curves (any case in configs: `cubic`, `Cubic`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CurvePreset {
+    #[default]
+    Linear,
+    Quadratic,
+    Cubic,
+}
+
+impl CurvePreset {
+    const ALL: [CurvePreset; 3] = [
+        CurvePreset::Linear,
+        CurvePreset::Quadratic,
+        CurvePreset::Cubic,
+    ];
+    const NAMES: &'static [&'static str] = &["linear", "quadratic", "cubic"];
+}
+
+impl<'de> Deserialize<'de> for CurvePreset {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        CurvePreset::ALL
+            .into_iter()
+            .zip(CurvePreset::NAMES)
+            .find(|(_, preset)| preset.eq_ignore_ascii_case(&name))
+            .map(|(preset, _)| preset)
+            .ok_or_else(|| serde::de::Error::unknown_variant(&name, CurvePreset::NAMES))
+    }
+}
+
+impl JsonSchema for CurvePreset {
+    fn schema_name() -> String {
+        "CurvePreset".to_string()
+    }
+
+    /// The lower-case names, matched ignoring case by `config validate`
+    fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        crate::schema::ignore_case_enum("Named curve (case-insensitive)", CurvePreset::NAMES)
+    }
+}
+
+/// Parameterised curves
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum CurveShape {
+    /// `x^exponent`; above 1 gives finer control near center
+    Power {
+        #[schemars(range(min = 0.0))]
+        exponent: f32,
+    },
+    /// RC-style expo: blends linear (0.0) with cubic (1.0)
+    Exponential {
+        #[schemars(range(min = 0.0, max = 1.0))]
+        expo: f32,
+    },
+    /// Slow near center and at the ends, fast in between (0.0 is linear)
+    SCurve {
+        #[serde(default = "default_strength")]
+        #[schemars(range(min = 0.0, max = 1.0))]
+        strength: f32,
+    },
+    /// Piecewise linear through `[input, output]` points (a lookup table)
+    Points {
+        #[serde(deserialize_with = "sorted_points")]
+        points: Vec<[f32; 2]>,
+    },
+}
+
+fn default_strength() -> f32 {
+    1.0
+}
+
+/// Points sorted by input once at load, so applying the curve doesn't allocate
+fn sorted_points<'de, D>(deserializer: D) -> Result<Vec<[f32; 2]>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut points = Vec::<[f32; 2]>::deserialize(deserializer)?;
+    points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+    Ok(points)
+}
+
+impl ResponseCurve {
+    /// Apply the curve to a normalized input, keeping its sign
+    pub fn apply(&self, input: f32) -> f32 {
+        let sign = input.signum();
+        let x = input.abs().min(1.0);
+        let curved = match self {
+            ResponseCurve::Preset(CurvePreset::Linear) => x,
+            ResponseCurve::Preset(CurvePreset::Quadratic) => x * x,
+            ResponseCurve::Preset(CurvePreset::Cubic) => x * x * x,
+            ResponseCurve::Shape(CurveShape::Power { exponent }) => x.powf(*exponent),
+            ResponseCurve::Shape(CurveShape::Exponential { expo }) => {
+                (1.0 - expo) * x + expo * x * x * x
+            }
+            ResponseCurve::Shape(CurveShape::SCurve { strength }) => {
+                let smooth = x * x * (3.0 - 2.0 * x);
+                (1.0 - strength) * x + strength * smooth
+            }
+            ResponseCurve::Shape(CurveShape::Points { points }) => interpolate(points, x),
+        };
+        if input == 0.0 {
+            0.0
+        } else {
+            sign * curved
+        }
+    }
+}
+
+/// Linear interpolation through points sorted by input; flat outside them
+fn interpolate(points: &[[f32; 2]], x: f32) -> f32 {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return x;
+    };
+    if x <= first[0] {
+        return first[1];
+    }
+    if x >= last[0] {
+        return last[1];
+    }
+    for pair in points.windows(2) {
+        let ([x0, y0], [x1, y1]) = (pair[0], pair[1]);
+        if x <= x1 {
+            if x1 == x0 {
+                return y1;
+            }
+            return y0 + (y1 - y0) * (x - x0) / (x1 - x0);
+        }
+    }
+    last[1]
+}
+
+/// Curves per analog axis; unset axes use a fallback curve
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AxisCurves {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub left_stick_x: Option<ResponseCurve>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub left_stick_y: Option<ResponseCurve>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub right_stick_x: Option<ResponseCurve>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub right_stick_y: Option<ResponseCurve>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l2_trigger: Option<ResponseCurve>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r2_trigger: Option<ResponseCurve>,
+}
+
+impl AxisCurves {
+    /// Curve normalized left stick values
+    pub fn left_stick(&self, (x, y): (f32, f32), fallback: &ResponseCurve) -> (f32, f32) {
+        (
+            curve_or(&self.left_stick_x, fallback, x),
+            curve_or(&self.left_stick_y, fallback, y),
+        )
+    }
+
+    /// Curve normalized right stick values
+    pub fn right_stick(&self, (x, y): (f32, f32), fallback: &ResponseCurve) -> (f32, f32) {
+        (
+            curve_or(&self.right_stick_x, fallback, x),
+            curve_or(&self.right_stick_y, fallback, y),
+        )
+    }
+
+    /// Curve normalized trigger values
+    pub fn triggers(&self, (l2, r2): (f32, f32), fallback: &ResponseCurve) -> (f32, f32) {
+        (
+            curve_or(&self.l2_trigger, fallback, l2),
+            curve_or(&self.r2_trigger, fallback, r2),
+        )
+    }
+}
+
+fn curve_or(curve: &Option<ResponseCurve>, fallback: &ResponseCurve, value: f32) -> f32 {
+    curve.as_ref().unwrap_or(fallback).apply(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn test_presets() {
+        let linear = ResponseCurve::default();
+        assert!(close(linear.apply(0.5), 0.5));
+        assert!(close(linear.apply(-0.5), -0.5));
+
+        let cubic = ResponseCurve::Preset(CurvePreset::Cubic);
+        assert!(close(cubic.apply(0.5), 0.125));
+        assert!(close(cubic.apply(-0.5), -0.125));
+    }
+
+    #[test]
+    fn test_shapes() {
+        let power = ResponseCurve::Shape(CurveShape::Power { exponent: 2.0 });
+        assert!(close(power.apply(-0.5), -0.25));
+
+        let expo = ResponseCurve::Shape(CurveShape::Exponential { expo: 0.5 });
+        assert!(close(expo.apply(0.5), 0.25 + 0.0625));
+        assert!(close(expo.apply(1.0), 1.0));
+
+        let s = ResponseCurve::Shape(CurveShape::SCurve { strength: 1.0 });
+        assert!(close(s.apply(0.5), 0.5));
+        assert!(s.apply(0.25) < 0.25);
+        assert!(s.apply(0.75) > 0.75);
+    }
+
+    #[test]
+    fn test_points() {
+        let lut: ResponseCurve = serde_json::from_value(serde_json::json!({
+            "type": "points",
+            "points": [[1.0, 1.0], [0.0, 0.0], [0.5, 0.2]]
+        }))
+        .unwrap();
+        assert!(close(lut.apply(0.25), 0.1));
+        assert!(close(lut.apply(-0.75), -0.6));
+        assert!(close(lut.apply(1.0), 1.0));
+    }
+
+    #[test]
+    fn test_parse() {
+        let preset: ResponseCurve = serde_json::from_str(r#""quadratic""#).unwrap();
+        assert_eq!(preset, ResponseCurve::Preset(CurvePreset::Quadratic));
+        let preset: ResponseCurve = serde_json::from_str(r#""Cubic""#).unwrap();
+        assert_eq!(preset, ResponseCurve::Preset(CurvePreset::Cubic));
+
+        let shape: ResponseCurve = serde_json::from_str(r#"{"type": "s_curve"}"#).unwrap();
+        assert_eq!(shape, ResponseCurve::Shape(CurveShape::SCurve { strength: 1.0 }));
+
+        let curves: AxisCurves =
+            serde_json::from_str(r#"{"l2_trigger": {"type": "power", "exponent": 1.5}}"#)
+                .unwrap();
+        let fallback = ResponseCurve::Preset(CurvePreset::Quadratic);
+        let (l2, r2) = curves.triggers((0.25, 0.5), &fallback);
+        assert!(close(l2, 0.125));
+        assert!(close(r2, 0.25));
+    }
+}
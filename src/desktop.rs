@@ -0,0 +1,332 @@
+//! Virtual keyboard and mouse
+//!
+//! Turns `key` and `mouse` actions into input event frames, and moves the
+//! pointer from a stick or the gyro. Frames go to an [`EventSink`]: the
+//! uinput device when running, a recorder in tests.
+
+use anyhow::{Context, Result};
+
+use crate::config::{
+    KeyAction, KeyModifier, MouseAction, MouseButton, MouseConfig, PointerSource, PressMode,
+};
+use crate::curve::{AxisCurves, ResponseCurve};
+use crate::dualsense::ControllerState;
+use crate::uinput::{codes, key_code, EventSink, Frame, InputEvent};
+
+/// Gyro rate a `curve` input of 1.0 stands for (degrees per second)
+const GYRO_FULL_SCALE_DPS: f32 = 360.0;
+
+/// Longest poll gap turned into movement, so a stall doesn't jump the
+/// pointer across the screen
+const MAX_POINTER_DT: f32 = 0.1;
+
+fn down(code: u16) -> InputEvent {
+    InputEvent::Key {
+        code,
+        pressed: true,
+    }
+}
+
+fn up(code: u16) -> InputEvent {
+    InputEvent::Key {
+        code,
+        pressed: false,
+    }
+}
+
+fn modifier_code(modifier: &KeyModifier) -> u16 {
+    match modifier {
+        KeyModifier::Ctrl => codes::KEY_LEFTCTRL,
+        KeyModifier::Shift => codes::KEY_LEFTSHIFT,
+        KeyModifier::Alt => codes::KEY_LEFTALT,
+        KeyModifier::Meta => codes::KEY_LEFTMETA,
+    }
+}
+
+/// Frames of a key action. Modifiers go down in a frame before the key
+/// and up in one after it, so applications see them held.
+pub fn key_frames(action: &KeyAction) -> Result<Vec<Frame>> {
+    let code = key_code(&action.key).with_context(|| format!("Unknown key `{}`", action.key))?;
+    let modifiers: Vec<u16> = action.modifiers.iter().map(modifier_code).collect();
+
+    let mut frames = Vec::new();
+    if action.mode != PressMode::Release {
+        if !modifiers.is_empty() {
+            frames.push(modifiers.iter().copied().map(down).collect());
+        }
+        frames.push(vec![down(code)]);
+    }
+    if action.mode != PressMode::Press {
+        frames.push(vec![up(code)]);
+        if !modifiers.is_empty() {
+            frames.push(modifiers.iter().rev().copied().map(up).collect());
+        }
+    }
+    Ok(frames)
+}
+
+fn button_code(button: MouseButton) -> u16 {
+    match button {
+        MouseButton::Left => codes::BTN_LEFT,
+        MouseButton::Right => codes::BTN_RIGHT,
+        MouseButton::Middle => codes::BTN_MIDDLE,
+        MouseButton::Side => codes::BTN_SIDE,
+        MouseButton::Extra => codes::BTN_EXTRA,
+    }
+}
+
+/// Frames of a mouse action: movement, then the button, then the wheel
+pub fn mouse_frames(action: &MouseAction) -> Vec<Frame> {
+    let mut frames = Vec::new();
+    if let Some(frame) = pointer_frame(action.move_x, action.move_y) {
+        frames.push(frame);
+    }
+
+    if let Some(button) = action.button {
+        let code = button_code(button);
+        if action.mode != PressMode::Release {
+            frames.push(vec![down(code)]);
+        }
+        if action.mode != PressMode::Press {
+            frames.push(vec![up(code)]);
+        }
+    }
+
+    let wheel: Frame = [
+        (codes::REL_WHEEL, action.scroll),
+        (codes::REL_HWHEEL, action.scroll_horizontal),
+    ]
+    .into_iter()
+    .filter(|(_, value)| *value != 0)
+    .map(|(code, value)| InputEvent::Relative { code, value })
+    .collect();
+    if !wheel.is_empty() {
+        frames.push(wheel);
+    }
+    frames
+}
+
+/// Relative pointer movement, or `None` for no movement
+pub fn pointer_frame(dx: i32, dy: i32) -> Option<Frame> {
+    let frame: Frame = [(codes::REL_X, dx), (codes::REL_Y, dy)]
+        .into_iter()
+        .filter(|(_, value)| *value != 0)
+        .map(|(code, value)| InputEvent::Relative { code, value })
+        .collect();
+    (!frame.is_empty()).then_some(frame)
+}
+
+/// Write frames in order
+pub fn emit_all(sink: &mut dyn EventSink, frames: &[Frame]) -> Result<()> {
+    for frame in frames {
+        sink.emit(frame)?;
+    }
+    Ok(())
+}
+
+/// Pointer movement from a stick or the gyro, keeping sub-pixel remainders
+/// between polls
+#[derive(Debug, Default)]
+pub struct PointerMotion {
+    remainder: (f32, f32),
+}
+
+impl PointerMotion {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whole pixels to move for a poll `dt` seconds long
+    pub fn update(
+        &mut self,
+        config: &MouseConfig,
+        state: &ControllerState,
+        curves: &AxisCurves,
+        dt: f32,
+    ) -> (i32, i32) {
+        let (vx, vy) = pointer_velocity(config, state, curves);
+        if vx == 0.0 && vy == 0.0 {
+            // Don't let a leftover fraction nudge the pointer later
+            self.remainder = (0.0, 0.0);
+            return (0, 0);
+        }
+        let dt = dt.min(MAX_POINTER_DT);
+        let x = self.remainder.0 + vx * dt;
+        let y = self.remainder.1 + vy * dt;
+        let (dx, dy) = (x.trunc(), y.trunc());
+        self.remainder = (x - dx, y - dy);
+        (dx as i32, dy as i32)
+    }
+}
+
+/// Pointer velocity in pixels per second (positive is right and down)
+fn pointer_velocity(
+    config: &MouseConfig,
+    state: &ControllerState,
+    curves: &AxisCurves,
+) -> (f32, f32) {
+    let linear = ResponseCurve::default();
+    let (vx, vy) = match config.source {
+        PointerSource::LeftStick => {
+            let stick = state.left_stick.normalized_with_deadzone(config.deadzone);
+            stick_velocity(config, stick, curves.left_stick(stick, &linear))
+        }
+        PointerSource::RightStick => {
+            let stick = state.right_stick.normalized_with_deadzone(config.deadzone);
+            stick_velocity(config, stick, curves.right_stick(stick, &linear))
+        }
+        PointerSource::Gyro => {
+            let gyro = state.gyroscope.to_rad_per_sec();
+            let rate = |radians: f32| {
+                let dps = radians.to_degrees();
+                if dps.abs() < config.gyro_deadzone_dps {
+                    return 0.0;
+                }
+                let dps = match &config.curve {
+                    Some(curve) => curve.apply(dps / GYRO_FULL_SCALE_DPS) * GYRO_FULL_SCALE_DPS,
+                    None => dps,
+                };
+                dps * config.gyro_sensitivity
+            };
+            // Turning right (+y) moves right, tilting up (+x) moves up
+            (rate(gyro.y), -rate(gyro.x))
+        }
+    };
+
+    (
+        if config.invert_x { -vx } else { vx },
+        if config.invert_y { -vy } else { vy },
+    )
+}
+
+/// Stick velocity through `curve`, or else through the stick's analog
+/// curve (`analog`, already applied)
+fn stick_velocity(config: &MouseConfig, (x, y): (f32, f32), analog: (f32, f32)) -> (f32, f32) {
+    let (x, y) = match &config.curve {
+        Some(curve) => (curve.apply(x), curve.apply(y)),
+        None => analog,
+    };
+    (x * config.speed, y * config.speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uinput::testing::{from_json, Recorder};
+    use serde_json::json;
+
+    #[test]
+    fn test_key_tap_with_modifiers() {
+        let action: KeyAction =
+            serde_json::from_value(json!({ "key": "t", "modifiers": ["ctrl", "shift"] })).unwrap();
+        let mut sink = Recorder::default();
+        emit_all(&mut sink, &key_frames(&action).unwrap()).unwrap();
+
+        let (ctrl, shift, t) = (codes::KEY_LEFTCTRL, codes::KEY_LEFTSHIFT, 20);
+        assert_eq!(
+            sink.frames(),
+            vec![
+                vec![down(ctrl), down(shift)],
+                vec![down(t)],
+                vec![up(t)],
+                vec![up(shift), up(ctrl)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_key_press_and_release() {
+        let press: KeyAction =
+            serde_json::from_value(json!({ "key": "space", "mode": "press" })).unwrap();
+        assert_eq!(key_frames(&press).unwrap(), vec![vec![down(57)]]);
+        let release: KeyAction =
+            serde_json::from_value(json!({ "key": "space", "mode": "release" })).unwrap();
+        assert_eq!(key_frames(&release).unwrap(), vec![vec![up(57)]]);
+
+        let unknown: KeyAction = serde_json::from_value(json!({ "key": "hyper" })).unwrap();
+        assert!(key_frames(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_mouse_frames() {
+        let action: MouseAction = serde_json::from_value(json!({
+            "button": "left",
+            "move_x": 5,
+            "scroll": -2
+        }))
+        .unwrap();
+        assert_eq!(
+            mouse_frames(&action),
+            vec![
+                vec![InputEvent::Relative {
+                    code: codes::REL_X,
+                    value: 5
+                }],
+                vec![down(codes::BTN_LEFT)],
+                vec![up(codes::BTN_LEFT)],
+                vec![InputEvent::Relative {
+                    code: codes::REL_WHEEL,
+                    value: -2
+                }],
+            ]
+        );
+        assert!(mouse_frames(&MouseAction::default()).is_empty());
+    }
+
+    #[test]
+    fn test_stick_pointer() {
+        let config: MouseConfig = from_json(json!({ "source": "right_stick", "speed": 1000.0 }));
+        let mut state = ControllerState::default();
+        state.right_stick.x = 255;
+        state.right_stick.y = 128;
+        let mut motion = PointerMotion::new();
+        let curves = AxisCurves::default();
+
+        // 1000 px/s for 4 ms: 4 px
+        assert_eq!(motion.update(&config, &state, &curves, 0.004), (4, 0));
+
+        // Half deflection, past the deadzone: fractions add up
+        state.right_stick.x = 192;
+        let moved: i32 = (0..10)
+            .map(|_| motion.update(&config, &state, &curves, 0.001).0)
+            .sum();
+        assert!((3..=5).contains(&moved), "moved {}", moved);
+
+        // Centered: no movement
+        state.right_stick.x = 128;
+        assert_eq!(motion.update(&config, &state, &curves, 0.01), (0, 0));
+
+        // The stick's analog curve applies unless `curve` is set
+        let quadratic: ResponseCurve = serde_json::from_value(json!("quadratic")).unwrap();
+        let curves = AxisCurves {
+            right_stick_x: Some(quadratic),
+            ..Default::default()
+        };
+        state.right_stick.x = 192;
+        let (dx, _) = motion.update(&config, &state, &curves, 0.1);
+        assert!((20..=30).contains(&dx), "dx {}", dx);
+    }
+
+    #[test]
+    fn test_gyro_pointer() {
+        let config: MouseConfig = from_json(json!({ "source": "gyro", "gyro_sensitivity": 10.0 }));
+        let mut state = ControllerState::default();
+        let curves = AxisCurves::default();
+        let mut motion = PointerMotion::new();
+
+        // 1024 raw is 1 rad/s: about 57 deg/s, 573 px/s
+        state.gyroscope.y = 1024;
+        let (dx, dy) = motion.update(&config, &state, &curves, 0.1);
+        assert_eq!((dx, dy), (57, 0));
+
+        // Tilting up moves up
+        state.gyroscope.y = 0;
+        state.gyroscope.x = 1024;
+        let (_, dy) = motion.update(&config, &state, &curves, 0.1);
+        assert!(dy < 0);
+
+        // Below the gyro deadzone: still
+        state.gyroscope.x = 10;
+        assert_eq!(motion.update(&config, &state, &curves, 0.1), (0, 0));
+    }
+}
@@ -113,14 +113,19 @@ impl Stick {
 
     /// Get normalized values with deadzone applied
     pub fn normalized_with_deadzone(&self, deadzone: f32) -> (f32, f32) {
-        let (x, y) = self.normalized();
-        let magnitude = (x * x + y * y).sqrt();
-        if magnitude < deadzone {
-            (0.0, 0.0)
-        } else {
-            let scale = (magnitude - deadzone) / (1.0 - deadzone) / magnitude;
-            (x * scale, y * scale)
-        }
+        radial_deadzone(self.normalized(), deadzone)
+    }
+}
+
+/// Zero normalized stick values inside a radial deadzone and rescale the rest to 0.0-1.0
+pub fn radial_deadzone((x, y): (f32, f32), deadzone: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    // `<=` so a centered stick with no deadzone doesn't divide by zero
+    if magnitude <= deadzone {
+        (0.0, 0.0)
+    } else {
+        let scale = (magnitude - deadzone) / (1.0 - deadzone) / magnitude;
+        (x * scale, y * scale)
     }
 }
 
@@ -1119,6 +1124,23 @@ mod tests {
         assert!((y + 1.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_radial_deadzone() {
+        assert_eq!(radial_deadzone((0.05, 0.05), 0.1), (0.0, 0.0));
+        let (x, y) = radial_deadzone((0.55, 0.0), 0.1);
+        assert!((x - 0.5).abs() < 0.001);
+        assert_eq!(y, 0.0);
+        let (x, _) = radial_deadzone((1.0, 0.0), 0.1);
+        assert!((x - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_centered_without_deadzone() {
+        assert_eq!(radial_deadzone((0.0, 0.0), 0.0), (0.0, 0.0));
+        let (x, y) = radial_deadzone((0.5, -0.5), 0.0);
+        assert!((x - 0.5).abs() < 0.001 && (y + 0.5).abs() < 0.001);
+    }
+
     #[test]
     fn test_trigger_normalized() {
         let triggers = Triggers { l2: 0, r2: 255 };
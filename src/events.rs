@@ -0,0 +1,156 @@
+//! Event metadata for template contexts
+//!
+//! Tracks, per input, when it was pressed and when its action last fired,
+//! so templates can see which input fired, how long it has been held and
+//! how often it repeated.
+
+use std::collections::HashMap;
+
+use crate::config::TemplateContext;
+
+/// Hold and fire bookkeeping per input
+#[derive(Debug, Default)]
+pub struct EventTracker {
+    held_since: HashMap<String, u64>,
+    repeats: HashMap<String, u32>,
+    last_fire: HashMap<String, u64>,
+}
+
+impl EventTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a digital input's level; a new press restarts its hold and repeat count
+    pub fn track(&mut self, source: &str, prev: bool, current: bool, now_ms: u64) {
+        if !prev && current {
+            self.held_since.insert(source.to_string(), now_ms);
+            self.repeats.insert(source.to_string(), 0);
+        }
+    }
+
+    /// Record a fire of `source` and return `ctx` with its event fields filled in
+    pub fn fire(
+        &mut self,
+        source: &str,
+        event_type: &str,
+        ctx: &TemplateContext,
+        now_ms: u64,
+    ) -> TemplateContext {
+        self.fire_as(source, source, event_type, ctx, now_ms)
+    }
+
+    /// Like [`fire`](Self::fire), for sources grouping unrelated events
+    /// (`motion`, `lifecycle`): each event is tracked on its own, e.g. as
+    /// `motion:shake`
+    pub fn fire_event(
+        &mut self,
+        source: &str,
+        event_type: &str,
+        ctx: &TemplateContext,
+        now_ms: u64,
+    ) -> TemplateContext {
+        let key = format!("{}:{}", source, event_type);
+        self.fire_as(&key, source, event_type, ctx, now_ms)
+    }
+
+    fn fire_as(
+        &mut self,
+        key: &str,
+        source: &str,
+        event_type: &str,
+        ctx: &TemplateContext,
+        now_ms: u64,
+    ) -> TemplateContext {
+        let mut ctx = ctx.clone();
+        ctx.event_source = source.to_string();
+        ctx.event_type = event_type.to_string();
+        ctx.hold_ms = self
+            .held_since
+            .get(key)
+            .map_or(0, |since| now_ms.saturating_sub(*since));
+        ctx.since_last_ms = self
+            .last_fire
+            .insert(key.to_string(), now_ms)
+            .map_or(0, |last| now_ms.saturating_sub(last));
+        if let Some(repeats) = self.repeats.get_mut(key) {
+            ctx.repeat_count = *repeats;
+            *repeats += 1;
+        }
+        ctx
+    }
+
+    /// End the hold of a released input (after its release action fired)
+    pub fn release(&mut self, source: &str, current: bool) {
+        if !current {
+            self.held_since.remove(source);
+            self.repeats.remove(source);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dualsense::ControllerState;
+
+    #[test]
+    fn test_hold_and_repeat() {
+        let ctx = TemplateContext::from(&ControllerState::default());
+        let mut tracker = EventTracker::new();
+
+        tracker.track("cross", false, true, 1000);
+        let first = tracker.fire("cross", "hold", &ctx, 1000);
+        assert_eq!(first.event_source, "cross");
+        assert_eq!(first.event_type, "hold");
+        assert_eq!(
+            (first.hold_ms, first.repeat_count, first.since_last_ms),
+            (0, 0, 0)
+        );
+
+        tracker.track("cross", true, true, 1016);
+        let second = tracker.fire("cross", "hold", &ctx, 1016);
+        assert_eq!(
+            (second.hold_ms, second.repeat_count, second.since_last_ms),
+            (16, 1, 16)
+        );
+
+        // Release still reports the hold, then a new press starts over
+        tracker.track("cross", true, false, 1500);
+        let released = tracker.fire("cross", "release", &ctx, 1500);
+        assert_eq!((released.hold_ms, released.repeat_count), (500, 2));
+        tracker.release("cross", false);
+
+        tracker.track("cross", false, true, 2000);
+        let again = tracker.fire("cross", "press", &ctx, 2000);
+        assert_eq!(
+            (again.hold_ms, again.repeat_count, again.since_last_ms),
+            (0, 0, 500)
+        );
+    }
+
+    #[test]
+    fn test_untracked_sources() {
+        let ctx = TemplateContext::from(&ControllerState::default());
+        let mut tracker = EventTracker::new();
+
+        tracker.fire("touchpad", "swipe_left", &ctx, 100);
+        let fired = tracker.fire("touchpad", "swipe_left", &ctx, 350);
+        assert_eq!(
+            (fired.hold_ms, fired.repeat_count, fired.since_last_ms),
+            (0, 0, 250)
+        );
+    }
+
+    #[test]
+    fn test_grouped_events_tracked_apart() {
+        let ctx = TemplateContext::from(&ControllerState::default());
+        let mut tracker = EventTracker::new();
+
+        tracker.fire_event("motion", "shake", &ctx, 100);
+        let tilt = tracker.fire_event("motion", "tilt_left", &ctx, 300);
+        assert_eq!((tilt.event_source.as_str(), tilt.since_last_ms), ("motion", 0));
+        let shake = tracker.fire_event("motion", "shake", &ctx, 400);
+        assert_eq!(shake.since_last_ms, 300);
+    }
+}
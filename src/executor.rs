@@ -1,50 +1,61 @@
 //! Action executor
 //!
-//! Handles execution of shell commands, HTTP requests, and WebSocket messages
-//! based on controller input events.
+//! Handles execution of shell commands, HTTP requests, WebSocket, OSC, MQTT
+//! and raw socket messages and virtual keyboard and mouse input based on
+//! controller input events, and MIDI output, pointer movement and the
+//! virtual gamepad following the controller state.
+//!
+//! Detection runs on the poll loop; the actions it fires go through a
+//! bounded [`JobQueue`] to a worker task, so a slow action never delays the
+//! next HID read. The worker runs jobs of different keys side by side;
+//! each output is locked while it sends, so messages never interleave.
 
 use std::collections::HashMap;
-use std::process::Command;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
-use futures_util::stream::SplitSink;
-use futures_util::SinkExt;
-use handlebars::Handlebars;
+use futures_util::stream::{FuturesUnordered, SplitSink};
+use futures_util::{SinkExt, StreamExt};
 use reqwest::Client as HttpClient;
 use tokio::net::TcpStream;
 use tokio::sync::mpsc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, OnceCell};
+use tokio::task::JoinHandle;
 use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
-use tracing::{debug, error, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 
 use crate::config::{
-    ActionConfig, Config, HttpRequest, RumbleConfig,
-    TemplateContext, WebSocketMessage,
+    ActionConfig, Config, EventType, HttpFeedback, HttpMethod, HttpRequest, HttpResponseConfig,
+    MqttMessage, OscMessageConfig, ProcessConfig, RumbleConfig, ShellCommand, StickMapping,
+    TemplateContext, TriggerMapping, WebSocketMessage,
 };
-use crate::dualsense::ControllerState;
-
-/// Event types for action triggering
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum EventType {
-    Press,
-    Release,
-    Hold,
-    Change,
-}
-
-impl EventType {
-    pub fn from_str(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
-            "release" => EventType::Release,
-            "hold" => EventType::Hold,
-            "change" => EventType::Change,
-            _ => EventType::Press,
-        }
-    }
-}
+use crate::curve::{AxisCurves, ResponseCurve};
+use crate::desktop::{self, PointerMotion};
+use crate::dualsense::{ControllerState, TriggerEffect};
+use crate::events::EventTracker;
+use crate::gamepad::{self, VirtualGamepad};
+use crate::http::{self, HttpOutcome};
+use crate::lifecycle::{BatteryMonitor, LifecycleEvent};
+use crate::midi::{MidiMapper, MidiMessage, MidiPort};
+use crate::motion::MotionDetector;
+use crate::mqtt::MqttPublisher;
+use crate::osc::{self, OscPacket, OscSender};
+use crate::pipeline::{JobQueue, QueueMetrics, BLOCK_WARN_AFTER};
+use crate::process::{ProcessOutput, ProcessSpec, ProcessSupervisor};
+use crate::repeat::AutoRepeat;
+use crate::socket::SocketSink;
+use crate::stick::{StickProcessor, StickSettings};
+use crate::templates::TemplateSet;
+use crate::touchpad::GestureRecognizer;
+use crate::trigger::{TriggerProcessor, TriggerSettings};
+use crate::uinput::{DeviceSpec, EventSink, Frame, UinputDevice};
+use crate::vars::{self, VarStore};
+
+/// Name of the virtual keyboard and mouse
+const VIRTUAL_INPUT_NAME: &str = "DualSense Virtual Input";
 
 /// Debounce tracker
 struct DebounceState {
@@ -74,49 +85,402 @@ impl DebounceState {
     }
 }
 
+/// An action auto-repeating while its input is held
+struct HeldRepeat {
+    timer: AutoRepeat,
+    action: ActionConfig,
+    /// Latest context of the input, so repeats see current values
+    ctx: TemplateContext,
+}
+
 /// Commands to send to the controller
 pub enum ControllerCommand {
     SetLed(u8, u8, u8),
     SetRumble(u8, u8, u64), // left, right, duration_ms
+    BlinkLed(u8, u8, u8, u32, u64), // r, g, b, count, interval_ms
+    SetL2Effect(TriggerEffect),
+    SetR2Effect(TriggerEffect),
+}
+
+type WsSender = Arc<Mutex<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>>>;
+
+/// Virtual keyboard and mouse, created on first use. The poll loop moves
+/// the pointer; the runner sends keys and clicks.
+type SharedInput = Arc<std::sync::Mutex<Option<Box<dyn EventSink>>>>;
+
+/// Work handed from the poll loop to the action worker
+enum Job {
+    Action {
+        action: Box<ActionConfig>,
+        ctx: TemplateContext,
+    },
+    StateUpdate(StateStream, TemplateContext),
+    Reconfigure(Box<Reload>),
+    SetWsSender(WsSender),
+    /// Store a command's output or an HTTP response value in a variable
+    SetVar(String, serde_json::Value),
+    Midi(Vec<MidiMessage>),
+}
+
+impl Job {
+    /// Jobs that change the runner itself; they are queued as barriers
+    fn is_exclusive(&self) -> bool {
+        matches!(self, Job::Reconfigure(_) | Job::SetWsSender(_))
+    }
+}
+
+/// A new configuration with everything that can fail already done, so the
+/// worker can swap it in without leaving the runner half reloaded
+struct Reload {
+    config: Config,
+    templates: TemplateSet,
+    /// New MQTT connection, when its settings changed
+    mqtt: Option<Option<MqttPublisher>>,
+    /// New MIDI port, when its settings changed
+    midi: Option<Option<MidiPort>>,
+}
+
+/// Periodic state output
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum StateStream {
+    WebSocket,
+    Osc,
+    Mqtt,
+    /// Raw socket by name
+    Socket(String),
+}
+
+impl StateStream {
+    /// Queue key; each stream coalesces on its own
+    fn key(&self) -> String {
+        match self {
+            StateStream::WebSocket => "state:websocket".to_string(),
+            StateStream::Osc => "state:osc".to_string(),
+            StateStream::Mqtt => "state:mqtt".to_string(),
+            StateStream::Socket(name) => format!("state:socket:{}", name),
+        }
+    }
+}
+
+/// When a state stream is next due
+struct StateSchedule {
+    stream: StateStream,
+    interval: Duration,
+    last: Instant,
 }
 
 /// Action executor
 pub struct Executor {
     config: Config,
-    handlebars: Handlebars<'static>,
-    http_client: Option<HttpClient>,
+    curves: AxisCurves,
     debounce: DebounceState,
-    ws_sender: Option<Arc<Mutex<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>>>>,
+    events: EventTracker,
+    repeating: HashMap<String, HeldRepeat>,
+    state_streams: Vec<StateSchedule>,
+    queue: Arc<JobQueue<Job>>,
+    worker: JoinHandle<()>,
+    controller_cmd_tx: mpsc::Sender<ControllerCommand>,
+    left_stick: StickProcessor,
+    right_stick: StickProcessor,
+    l2_trigger: TriggerProcessor,
+    r2_trigger: TriggerProcessor,
+    /// Detent effects need (re)sending to the controller
+    detents_stale: bool,
+    gestures: GestureRecognizer,
+    motion: MotionDetector,
+    battery: BatteryMonitor,
+    midi: MidiMapper,
+    pointer: PointerMotion,
+    last_pointer_update: Instant,
+    /// Pointer motion goes out from the poll loop, so none of it is dropped
+    input: SharedInput,
+    /// Written from the poll loop, not the worker, to keep its latency low
+    gamepad: Option<VirtualGamepad>,
+    started: Instant,
+}
+
+/// Runs queued jobs on the worker task, several keys at once. Reloads
+/// and a new WebSocket sender run alone, so only they change the runner;
+/// every other job shares it.
+struct ActionRunner {
+    config: Config,
+    templates: TemplateSet,
+    http_client: Option<HttpClient>,
+    ws_sender: Option<WsSender>,
+    /// Bound on first use, dropped on reload
+    osc: OnceCell<OscSender>,
+    mqtt: Option<MqttPublisher>,
+    /// Raw socket outputs by name, each locked while it sends
+    sockets: HashMap<String, Mutex<SocketSink>>,
+    midi: std::sync::Mutex<Option<MidiPort>>,
+    input: SharedInput,
     controller_cmd_tx: mpsc::Sender<ControllerCommand>,
+    vars: std::sync::Mutex<VarStore>,
+    processes: ProcessSupervisor,
+    /// The queue the runner works off (commands report back through it)
+    queue: Arc<JobQueue<Job>>,
 }
 
 impl Executor {
-    pub fn new(config: Config, controller_cmd_tx: mpsc::Sender<ControllerCommand>) -> Self {
-        let http_client = config.http.as_ref().map(|_| {
-            HttpClient::builder()
-                .timeout(Duration::from_millis(
-                    config.http.as_ref().map(|h| h.timeout_ms).unwrap_or(5000),
-                ))
-                .build()
-                .expect("Failed to create HTTP client")
-        });
+    /// Build an executor, compiling every template in the config, and
+    /// start its action worker (needs a Tokio runtime)
+    pub fn new(config: Config, controller_cmd_tx: mpsc::Sender<ControllerCommand>) -> Result<Self> {
+        let templates = TemplateSet::compile(&config)?;
+        let curves = config.analog.curves();
+        let left_stick = stick_processor(config.analog.left_stick.as_ref());
+        let right_stick = stick_processor(config.analog.right_stick.as_ref());
+        let l2_trigger = trigger_processor(config.analog.l2_trigger.as_ref());
+        let r2_trigger = trigger_processor(config.analog.r2_trigger.as_ref());
+        let gestures = GestureRecognizer::new(config.touchpad.gesture_settings());
+        let motion = MotionDetector::new(config.motion.motion_settings());
+        let battery = BatteryMonitor::new(
+            config.lifecycle.low_battery_percent,
+            config.lifecycle.battery_hysteresis_percent,
+        );
+
+        let mqtt = config
+            .mqtt
+            .as_ref()
+            .map(MqttPublisher::connect)
+            .transpose()?;
+        let midi = config.midi.as_ref().map(MidiPort::open).transpose()?;
+        let gamepad = config
+            .gamepad
+            .as_ref()
+            .map(VirtualGamepad::create)
+            .transpose()?;
+
+        let input = SharedInput::default();
+        let queue = Arc::new(JobQueue::new(
+            config.pipeline.queue_size,
+            config.pipeline.overflow,
+            config.pipeline.workers,
+        ));
+        let runner = ActionRunner {
+            config: config.clone(),
+            templates,
+            http_client: build_http_client(&config),
+            ws_sender: None,
+            osc: OnceCell::new(),
+            mqtt,
+            sockets: socket_sinks(&config),
+            midi: std::sync::Mutex::new(midi),
+            input: input.clone(),
+            controller_cmd_tx: controller_cmd_tx.clone(),
+            vars: std::sync::Mutex::new(VarStore::new(
+                &config.variables.initial,
+                persist_path(&config),
+            )),
+            processes: ProcessSupervisor::new(),
+            queue: queue.clone(),
+        };
+        let worker = tokio::spawn(runner.run());
+        let state_streams = state_schedules(&config);
 
-        Self {
+        Ok(Self {
             config,
-            handlebars: Handlebars::new(),
-            http_client,
+            curves,
             debounce: DebounceState::new(),
-            ws_sender: None,
+            events: EventTracker::new(),
+            repeating: HashMap::new(),
+            state_streams,
+            queue,
+            worker,
             controller_cmd_tx,
+            left_stick,
+            right_stick,
+            l2_trigger,
+            r2_trigger,
+            detents_stale: true,
+            gestures,
+            motion,
+            battery,
+            midi: MidiMapper::new(),
+            pointer: PointerMotion::new(),
+            last_pointer_update: Instant::now(),
+            input,
+            gamepad,
+            started: Instant::now(),
+        })
+    }
+
+    /// Swap in a new configuration.
+    /// Connections, debounce timers and the start time are kept; detectors
+    /// are rebuilt with the new tuning. If a template fails to compile or a
+    /// changed connection can't be opened the current configuration stays
+    /// active. Actions queued before the swap still run with the old
+    /// configuration.
+    pub fn set_config(&mut self, config: Config) -> Result<()> {
+        let templates = TemplateSet::compile(&config)?;
+        let curves = config.analog.curves();
+
+        // Recreate the virtual gamepad only when its device changes
+        let gamepad_changed = match (&config.gamepad, &self.config.gamepad) {
+            (Some(new), Some(old)) => new.name != old.name,
+            (new, old) => new.is_some() != old.is_some(),
+        };
+        let gamepad = if gamepad_changed {
+            Some(
+                config
+                    .gamepad
+                    .as_ref()
+                    .map(VirtualGamepad::create)
+                    .transpose()?,
+            )
+        } else {
+            None
+        };
+
+        // Open changed outputs before touching anything; MQTT goes last
+        // since a dropped publisher would leave its connection running
+        let midi_changed = match (&config.midi, &self.config.midi) {
+            (Some(new), Some(old)) => !new.same_port(old),
+            (new, old) => new.is_some() != old.is_some(),
+        };
+        let midi = if midi_changed {
+            Some(config.midi.as_ref().map(MidiPort::open).transpose()?)
+        } else {
+            None
+        };
+        let mqtt = if config.mqtt != self.config.mqtt {
+            Some(
+                config
+                    .mqtt
+                    .as_ref()
+                    .map(MqttPublisher::connect)
+                    .transpose()?,
+            )
+        } else {
+            None
+        };
+
+        // Release detents the new config no longer places
+        let off = TriggerEffect::default();
+        if detent_force(&self.config.analog.l2_trigger).is_some()
+            && detent_force(&config.analog.l2_trigger).is_none()
+        {
+            self.controller_cmd_tx
+                .try_send(ControllerCommand::SetL2Effect(off))
+                .ok();
+        }
+        if detent_force(&self.config.analog.r2_trigger).is_some()
+            && detent_force(&config.analog.r2_trigger).is_none()
+        {
+            self.controller_cmd_tx
+                .try_send(ControllerCommand::SetR2Effect(off))
+                .ok();
+        }
+
+        let pipeline = &self.config.pipeline;
+        if pipeline.queue_size != config.pipeline.queue_size
+            || pipeline.overflow != config.pipeline.overflow
+            || pipeline.workers != config.pipeline.workers
+        {
+            warn!("Pipeline changes take effect after restarting `run`");
         }
+
+        // Don't leave notes of the old mapping hanging
+        self.release_midi_notes();
+
+        self.queue.push_barrier(
+            "config".to_string(),
+            Job::Reconfigure(Box::new(Reload {
+                config: config.clone(),
+                templates,
+                mqtt,
+                midi,
+            })),
+        );
+        // Retune the recognizers in place so a press, touch or latch in
+        // progress carries over the reload
+        self.left_stick
+            .set_settings(stick_settings(config.analog.left_stick.as_ref()));
+        self.right_stick
+            .set_settings(stick_settings(config.analog.right_stick.as_ref()));
+        self.l2_trigger
+            .set_settings(trigger_settings(config.analog.l2_trigger.as_ref()));
+        self.r2_trigger
+            .set_settings(trigger_settings(config.analog.r2_trigger.as_ref()));
+        self.gestures
+            .set_settings(config.touchpad.gesture_settings());
+        self.motion.set_settings(config.motion.motion_settings());
+        self.battery.set_thresholds(
+            config.lifecycle.low_battery_percent,
+            config.lifecycle.battery_hysteresis_percent,
+        );
+        self.config = config;
+        self.curves = curves;
+        self.detents_stale = true;
+        self.repeating.clear();
+        self.state_streams = state_schedules(&self.config);
+        if let Some(gamepad) = gamepad {
+            self.gamepad = gamepad;
+        }
+        Ok(())
+    }
+
+    /// Template context of a controller state, with the deadzone and analog curves applied
+    fn context(&self, state: &ControllerState) -> TemplateContext {
+        let mut ctx = TemplateContext::from(state);
+        self.shape_analog(&mut ctx);
+        ctx
+    }
+
+    /// Apply the stick deadzone and the analog curves, matching the stick mappings
+    fn shape_analog(&self, ctx: &mut TemplateContext) {
+        ctx.apply_deadzone(self.config.deadzone);
+        ctx.apply_curves(&self.curves);
     }
 
     /// Set the WebSocket sender
-    pub fn set_ws_sender(
-        &mut self,
-        sender: Arc<Mutex<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>>>,
-    ) {
-        self.ws_sender = Some(sender);
+    pub fn set_ws_sender(&mut self, sender: WsSender) {
+        self.queue
+            .push_barrier("websocket".to_string(), Job::SetWsSender(sender));
+    }
+
+    /// Counters of the action queue
+    pub fn queue_metrics(&self) -> QueueMetrics {
+        self.queue.metrics()
+    }
+
+    /// Stop taking actions and wait for the queued ones to finish
+    pub async fn shutdown(mut self) -> QueueMetrics {
+        self.release_midi_notes();
+        self.queue.close();
+        if let Err(e) = self.worker.await {
+            error!("Action worker failed: {}", e);
+        }
+        self.queue.metrics()
+    }
+
+    /// Hand an action to the worker; the key is the input that fired it,
+    /// so its press and release actions run in order
+    async fn queue_action(&self, action: &ActionConfig, ctx: TemplateContext) {
+        let key = ctx.event_source.clone();
+        let job = Job::Action {
+            action: Box::new(action.clone()),
+            ctx,
+        };
+        self.push(key, job, false).await;
+    }
+
+    /// Queue a job; a `continuous` one may be replaced by a newer one with
+    /// its key under `coalesce`
+    async fn push(&self, key: String, job: Job, continuous: bool) {
+        let start = Instant::now();
+        let queued = if continuous {
+            self.queue.push_continuous(key, job).await
+        } else {
+            self.queue.push(key, job).await
+        };
+        if !queued {
+            warn!("Action queue closed, dropping job");
+        }
+        // Only the `block` policy waits
+        let waited = start.elapsed();
+        if waited >= BLOCK_WARN_AFTER {
+            warn!("Action queue full, poll loop blocked for {:?}", waited);
+        }
     }
 
     /// Process a state change and execute matching actions
@@ -125,7 +489,7 @@ impl Executor {
         prev: &ControllerState,
         current: &ControllerState,
     ) -> Result<()> {
-        let ctx = TemplateContext::from(current);
+        let ctx = self.context(current);
 
         // Check button changes
         self.check_button_action("cross", prev.buttons.cross, current.buttons.cross, &ctx)
@@ -207,12 +571,73 @@ impl Executor {
             .await?;
 
         // Check analog inputs
-        self.check_stick_actions(prev, current, &ctx).await?;
+        self.check_stick_actions(current, &ctx).await?;
         self.check_trigger_actions(prev, current, &ctx).await?;
 
+        // Touchpad gestures and regions
+        self.check_touchpad_actions(prev, current, &ctx).await?;
+
+        // IMU gestures
+        self.check_motion_actions(current, &ctx).await?;
+
+        // Battery lifecycle
+        for event in self.battery.update(&current.battery) {
+            self.fire_lifecycle_event(event, &ctx).await?;
+        }
+
+        // MIDI notes and controllers
+        self.update_midi(&ctx).await;
+
+        // Pointer movement
+        self.update_pointer(current)?;
+
+        // Virtual gamepad
+        if let (Some(gamepad), Some(gamepad_config)) = (&mut self.gamepad, &self.config.gamepad) {
+            gamepad.update(gamepad::map_state(gamepad_config, current, &self.curves))?;
+        }
+
         Ok(())
     }
 
+    /// Queue MIDI messages for inputs that changed. Only controller values
+    /// are continuous; notes are never merged away.
+    async fn update_midi(&mut self, ctx: &TemplateContext) {
+        let Some(midi) = &self.config.midi else {
+            return;
+        };
+        for (input, messages) in self.midi.update(midi, ctx) {
+            let note = matches!(
+                messages.first(),
+                Some(MidiMessage::NoteOn { .. } | MidiMessage::NoteOff { .. })
+            );
+            self.push(format!("midi:{}", input), Job::Midi(messages), !note)
+                .await;
+        }
+    }
+
+    /// Move the pointer for the time since the last poll
+    fn update_pointer(&mut self, state: &ControllerState) -> Result<()> {
+        let dt = self.last_pointer_update.elapsed().as_secs_f32();
+        self.last_pointer_update = Instant::now();
+        let Some(mouse) = &self.config.mouse else {
+            return Ok(());
+        };
+        let (dx, dy) = self.pointer.update(mouse, state, &self.curves, dt);
+        match desktop::pointer_frame(dx, dy) {
+            Some(frame) => emit_input(&self.input, &[frame]),
+            None => Ok(()),
+        }
+    }
+
+    /// Release sounding notes once the note ons queued before are sent
+    fn release_midi_notes(&mut self) {
+        let released = self.midi.release_all();
+        if !released.is_empty() {
+            self.queue
+                .push_barrier("midi:release".to_string(), Job::Midi(released));
+        }
+    }
+
     async fn check_button_action(
         &mut self,
         name: &str,
@@ -245,112 +670,242 @@ impl Executor {
         };
 
         if let Some(action) = action_opt {
-            let event_type = EventType::from_str(&action.trigger);
-            let should_trigger = match event_type {
-                EventType::Press => !prev && current,
-                EventType::Release => prev && !current,
-                EventType::Hold => current,
-                EventType::Change => prev != current,
+            self.check_edge_action(name, &action, prev, current, ctx)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Run a digital-input action if its trigger matches the prev -> current edge
+    async fn check_edge_action(
+        &mut self,
+        name: &str,
+        action: &ActionConfig,
+        prev: bool,
+        current: bool,
+        ctx: &TemplateContext,
+    ) -> Result<()> {
+        let event_type = action.trigger;
+        let should_trigger = match event_type {
+            EventType::Press => !prev && current,
+            EventType::Release => prev && !current,
+            EventType::Hold => current,
+            EventType::Change => prev != current,
+        };
+
+        let now_ms = self.started.elapsed().as_millis() as u64;
+        self.events.track(name, prev, current, now_ms);
+        let fired = should_trigger && self.debounce.can_trigger(name, action.debounce_ms);
+        if fired {
+            debug!("Triggering action for: {}", name);
+            let ctx = self.events.fire(name, event_type.name(), ctx, now_ms);
+            self.queue_action(action, ctx).await;
+        }
+
+        // Auto-repeat is driven by `tick`; here it only starts, follows and
+        // stops. A press the debounce swallowed doesn't start it.
+        if let Some(repeat) = action
+            .repeat
+            .as_ref()
+            .filter(|_| event_type == EventType::Press)
+        {
+            if fired {
+                let held = HeldRepeat {
+                    timer: AutoRepeat::start(repeat.repeat_settings(), now_ms),
+                    action: action.clone(),
+                    ctx: ctx.clone(),
+                };
+                self.repeating.insert(name.to_string(), held);
+            } else if let Some(held) = self.repeating.get_mut(name) {
+                held.ctx = ctx.clone();
+            }
+        }
+        if !current {
+            self.repeating.remove(name);
+        }
+        self.events.release(name, current);
+
+        Ok(())
+    }
+
+    /// Fire auto-repeats that are due; call on every loop iteration,
+    /// whether or not a controller report arrived
+    pub async fn tick(&mut self) -> Result<()> {
+        let now_ms = self.started.elapsed().as_millis() as u64;
+        let due: Vec<String> = self
+            .repeating
+            .iter_mut()
+            .filter_map(|(name, held)| held.timer.poll(now_ms).then(|| name.clone()))
+            .collect();
+
+        for name in due {
+            let Some(held) = self.repeating.get(&name) else {
+                continue;
             };
+            let action = held.action.clone();
+            let ctx = self.events.fire(&name, "repeat", &held.ctx, now_ms);
+            trace!("Repeating action for: {}", name);
+            self.queue_action(&action, ctx).await;
+        }
+
+        Ok(())
+    }
+
+    /// Run the action hooked to a lifecycle event (connect, disconnect, ...)
+    pub async fn fire_lifecycle(
+        &mut self,
+        event: LifecycleEvent,
+        state: &ControllerState,
+    ) -> Result<()> {
+        if event == LifecycleEvent::Reconnect {
+            self.battery.reset();
+            self.detents_stale = true;
+        }
+        if event == LifecycleEvent::Disconnect {
+            self.repeating.clear();
+            // Don't leave the virtual gamepad holding the last input
+            if let Some(gamepad) = &mut self.gamepad {
+                gamepad.neutral().ok();
+            }
+        }
+        let ctx = self.context(state);
+        self.fire_lifecycle_event(event, &ctx).await
+    }
 
-            if should_trigger && self.debounce.can_trigger(name, action.debounce_ms) {
-                debug!("Triggering action for button: {}", name);
-                self.execute_action(&action, ctx).await?;
+    async fn fire_lifecycle_event(
+        &mut self,
+        event: LifecycleEvent,
+        ctx: &TemplateContext,
+    ) -> Result<()> {
+        debug!("Lifecycle event: {}", event.name());
+        if let Some(action) = self.config.lifecycle.event_action(event).cloned() {
+            let now_ms = self.started.elapsed().as_millis() as u64;
+            let ctx = self.events.fire_event("lifecycle", event.name(), ctx, now_ms);
+            self.queue_action(&action, ctx).await;
+        }
+        Ok(())
+    }
+
+    async fn check_motion_actions(
+        &mut self,
+        current: &ControllerState,
+        ctx: &TemplateContext,
+    ) -> Result<()> {
+        let now_ms = self.started.elapsed().as_millis() as u64;
+        let events = self.motion.update(
+            current.gyroscope.to_rad_per_sec(),
+            current.accelerometer.to_g(),
+            current.orientation,
+            now_ms,
+        );
+
+        for event in events {
+            let Some(action) = self.config.motion.event_action(event).cloned() else {
+                continue;
+            };
+            let key = format!("motion_{}", event.name());
+            if self.debounce.can_trigger(&key, action.debounce_ms) {
+                debug!("Motion event: {}", event.name());
+                let ctx = self.events.fire_event("motion", event.name(), ctx, now_ms);
+                self.queue_action(&action, ctx).await;
             }
         }
 
         Ok(())
     }
 
-    async fn check_stick_actions(
+    async fn check_touchpad_actions(
         &mut self,
         prev: &ControllerState,
         current: &ControllerState,
         ctx: &TemplateContext,
     ) -> Result<()> {
-        let deadzone = self.config.deadzone;
-        let mut actions_to_execute: Vec<ActionConfig> = Vec::new();
+        let now_ms = self.started.elapsed().as_millis() as u64;
+        let gestures = self.gestures.update(&current.touchpad, now_ms);
 
-        // Left stick - collect actions
-        if let Some(mapping) = &self.config.analog.left_stick {
-            let (px, py) = prev.left_stick.normalized_with_deadzone(deadzone);
-            let (cx, cy) = current.left_stick.normalized_with_deadzone(deadzone);
-            let threshold = mapping.threshold;
-            let rate_limit = mapping.rate_limit_ms;
-
-            // Directional triggers
-            if let Some(action) = &mapping.on_right {
-                if px <= threshold && cx > threshold {
-                    actions_to_execute.push(action.clone());
-                }
-            }
-            if let Some(action) = &mapping.on_left {
-                if px >= -threshold && cx < -threshold {
-                    actions_to_execute.push(action.clone());
-                }
-            }
-            if let Some(action) = &mapping.on_up {
-                if py >= -threshold && cy < -threshold {
-                    // Y is inverted
-                    actions_to_execute.push(action.clone());
-                }
-            }
-            if let Some(action) = &mapping.on_down {
-                if py <= threshold && cy > threshold {
-                    actions_to_execute.push(action.clone());
-                }
+        for gesture in gestures {
+            let Some(action) = self.config.touchpad.gesture_action(gesture).cloned() else {
+                continue;
+            };
+            let key = format!("touchpad_{}", gesture.name());
+            if self.debounce.can_trigger(&key, action.debounce_ms) {
+                debug!("Touchpad gesture: {}", gesture.name());
+                let ctx = self.events.fire_event("touchpad_gesture", gesture.name(), ctx, now_ms);
+                self.queue_action(&action, ctx).await;
             }
+        }
 
-            // Continuous movement - check debounce separately
-            if let Some(action) = &mapping.on_move {
-                if (cx != 0.0 || cy != 0.0)
-                    && self.debounce.can_trigger("left_stick_move", rate_limit)
-                {
-                    actions_to_execute.push(action.clone());
-                }
+        // Regions behave like extra buttons; taken out of the config for the
+        // loop rather than cloned on every poll
+        let regions = std::mem::take(&mut self.config.touchpad.regions);
+        let mut result = Ok(());
+        for region in &regions {
+            let was_pressed = region.rect().is_pressed(
+                &prev.touchpad,
+                prev.buttons.touchpad,
+                region.require_click,
+            );
+            let is_pressed = region.rect().is_pressed(
+                &current.touchpad,
+                current.buttons.touchpad,
+                region.require_click,
+            );
+            let key = format!("touchpad_region_{}", region.name);
+            result = self
+                .check_edge_action(&key, &region.action, was_pressed, is_pressed, ctx)
+                .await;
+            if result.is_err() {
+                break;
             }
         }
+        self.config.touchpad.regions = regions;
 
-        // Right stick - collect actions
-        if let Some(mapping) = &self.config.analog.right_stick {
-            let (px, py) = prev.right_stick.normalized_with_deadzone(deadzone);
-            let (cx, cy) = current.right_stick.normalized_with_deadzone(deadzone);
-            let threshold = mapping.threshold;
-            let rate_limit = mapping.rate_limit_ms;
-
-            if let Some(action) = &mapping.on_right {
-                if px <= threshold && cx > threshold {
-                    actions_to_execute.push(action.clone());
-                }
-            }
-            if let Some(action) = &mapping.on_left {
-                if px >= -threshold && cx < -threshold {
-                    actions_to_execute.push(action.clone());
-                }
-            }
-            if let Some(action) = &mapping.on_up {
-                if py >= -threshold && cy < -threshold {
-                    actions_to_execute.push(action.clone());
-                }
-            }
-            if let Some(action) = &mapping.on_down {
-                if py <= threshold && cy > threshold {
-                    actions_to_execute.push(action.clone());
-                }
-            }
+        result
+    }
 
-            if let Some(action) = &mapping.on_move {
-                if (cx != 0.0 || cy != 0.0)
-                    && self.debounce.can_trigger("right_stick_move", rate_limit)
-                {
-                    actions_to_execute.push(action.clone());
-                }
-            }
+    async fn check_stick_actions(
+        &mut self,
+        current: &ControllerState,
+        ctx: &TemplateContext,
+    ) -> Result<()> {
+        let deadzone = self.config.deadzone;
+        let linear = ResponseCurve::default();
+        let mut actions_to_execute = Vec::new();
+
+        if let Some(mapping) = &self.config.analog.left_stick {
+            let position = self.curves.left_stick(
+                current.left_stick.normalized_with_deadzone(deadzone),
+                &linear,
+            );
+            actions_to_execute.extend(stick_actions(
+                "left_stick",
+                mapping,
+                &mut self.left_stick,
+                position,
+                &mut self.debounce,
+            ));
+        }
+
+        if let Some(mapping) = &self.config.analog.right_stick {
+            let position = self.curves.right_stick(
+                current.right_stick.normalized_with_deadzone(deadzone),
+                &linear,
+            );
+            actions_to_execute.extend(stick_actions(
+                "right_stick",
+                mapping,
+                &mut self.right_stick,
+                position,
+                &mut self.debounce,
+            ));
         }
 
         // Execute collected actions
-        for action in actions_to_execute {
-            self.execute_action(&action, ctx).await?;
+        let now_ms = self.started.elapsed().as_millis() as u64;
+        for (action, source, event_type) in actions_to_execute {
+            let ctx = self.events.fire(source, event_type, ctx, now_ms);
+            self.queue_action(&action, ctx).await;
         }
 
         Ok(())
@@ -362,59 +917,274 @@ impl Executor {
         current: &ControllerState,
         ctx: &TemplateContext,
     ) -> Result<()> {
-        let (pl2, pr2) = prev.triggers.normalized();
-        let (cl2, cr2) = current.triggers.normalized();
+        let linear = ResponseCurve::default();
+        let (pl2, pr2) = self.curves.triggers(prev.triggers.normalized(), &linear);
+        let (cl2, cr2) = self.curves.triggers(current.triggers.normalized(), &linear);
 
         // Collect actions to execute (to avoid borrow issues)
-        let mut actions_to_execute: Vec<ActionConfig> = Vec::new();
+        let mut actions_to_execute = Vec::new();
 
-        // L2 trigger
         if let Some(mapping) = &self.config.analog.l2_trigger {
-            let threshold = mapping.threshold;
-
-            if let Some(action) = &mapping.on_press {
-                if pl2 < threshold && cl2 >= threshold {
-                    actions_to_execute.push(action.clone());
+            let zone = self.l2_trigger.zone();
+            actions_to_execute.extend(trigger_actions(
+                "l2_trigger",
+                mapping,
+                &mut self.l2_trigger,
+                pl2,
+                cl2,
+            ));
+            if let Some(force) = mapping.detent_force {
+                if self.detents_stale || zone != self.l2_trigger.zone() {
+                    let effect = self.l2_trigger.detent_effect(force);
+                    self.controller_cmd_tx
+                        .send(ControllerCommand::SetL2Effect(effect))
+                        .await
+                        .ok();
                 }
             }
+        }
 
-            if let Some(action) = &mapping.on_change {
-                if (pl2 - cl2).abs() > 0.01 {
-                    actions_to_execute.push(action.clone());
+        if let Some(mapping) = &self.config.analog.r2_trigger {
+            let zone = self.r2_trigger.zone();
+            actions_to_execute.extend(trigger_actions(
+                "r2_trigger",
+                mapping,
+                &mut self.r2_trigger,
+                pr2,
+                cr2,
+            ));
+            if let Some(force) = mapping.detent_force {
+                if self.detents_stale || zone != self.r2_trigger.zone() {
+                    let effect = self.r2_trigger.detent_effect(force);
+                    self.controller_cmd_tx
+                        .send(ControllerCommand::SetR2Effect(effect))
+                        .await
+                        .ok();
                 }
             }
         }
+        self.detents_stale = false;
 
-        // R2 trigger
-        if let Some(mapping) = &self.config.analog.r2_trigger {
-            let threshold = mapping.threshold;
+        // Execute collected actions
+        let now_ms = self.started.elapsed().as_millis() as u64;
+        for (action, source, event_type) in actions_to_execute {
+            let ctx = self.events.fire(source, &event_type, ctx, now_ms);
+            self.queue_action(&action, ctx).await;
+        }
 
-            if let Some(action) = &mapping.on_press {
-                if pr2 < threshold && cr2 >= threshold {
-                    actions_to_execute.push(action.clone());
-                }
+        Ok(())
+    }
+
+    /// Whether a state stream (`state_interval_ms` of `websocket`, `osc`,
+    /// `mqtt` or a socket) is due for an update
+    pub fn state_due(&self) -> bool {
+        self.state_streams
+            .iter()
+            .any(|schedule| schedule.last.elapsed() >= schedule.interval)
+    }
+
+    /// Queue a state update for every stream that is due. Updates of a
+    /// stream share a key, so `coalesce` keeps only the newest.
+    pub async fn send_state_update(&mut self, ctx: &TemplateContext) -> Result<()> {
+        let mut ctx = ctx.clone();
+        self.shape_analog(&mut ctx);
+
+        let mut due = Vec::new();
+        for schedule in &mut self.state_streams {
+            if schedule.last.elapsed() >= schedule.interval {
+                schedule.last = Instant::now();
+                due.push(schedule.stream.clone());
+            }
+        }
+        for stream in due {
+            let key = stream.key();
+            self.push(key, Job::StateUpdate(stream, ctx.clone()), true)
+                .await;
+        }
+        Ok(())
+    }
+}
+
+impl ActionRunner {
+    /// Run jobs until the queue is closed and drained, then kill commands
+    /// still running. A failing action is logged and flashes the error
+    /// color; the next job runs regardless.
+    async fn run(mut self) {
+        while let Some((key, job)) = self.run_until_exclusive().await {
+            if let Err(e) = self.handle_exclusive(job).await {
+                self.report(e).await;
             }
+            self.queue.done(&key);
+        }
+        self.processes.shutdown().await;
+        self.flush_vars();
+        if let Some(mqtt) = self.mqtt.take() {
+            mqtt.shutdown().await;
+        }
+    }
 
-            if let Some(action) = &mapping.on_change {
-                if (pr2 - cr2).abs() > 0.01 {
-                    actions_to_execute.push(action.clone());
+    /// Run jobs, one per key and up to `pipeline.workers` at once, until a
+    /// job that changes the runner comes up (returned; the queue hands it
+    /// out once nothing else runs) or the queue is closed and drained
+    async fn run_until_exclusive(&self) -> Option<(String, Job)> {
+        let mut running = FuturesUnordered::new();
+        loop {
+            // Wake up to write variables once their persist delay is up
+            let flush_in = self.vars.lock().unwrap().flush_in();
+            tokio::select! {
+                Some(()) = running.next() => {}
+                popped = self.queue.pop() => match popped {
+                    Some((key, job)) if job.is_exclusive() => return Some((key, job)),
+                    Some((key, job)) => running.push(async move {
+                        if let Err(e) = self.handle(job).await {
+                            self.report(e).await;
+                        }
+                        self.queue.done(&key);
+                    }),
+                    None => {
+                        while running.next().await.is_some() {}
+                        return None;
+                    }
+                },
+                _ = tokio::time::sleep(flush_in.unwrap_or_default()), if flush_in.is_some() => {
+                    self.flush_vars();
                 }
             }
         }
+    }
 
-        // Execute collected actions
-        for action in actions_to_execute {
-            self.execute_action(&action, ctx).await?;
+    /// Log a failed job and flash the error color
+    async fn report(&self, e: anyhow::Error) {
+        error!("Error running action: {}", e);
+        if let Some(color) = &self.config.led.error_color {
+            self.controller_cmd_tx
+                .send(ControllerCommand::SetLed(color.r, color.g, color.b))
+                .await
+                .ok();
+        }
+    }
+
+    fn flush_vars(&self) {
+        if let Err(e) = self.vars.lock().unwrap().flush() {
+            warn!("{:#}", e);
+        }
+    }
+
+    async fn handle(&self, job: Job) -> Result<()> {
+        match job {
+            Job::Action { action, ctx } => self.execute_action(&action, &ctx).await?,
+            Job::StateUpdate(stream, ctx) => {
+                let ctx = self.with_vars(&ctx);
+                let sent = match stream {
+                    StateStream::WebSocket => self.send_state_update(&ctx).await,
+                    StateStream::Osc => match &self.config.osc {
+                        Some(osc) => self.send_osc(&osc.state_messages, &ctx).await,
+                        None => Ok(()),
+                    },
+                    StateStream::Mqtt => self.publish_mqtt_state(&ctx),
+                    StateStream::Socket(name) => self.send_socket_state(&name, &ctx).await,
+                };
+                if let Err(e) = sent {
+                    debug!("Error sending state update: {}", e);
+                }
+            }
+            Job::SetVar(name, value) => self.vars.lock().unwrap().set(&name, value),
+            Job::Midi(messages) => {
+                if let Some(port) = self.midi.lock().unwrap().as_mut() {
+                    for message in &messages {
+                        port.send(message)?;
+                    }
+                }
+            }
+            Job::Reconfigure(_) | Job::SetWsSender(_) => {
+                unreachable!("jobs that change the runner go through `handle_exclusive`")
+            }
         }
+        Ok(())
+    }
+
+    /// Run a job that changes the runner; nothing else runs meanwhile
+    async fn handle_exclusive(&mut self, job: Job) -> Result<()> {
+        match job {
+            Job::Reconfigure(reload) => {
+                let Reload {
+                    config,
+                    templates,
+                    mqtt,
+                    midi,
+                } = *reload;
+                // The new settings apply even if the old file can't be written
+                if let Err(e) = self
+                    .vars
+                    .get_mut()
+                    .unwrap()
+                    .reconfigure(&config.variables.initial, persist_path(&config))
+                {
+                    warn!("{:#}", e);
+                }
+                self.http_client = build_http_client(&config);
+                self.osc = OnceCell::new();
+                self.templates = templates;
+                self.config = config;
+
+                // Keep the connections of sockets whose settings are unchanged
+                let sockets = &self.config.sockets;
+                self.sockets
+                    .retain(|name, sink| sockets.get(name) == Some(sink.get_mut().config()));
+                for (name, socket) in sockets {
+                    self.sockets
+                        .entry(name.clone())
+                        .or_insert_with(|| Mutex::new(SocketSink::new(name, socket)));
+                }
 
+                if let Some(mqtt) = mqtt {
+                    if let Some(old) = std::mem::replace(&mut self.mqtt, mqtt) {
+                        old.shutdown().await;
+                    }
+                }
+                if let Some(midi) = midi {
+                    *self.midi.get_mut().unwrap() = midi;
+                }
+            }
+            Job::SetWsSender(sender) => self.ws_sender = Some(sender),
+            job => return self.handle(job).await,
+        }
         Ok(())
     }
 
+    /// Copy of a template context with the current variables filled in
+    fn with_vars(&self, ctx: &TemplateContext) -> TemplateContext {
+        let mut ctx = ctx.clone();
+        ctx.vars = self.vars.lock().unwrap().snapshot();
+        ctx
+    }
+
     /// Execute an action
-    async fn execute_action(&mut self, action: &ActionConfig, ctx: &TemplateContext) -> Result<()> {
+    async fn execute_action(&self, action: &ActionConfig, ctx: &TemplateContext) -> Result<()> {
+        if let Some(condition) = &action.when {
+            if !self.vars.lock().unwrap().matches(condition) {
+                trace!("Skipping action, condition on `{}` not met", condition.var);
+                return Ok(());
+            }
+        }
+
+        // Variable updates come first so the rest of the action sees them
+        if !action.vars.is_empty() {
+            let ctx = self.with_vars(ctx);
+            let mut vars = self.vars.lock().unwrap();
+            for op in &action.vars {
+                vars.apply(op, |template| {
+                    self.templates
+                        .render(template, &ctx)
+                        .context("Failed to render variable value")
+                })?;
+            }
+        }
+        let ctx = &self.with_vars(ctx);
+
         // Shell command
-        if let Some(cmd_template) = &action.command {
-            self.execute_shell_command(cmd_template, ctx).await?;
+        if let Some(command) = &action.command {
+            self.execute_shell_command(command, action.process.as_ref(), ctx)?;
         }
 
         // WebSocket message
@@ -426,6 +1196,29 @@ impl Executor {
         if let Some(http_req) = &action.http {
             self.execute_http_request(http_req, ctx).await?;
         }
+
+        // OSC message or bundle
+        if let Some(osc) = &action.osc {
+            self.send_osc(osc.messages(), ctx).await?;
+        }
+
+        // MQTT message
+        if let Some(mqtt_msg) = &action.mqtt {
+            self.publish_mqtt(mqtt_msg, ctx)?;
+        }
+
+        // Raw socket messages
+        for (name, socket_msg) in &action.sockets {
+            self.send_socket_message(name, socket_msg, ctx).await?;
+        }
+
+        // Virtual keyboard and mouse
+        if let Some(key) = &action.key {
+            self.emit_input(&desktop::key_frames(key)?)?;
+        }
+        if let Some(mouse) = &action.mouse {
+            self.emit_input(&desktop::mouse_frames(mouse))?;
+        }
         // [TODO] Doesn't seem to work on macOS
         // Rumble feedback
         if let Some(rumble) = &action.rumble {
@@ -440,68 +1233,97 @@ impl Executor {
                 .ok();
         }
 
+        if let Some(blink) = &action.led_blink {
+            self.controller_cmd_tx
+                .send(ControllerCommand::BlinkLed(
+                    blink.r,
+                    blink.g,
+                    blink.b,
+                    blink.count,
+                    blink.interval_ms,
+                ))
+                .await
+                .ok();
+        }
+
         Ok(())
     }
 
-    async fn execute_shell_command(
+    /// Start a command; it runs on its own and reports back when it exits.
+    /// Concurrency limits count runs of the action, i.e. the input and event
+    /// that fired it, not the rendered command line.
+    fn execute_shell_command(
         &self,
-        cmd_template: &str,
+        command: &ShellCommand,
+        process: Option<&ProcessConfig>,
         ctx: &TemplateContext,
     ) -> Result<()> {
-        let cmd = self
-            .handlebars
-            .render_template(cmd_template, ctx)
-            .context("Failed to render command template")?;
-
-        debug!("Executing shell command: {}", cmd);
-
-        let shell = self
-            .config
-            .shell
-            .shell
-            .as_deref()
-            .unwrap_or(if cfg!(windows) { "cmd" } else { "/bin/sh" });
-
-        let shell_arg = if cfg!(windows) { "/C" } else { "-c" };
-
-        let mut command = Command::new(shell);
-        command.arg(shell_arg).arg(&cmd);
+        let (program, args) = match command {
+            ShellCommand::Line(template) => {
+                let cmd = self
+                    .templates
+                    .render(template, ctx)
+                    .context("Failed to render command template")?;
+
+                debug!("Executing shell command: {}", cmd);
+
+                let shell = self
+                    .config
+                    .shell
+                    .shell
+                    .as_deref()
+                    .unwrap_or(if cfg!(windows) { "cmd" } else { "/bin/sh" });
+
+                let shell_arg = if cfg!(windows) { "/C" } else { "-c" };
+                (shell.to_string(), vec![shell_arg.to_string(), cmd])
+            }
+            ShellCommand::Argv(templates) => {
+                let mut argv = templates
+                    .iter()
+                    .map(|template| self.templates.render(template, ctx))
+                    .collect::<Result<Vec<_>>>()
+                    .context("Failed to render command argument")?;
+                if argv.is_empty() {
+                    anyhow::bail!("Command list is empty");
+                }
 
-        if let Some(dir) = &self.config.shell.working_dir {
-            command.current_dir(dir);
-        }
+                debug!("Executing command: {:?}", argv);
+                let program = argv.remove(0);
+                (program, argv)
+            }
+        };
 
-        for (key, value) in &self.config.shell.env {
-            command.env(key, value);
-        }
+        let process = process.cloned().unwrap_or_default();
+        let spec = ProcessSpec {
+            program,
+            args,
+            working_dir: self.config.shell.working_dir.clone(),
+            env: self
+                .config
+                .shell
+                .env
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            timeout: process.timeout_ms.map(Duration::from_millis),
+            capture: process.captures(),
+        };
+        let limit = process.max_concurrent.map(|max| (max, process.on_limit));
 
-        // Spawn and don't wait (fire and forget for non-blocking)
-        match command.spawn() {
-            Ok(mut child) => {
-                // Optionally wait for short commands
-                tokio::spawn(async move {
-                    match child.wait() {
-                        Ok(status) => {
-                            if !status.success() {
-                                warn!("Command exited with status: {}", status);
-                            }
-                        }
-                        Err(e) => {
-                            error!("Failed to wait for command: {}", e);
-                        }
-                    }
-                });
-            }
-            Err(e) => {
-                error!("Failed to spawn command: {}", e);
-            }
+        let queue = self.queue.clone();
+        let key = format!("{}:{}", ctx.event_source, ctx.event_type);
+        let started = self.processes.spawn(&key, spec, limit, move |output| {
+            command_finished(&output, &process, &queue);
+        });
+        if !started {
+            debug!("Command already running, skipping");
         }
 
         Ok(())
     }
 
     async fn send_websocket_message(
-        &mut self,
+        &self,
         ws_msg: &WebSocketMessage,
         ctx: &TemplateContext,
     ) -> Result<()> {
@@ -511,8 +1333,8 @@ impl Executor {
         };
 
         let content = self
-            .handlebars
-            .render_template(&ws_msg.message, ctx)
+            .templates
+            .render(&ws_msg.message, ctx)
             .context("Failed to render WebSocket message template")?;
 
         let message = if ws_msg.binary {
@@ -546,21 +1368,24 @@ impl Executor {
             return Ok(());
         };
 
-        let url = format!("{}{}", http_config.base_url, http_req.path);
-        let url = self
-            .handlebars
-            .render_template(&url, ctx)
+        let base_url = self
+            .templates
+            .render(&http_config.base_url, ctx)
+            .context("Failed to render base URL template")?;
+        let path = self
+            .templates
+            .render(&http_req.path, ctx)
             .context("Failed to render URL template")?;
+        let url = format!("{}{}", base_url, path);
 
         debug!("HTTP {} {}", http_req.method, url);
 
-        let mut request = match http_req.method.to_uppercase().as_str() {
-            "GET" => client.get(&url),
-            "POST" => client.post(&url),
-            "PUT" => client.put(&url),
-            "DELETE" => client.delete(&url),
-            "PATCH" => client.patch(&url),
-            _ => client.post(&url),
+        let mut request = match http_req.method {
+            HttpMethod::Get => client.get(&url),
+            HttpMethod::Post => client.post(&url),
+            HttpMethod::Put => client.put(&url),
+            HttpMethod::Delete => client.delete(&url),
+            HttpMethod::Patch => client.patch(&url),
         };
 
         // Add default headers
@@ -576,30 +1401,46 @@ impl Executor {
         // Add body if present
         if let Some(body_template) = &http_req.body {
             let body = self
-                .handlebars
-                .render_template(body_template, ctx)
+                .templates
+                .render(body_template, ctx)
                 .context("Failed to render body template")?;
             request = request.body(body);
         }
 
-        // Fire and forget
+        if !http_req.query.is_empty() {
+            let query = http_req
+                .query
+                .iter()
+                .map(|(name, template)| {
+                    self.templates
+                        .render(template, ctx)
+                        .map(|value| (name, value))
+                })
+                .collect::<Result<Vec<_>>>()
+                .context("Failed to render query parameter")?;
+            request = request.query(&query);
+        }
+
+        if let Some(timeout_ms) = http_req.timeout_ms {
+            request = request.timeout(Duration::from_millis(timeout_ms));
+        }
+
+        if let Some(auth) = http_req.auth.as_ref().or(http_config.auth.as_ref()) {
+            request = http::apply_auth(request, auth)?;
+        }
+
+        // Delivered within the job, so requests of one input stay in order
         let request = request.build()?;
-        let client = client.clone();
-        tokio::spawn(async move {
-            match client.execute(request).await {
-                Ok(response) => {
-                    if !response.status().is_success() {
-                        warn!("HTTP request failed with status: {}", response.status());
-                    }
-                }
-                Err(e) => {
-                    error!("HTTP request failed: {}", e);
-                }
-            }
-        });
+        let retries = http_req.allowed_retries();
+        let backoff = Duration::from_millis(http_req.retry_backoff_ms);
+        let outcome = http::send_with_retries(client, request, retries, backoff).await;
+        if let Some(response) = &http_req.response {
+            http_finished(&outcome, response, &self.controller_cmd_tx, &self.queue).await;
+        }
 
         Ok(())
     }
+
     /// [TODO] Doesn't seem to work on macOS
     async fn trigger_rumble(&self, rumble: &RumbleConfig) -> Result<()> {
         self.controller_cmd_tx
@@ -613,8 +1454,140 @@ impl Executor {
         Ok(())
     }
 
+    /// Send OSC messages, several as one bundle
+    async fn send_osc(
+        &self,
+        messages: &[OscMessageConfig],
+        ctx: &TemplateContext,
+    ) -> Result<()> {
+        let Some(osc_config) = &self.config.osc else {
+            warn!("OSC not configured");
+            return Ok(());
+        };
+
+        // Serialized once per packet; field arguments and templates all read it
+        let fields = serde_json::to_value(ctx).context("Failed to serialize template context")?;
+        let mut built = messages
+            .iter()
+            .map(|message| {
+                osc::build_message(message, &fields, |template| {
+                    self.templates.render(template, &fields)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let packet = match built.len() {
+            0 => return Ok(()),
+            1 => OscPacket::Message(built.remove(0)),
+            _ => OscPacket::Bundle(built),
+        };
+
+        let sender = self
+            .osc
+            .get_or_try_init(|| OscSender::bind(&osc_config.bind, &osc_config.target))
+            .await?;
+        sender.send(&packet).await?;
+
+        trace!("Sent OSC packet");
+        Ok(())
+    }
+
+    fn publish_mqtt(&self, mqtt_msg: &MqttMessage, ctx: &TemplateContext) -> Result<()> {
+        let Some(mqtt) = &self.mqtt else {
+            warn!("MQTT not configured");
+            return Ok(());
+        };
+
+        let topic = self
+            .templates
+            .render(&mqtt_msg.topic, ctx)
+            .context("Failed to render MQTT topic template")?;
+        let payload = self
+            .templates
+            .render(&mqtt_msg.payload, ctx)
+            .context("Failed to render MQTT payload template")?;
+
+        mqtt.publish(&topic, payload.into_bytes(), mqtt_msg.qos, mqtt_msg.retain)?;
+        trace!("Published MQTT message to {}", topic);
+        Ok(())
+    }
+
+    /// Publish state to `mqtt.state_topic` (for streaming)
+    fn publish_mqtt_state(&self, ctx: &TemplateContext) -> Result<()> {
+        let (Some(mqtt), Some(mqtt_config)) = (&self.mqtt, &self.config.mqtt) else {
+            return Ok(());
+        };
+        let (Some(topic), Some(format)) = (&mqtt_config.state_topic, &mqtt_config.state_format)
+        else {
+            return Ok(());
+        };
+
+        let topic = self
+            .templates
+            .render(topic, ctx)
+            .context("Failed to render MQTT state topic")?;
+        let payload = self
+            .templates
+            .render(format, ctx)
+            .context("Failed to render state format")?;
+
+        mqtt.publish(&topic, payload.into_bytes(), mqtt_config.state_qos, false)
+    }
+
+    async fn send_socket_message(
+        &self,
+        name: &str,
+        socket_msg: &WebSocketMessage,
+        ctx: &TemplateContext,
+    ) -> Result<()> {
+        let content = self
+            .templates
+            .render(&socket_msg.message, ctx)
+            .context("Failed to render socket message template")?;
+
+        let Some(sink) = self.sockets.get(name) else {
+            warn!("Socket `{}` not configured", name);
+            return Ok(());
+        };
+        if sink.lock().await.send(content.as_bytes(), socket_msg.binary).await? {
+            trace!("Sent message to socket `{}`", name);
+        } else {
+            trace!("Socket `{}` not connected, skipping message", name);
+        }
+        Ok(())
+    }
+
+    /// Send state to a raw socket (for streaming)
+    async fn send_socket_state(&self, name: &str, ctx: &TemplateContext) -> Result<()> {
+        let Some(format) = self
+            .config
+            .sockets
+            .get(name)
+            .and_then(|socket| socket.state_format.as_ref())
+        else {
+            return Ok(());
+        };
+        let content = self
+            .templates
+            .render(format, ctx)
+            .context("Failed to render state format")?;
+
+        let Some(sink) = self.sockets.get(name) else {
+            return Ok(());
+        };
+        let mut sink = sink.lock().await;
+        let binary = sink.config().binary;
+        sink.send(content.as_bytes(), binary).await?;
+        Ok(())
+    }
+
+    /// Write frames to the virtual keyboard and mouse, creating it first
+    /// if needed
+    fn emit_input(&self, frames: &[Frame]) -> Result<()> {
+        emit_input(&self.input, frames)
+    }
+
     /// Send raw state via WebSocket (for streaming)
-    pub async fn send_state_update(&mut self, ctx: &TemplateContext) -> Result<()> {
+    async fn send_state_update(&self, ctx: &TemplateContext) -> Result<()> {
         let Some(ws_config) = &self.config.websocket else {
             return Ok(());
         };
@@ -628,8 +1601,8 @@ impl Executor {
         };
 
         let content = self
-            .handlebars
-            .render_template(format, ctx)
+            .templates
+            .render(format, ctx)
             .context("Failed to render state format")?;
 
         let message = if ws_config.binary {
@@ -644,3 +1617,245 @@ impl Executor {
         Ok(())
     }
 }
+
+/// Actions of one stick for this frame: direction changes and rate-limited moves
+fn stick_actions(
+    name: &'static str,
+    mapping: &StickMapping,
+    processor: &mut StickProcessor,
+    (x, y): (f32, f32),
+    debounce: &mut DebounceState,
+) -> Vec<(ActionConfig, &'static str, &'static str)> {
+    let mut actions = Vec::new();
+
+    if let Some(event) = processor.update(x, y) {
+        if let Some(action) = mapping.event_action(event) {
+            actions.push((action.clone(), name, event.name()));
+        }
+    }
+
+    // Continuous movement - check debounce separately
+    if let Some(action) = &mapping.on_move {
+        let key = format!("{}_move", name);
+        if (x != 0.0 || y != 0.0) && debounce.can_trigger(&key, mapping.rate_limit_ms) {
+            actions.push((action.clone(), name, "move"));
+        }
+    }
+
+    actions
+}
+
+/// Actions of one trigger for this frame: press/release, zones and changes
+fn trigger_actions(
+    name: &'static str,
+    mapping: &TriggerMapping,
+    processor: &mut TriggerProcessor,
+    prev: f32,
+    current: f32,
+) -> Vec<(ActionConfig, &'static str, String)> {
+    let mut actions = Vec::new();
+
+    for event in processor.update(current) {
+        if let Some(action) = mapping.event_action(event) {
+            actions.push((action.clone(), name, mapping.event_name(event)));
+        }
+    }
+
+    if let Some(action) = &mapping.on_change {
+        if (prev - current).abs() > 0.01 {
+            actions.push((action.clone(), name, "change".to_string()));
+        }
+    }
+
+    actions
+}
+
+/// Press and zone recognizer for a trigger mapping (idle when unmapped)
+fn trigger_processor(mapping: Option<&TriggerMapping>) -> TriggerProcessor {
+    TriggerProcessor::new(trigger_settings(mapping))
+}
+
+fn trigger_settings(mapping: Option<&TriggerMapping>) -> TriggerSettings {
+    mapping
+        .map(TriggerMapping::trigger_settings)
+        .unwrap_or_default()
+}
+
+fn detent_force(mapping: &Option<TriggerMapping>) -> Option<u8> {
+    mapping.as_ref().and_then(|m| m.detent_force)
+}
+
+/// Direction recognizer for a stick mapping (idle when unmapped)
+fn stick_processor(mapping: Option<&StickMapping>) -> StickProcessor {
+    StickProcessor::new(stick_settings(mapping))
+}
+
+fn stick_settings(mapping: Option<&StickMapping>) -> StickSettings {
+    mapping
+        .map(StickMapping::stick_settings)
+        .unwrap_or_default()
+}
+
+/// Apply an HTTP response: feedback for its status class, then variables
+async fn http_finished(
+    outcome: &HttpOutcome,
+    response: &HttpResponseConfig,
+    controller_cmd_tx: &mpsc::Sender<ControllerCommand>,
+    queue: &JobQueue<Job>,
+) {
+    if let Some(feedback) = response.feedback(outcome.status) {
+        for command in feedback_commands(feedback) {
+            controller_cmd_tx.send(command).await.ok();
+        }
+    }
+
+    let mut values = Vec::new();
+    if let (Some(var), Some(status)) = (&response.status_var, outcome.status) {
+        values.push((var.clone(), status.into()));
+    }
+    let success = outcome
+        .status
+        .is_some_and(|status| (200..300).contains(&status));
+    if success && !response.extract.is_empty() {
+        match serde_json::from_str(&outcome.body) {
+            Ok(body) => values.extend(http::extract(&body, &response.extract)),
+            Err(e) => warn!("HTTP response is not JSON, nothing extracted: {}", e),
+        }
+    }
+    for (var, value) in values {
+        queue.push_unbounded("vars".to_string(), Job::SetVar(var, value));
+    }
+}
+
+fn feedback_commands(feedback: &HttpFeedback) -> Vec<ControllerCommand> {
+    let mut commands = Vec::new();
+    if let Some(led) = &feedback.led {
+        commands.push(ControllerCommand::SetLed(led.r, led.g, led.b));
+    }
+    if let Some(blink) = &feedback.led_blink {
+        commands.push(ControllerCommand::BlinkLed(
+            blink.r,
+            blink.g,
+            blink.b,
+            blink.count,
+            blink.interval_ms,
+        ));
+    }
+    if let Some(rumble) = &feedback.rumble {
+        commands.push(ControllerCommand::SetRumble(
+            rumble.left,
+            rumble.right,
+            rumble.duration_ms,
+        ));
+    }
+    commands
+}
+
+/// Write frames to the virtual keyboard and mouse, creating it first
+fn emit_input(
+    input: &std::sync::Mutex<Option<Box<dyn EventSink>>>,
+    frames: &[Frame],
+) -> Result<()> {
+    let mut input = input.lock().unwrap();
+    if input.is_none() {
+        let spec = DeviceSpec::keyboard_mouse(VIRTUAL_INPUT_NAME);
+        info!("Creating virtual keyboard and mouse `{}`", spec.name);
+        *input = Some(Box::new(UinputDevice::create(&spec)?));
+    }
+    match input.as_mut() {
+        Some(sink) => desktop::emit_all(sink.as_mut(), frames),
+        None => Ok(()),
+    }
+}
+
+/// Log how a command ended and hand its captured output to the runner
+fn command_finished(output: &ProcessOutput, process: &ProcessConfig, queue: &JobQueue<Job>) {
+    if output.timed_out {
+        warn!(
+            "Command timed out after {} ms and was killed",
+            process.timeout_ms.unwrap_or_default()
+        );
+    } else if output.killed {
+        debug!("Command killed");
+    } else if !output.success {
+        match output.code {
+            Some(code) => warn!("Command exited with status: {}", code),
+            None => warn!("Command did not exit normally"),
+        }
+    }
+
+    if process.log_output {
+        for line in output.stdout.lines() {
+            info!("[stdout] {}", line);
+        }
+        for line in output.stderr.lines() {
+            info!("[stderr] {}", line);
+        }
+    }
+
+    for (var, text) in [
+        (&process.stdout_var, &output.stdout),
+        (&process.stderr_var, &output.stderr),
+    ] {
+        if let Some(var) = var {
+            queue.push_unbounded(
+                "vars".to_string(),
+                Job::SetVar(var.clone(), vars::parse_rendered(text.trim_end())),
+            );
+        }
+    }
+}
+
+/// State streams the config enables
+fn state_schedules(config: &Config) -> Vec<StateSchedule> {
+    let mut streams = Vec::new();
+    if let Some(ws) = &config.websocket {
+        if ws.state_format.is_some() && ws.state_interval_ms > 0 {
+            streams.push((StateStream::WebSocket, ws.state_interval_ms));
+        }
+    }
+    if let Some(osc) = &config.osc {
+        if !osc.state_messages.is_empty() && osc.state_interval_ms > 0 {
+            streams.push((StateStream::Osc, osc.state_interval_ms));
+        }
+    }
+    if let Some(mqtt) = &config.mqtt {
+        if mqtt.state_topic.is_some() && mqtt.state_format.is_some() && mqtt.state_interval_ms > 0 {
+            streams.push((StateStream::Mqtt, mqtt.state_interval_ms));
+        }
+    }
+    for (name, socket) in &config.sockets {
+        if socket.state_format.is_some() && socket.state_interval_ms > 0 {
+            streams.push((StateStream::Socket(name.clone()), socket.state_interval_ms));
+        }
+    }
+    streams
+        .into_iter()
+        .map(|(stream, interval_ms)| StateSchedule {
+            stream,
+            interval: Duration::from_millis(interval_ms),
+            last: Instant::now(),
+        })
+        .collect()
+}
+
+fn socket_sinks(config: &Config) -> HashMap<String, Mutex<SocketSink>> {
+    config
+        .sockets
+        .iter()
+        .map(|(name, socket)| (name.clone(), Mutex::new(SocketSink::new(name, socket))))
+        .collect()
+}
+
+fn persist_path(config: &Config) -> Option<PathBuf> {
+    config.variables.persist.as_ref().map(PathBuf::from)
+}
+
+fn build_http_client(config: &Config) -> Option<HttpClient> {
+    config.http.as_ref().map(|http| {
+        HttpClient::builder()
+            .timeout(Duration::from_millis(http.timeout_ms))
+            .build()
+            .expect("Failed to create HTTP client")
+    })
+}
@@ -0,0 +1,189 @@
+//! Config file formats
+//!
+//! Configs and profiles can be written as JSON, TOML or YAML; the format
+//! is picked from the file extension. TOML and YAML allow comments and
+//! avoid escaping quotes inside templates.
+
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Supported file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl FileFormat {
+    /// All formats, in the order their extensions are tried
+    pub const ALL: [FileFormat; 3] = [FileFormat::Json, FileFormat::Toml, FileFormat::Yaml];
+
+    /// Detect the format from a file extension
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+
+        match ext.as_deref() {
+            Some("json") => Ok(FileFormat::Json),
+            Some("toml") => Ok(FileFormat::Toml),
+            Some("yaml") | Some("yml") => Ok(FileFormat::Yaml),
+            _ => bail!(
+                "Unsupported file extension: {} (expected .json, .toml, .yaml or .yml)",
+                path.display()
+            ),
+        }
+    }
+
+    /// Whether the path has a supported extension
+    pub fn is_supported(path: &Path) -> bool {
+        Self::from_path(path).is_ok()
+    }
+
+    /// Canonical file extension
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Json => "json",
+            FileFormat::Toml => "toml",
+            FileFormat::Yaml => "yaml",
+        }
+    }
+
+    /// Parse a document
+    pub fn parse<T: DeserializeOwned>(&self, content: &str) -> Result<T> {
+        Ok(match self {
+            FileFormat::Json => serde_json::from_str(content)?,
+            FileFormat::Toml => toml::from_str(content)?,
+            FileFormat::Yaml => serde_yaml::from_str(content)?,
+        })
+    }
+
+    /// Serialize a document
+    pub fn to_string<T: Serialize>(&self, value: &T) -> Result<String> {
+        Ok(match self {
+            FileFormat::Json => serde_json::to_string_pretty(value)?,
+            FileFormat::Toml => toml::to_string_pretty(value)?,
+            FileFormat::Yaml => serde_yaml::to_string(value)?,
+        })
+    }
+
+    /// Serialize an untyped document.
+    /// TOML has no null, so null entries are dropped for TOML output
+    /// (see [`has_null_items`] for the case where that loses data).
+    pub fn value_to_string(&self, value: &Value) -> Result<String> {
+        match self {
+            FileFormat::Toml => {
+                let mut value = value.clone();
+                strip_nulls(&mut value);
+                self.to_string(&value)
+            }
+            _ => self.to_string(value),
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// Remove null object entries and array items, recursively
+pub fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => {
+            items.retain(|v| !v.is_null());
+            items.iter_mut().for_each(strip_nulls);
+        }
+        _ => {}
+    }
+}
+
+/// Whether a document contains any null values
+pub fn has_nulls(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Object(map) => map.values().any(has_nulls),
+        Value::Array(items) => items.iter().any(has_nulls),
+        _ => false,
+    }
+}
+
+/// Whether any array in a document holds a null item.
+/// Unlike a null object entry (same as unset), dropping one shifts the
+/// array, so such a document can't be written as TOML.
+pub fn has_null_items(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => map.values().any(has_null_items),
+        Value::Array(items) => items.iter().any(|v| v.is_null() || has_null_items(v)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(FileFormat::from_path(Path::new("a.json")).unwrap(), FileFormat::Json);
+        assert_eq!(FileFormat::from_path(Path::new("a.TOML")).unwrap(), FileFormat::Toml);
+        assert_eq!(FileFormat::from_path(Path::new("a.yml")).unwrap(), FileFormat::Yaml);
+        assert!(FileFormat::from_path(Path::new("a.ini")).is_err());
+    }
+
+    /// Every shipped example survives a trip through each format
+    #[test]
+    fn test_example_configs_round_trip() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let Ok(source) = FileFormat::from_path(&path) else {
+                continue;
+            };
+            let content = std::fs::read_to_string(&path).unwrap();
+            let original: Value = source.parse(&content).unwrap();
+            let mut expected = original.clone();
+            strip_nulls(&mut expected);
+
+            for format in FileFormat::ALL {
+                let text = format.value_to_string(&original).unwrap();
+                let mut parsed: Value = format.parse(&text).unwrap();
+                strip_nulls(&mut parsed);
+                assert_eq!(parsed, expected, "{} via {}", path.display(), format);
+            }
+        }
+    }
+
+    #[test]
+    fn test_typed_config_round_trip() {
+        let config = Config::default();
+        let expected = serde_json::to_value(&config).unwrap();
+
+        for format in FileFormat::ALL {
+            let text = format.to_string(&config).unwrap();
+            let parsed: Config = format.parse(&text).unwrap();
+            assert_eq!(serde_json::to_value(&parsed).unwrap(), expected, "{}", format);
+        }
+    }
+
+    #[test]
+    fn test_null_items() {
+        assert!(!has_null_items(&serde_json::json!({ "a": null, "b": [1, 2] })));
+        assert!(has_null_items(&serde_json::json!({ "a": { "b": [1, null] } })));
+        assert!(has_null_items(&serde_json::json!([[null]])));
+    }
+}
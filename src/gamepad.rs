@@ -0,0 +1,478 @@
+//! Virtual Xbox-style gamepad
+//!
+//! Maps each controller state to the state of an Xbox 360-style pad
+//! (remapped buttons, curved and inverted sticks and triggers, gyro added
+//! to a stick) and writes what changed to a uinput device that reports
+//! the Xbox 360 controller's USB ids, so games and SDL pick their usual
+//! mapping. The real controller stays open, so actions and trigger effects
+//! keep working alongside.
+
+use anyhow::Result;
+
+use crate::config::{
+    GamepadButton, GamepadButtonMappings, GamepadConfig, GamepadStick, GamepadTrigger, GyroStick,
+    StickSide, TriggerSide,
+};
+use crate::curve::{AxisCurves, ResponseCurve};
+use crate::dualsense::ControllerState;
+use crate::uinput::{codes, AbsAxis, DeviceSpec, EventSink, Frame, InputEvent, UinputDevice};
+
+/// Xbox 360 controller: Microsoft vendor id, product id, version
+const XBOX_360_ID: (u16, u16, u16) = (0x045e, 0x028e, 0x0110);
+
+const STICK_MAX: i32 = i16::MAX as i32;
+const TRIGGER_MAX: i32 = 255;
+
+/// Buttons in [`PadState::buttons`] bit order
+const BUTTONS: [(GamepadButton, u16); 11] = [
+    (GamepadButton::A, codes::BTN_SOUTH),
+    (GamepadButton::B, codes::BTN_EAST),
+    (GamepadButton::X, codes::BTN_NORTH),
+    (GamepadButton::Y, codes::BTN_WEST),
+    (GamepadButton::Lb, codes::BTN_TL),
+    (GamepadButton::Rb, codes::BTN_TR),
+    (GamepadButton::Back, codes::BTN_SELECT),
+    (GamepadButton::Start, codes::BTN_START),
+    (GamepadButton::Guide, codes::BTN_MODE),
+    (GamepadButton::Ls, codes::BTN_THUMBL),
+    (GamepadButton::Rs, codes::BTN_THUMBR),
+];
+
+/// Axes in [`PadState::axes`] order
+const AXES: [u16; 8] = [
+    codes::ABS_X,
+    codes::ABS_Y,
+    codes::ABS_RX,
+    codes::ABS_RY,
+    codes::ABS_Z,
+    codes::ABS_RZ,
+    codes::ABS_HAT0X,
+    codes::ABS_HAT0Y,
+];
+
+/// Gamepad state in evdev units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PadState {
+    /// One bit per entry of [`BUTTONS`]
+    pub buttons: u16,
+    /// Sticks (-32768..32767, Y down), triggers (0..255) and the d-pad hat
+    /// (-1..1), per entry of [`AXES`]
+    pub axes: [i32; 8],
+}
+
+/// Device the gamepad is created as
+pub fn device_spec(name: &str) -> DeviceSpec {
+    let stick = |code| AbsAxis {
+        code,
+        min: -STICK_MAX - 1,
+        max: STICK_MAX,
+        fuzz: 16,
+        flat: 128,
+    };
+    let trigger = |code| AbsAxis {
+        code,
+        min: 0,
+        max: TRIGGER_MAX,
+        fuzz: 0,
+        flat: 0,
+    };
+    let hat = |code| AbsAxis {
+        code,
+        min: -1,
+        max: 1,
+        fuzz: 0,
+        flat: 0,
+    };
+    DeviceSpec {
+        name: name.to_string(),
+        id: Some(XBOX_360_ID),
+        keys: BUTTONS.iter().map(|(_, code)| *code).collect(),
+        relative: Vec::new(),
+        absolute: vec![
+            stick(codes::ABS_X),
+            stick(codes::ABS_Y),
+            stick(codes::ABS_RX),
+            stick(codes::ABS_RY),
+            trigger(codes::ABS_Z),
+            trigger(codes::ABS_RZ),
+            hat(codes::ABS_HAT0X),
+            hat(codes::ABS_HAT0Y),
+        ],
+    }
+}
+
+/// Controller buttons with their mapping, default target and state
+fn button_inputs(
+    buttons: &GamepadButtonMappings,
+    state: &ControllerState,
+) -> [(Option<GamepadButton>, GamepadButton, bool); 19] {
+    let b = &state.buttons;
+    [
+        (buttons.cross, GamepadButton::A, b.cross),
+        (buttons.circle, GamepadButton::B, b.circle),
+        (buttons.square, GamepadButton::X, b.square),
+        (buttons.triangle, GamepadButton::Y, b.triangle),
+        (buttons.dpad_up, GamepadButton::DpadUp, b.dpad_up),
+        (buttons.dpad_down, GamepadButton::DpadDown, b.dpad_down),
+        (buttons.dpad_left, GamepadButton::DpadLeft, b.dpad_left),
+        (buttons.dpad_right, GamepadButton::DpadRight, b.dpad_right),
+        (buttons.l1, GamepadButton::Lb, b.l1),
+        (buttons.r1, GamepadButton::Rb, b.r1),
+        // The analog triggers carry these
+        (buttons.l2_button, GamepadButton::None, b.l2_button),
+        (buttons.r2_button, GamepadButton::None, b.r2_button),
+        (buttons.l3, GamepadButton::Ls, b.l3),
+        (buttons.r3, GamepadButton::Rs, b.r3),
+        (buttons.options, GamepadButton::Start, b.options),
+        (buttons.create, GamepadButton::Back, b.create),
+        (buttons.ps, GamepadButton::Guide, b.ps),
+        (buttons.touchpad, GamepadButton::None, b.touchpad),
+        (buttons.mute, GamepadButton::None, b.mute),
+    ]
+}
+
+/// Gamepad state for a controller state
+pub fn map_state(config: &GamepadConfig, state: &ControllerState, curves: &AxisCurves) -> PadState {
+    let mut pad = PadState::default();
+    let (mut up, mut down, mut left, mut right) = (false, false, false, false);
+
+    for (mapped, default, pressed) in button_inputs(&config.buttons, state) {
+        if !pressed {
+            continue;
+        }
+        match mapped.unwrap_or(default) {
+            GamepadButton::DpadUp => up = true,
+            GamepadButton::DpadDown => down = true,
+            GamepadButton::DpadLeft => left = true,
+            GamepadButton::DpadRight => right = true,
+            GamepadButton::None => {}
+            button => {
+                if let Some(bit) = BUTTONS.iter().position(|(b, _)| *b == button) {
+                    pad.buttons |= 1 << bit;
+                }
+            }
+        }
+    }
+
+    let mut left_stick = stick(&config.left_stick, StickSide::Left, state, curves);
+    let mut right_stick = stick(&config.right_stick, StickSide::Right, state, curves);
+    if let Some(gyro) = &config.gyro {
+        let (gx, gy) = gyro_deflection(gyro, state);
+        let target = match gyro.stick {
+            StickSide::Left => &mut left_stick,
+            StickSide::Right => &mut right_stick,
+        };
+        target.0 = (target.0 + gx * gyro.weight).clamp(-1.0, 1.0);
+        target.1 = (target.1 + gy * gyro.weight).clamp(-1.0, 1.0);
+    }
+
+    let left_trigger = trigger(&config.left_trigger, TriggerSide::L2, state, curves);
+    let right_trigger = trigger(&config.right_trigger, TriggerSide::R2, state, curves);
+
+    let stick_value = |v: f32| (v * STICK_MAX as f32).round() as i32;
+    let trigger_value = |v: f32| (v * TRIGGER_MAX as f32).round() as i32;
+    let hat = |negative: bool, positive: bool| positive as i32 - negative as i32;
+    pad.axes = [
+        stick_value(left_stick.0),
+        stick_value(left_stick.1),
+        stick_value(right_stick.0),
+        stick_value(right_stick.1),
+        trigger_value(left_trigger),
+        trigger_value(right_trigger),
+        hat(left, right),
+        hat(up, down),
+    ];
+    pad
+}
+
+/// Stick position (-1..1, Y down)
+fn stick(
+    config: &GamepadStick,
+    side: StickSide,
+    state: &ControllerState,
+    curves: &AxisCurves,
+) -> (f32, f32) {
+    let linear = ResponseCurve::default();
+    let (x, y) = match config.source.unwrap_or(side) {
+        StickSide::Left => {
+            let xy = state.left_stick.normalized_with_deadzone(config.deadzone);
+            match &config.curve {
+                Some(curve) => (curve.apply(xy.0), curve.apply(xy.1)),
+                None => curves.left_stick(xy, &linear),
+            }
+        }
+        StickSide::Right => {
+            let xy = state.right_stick.normalized_with_deadzone(config.deadzone);
+            match &config.curve {
+                Some(curve) => (curve.apply(xy.0), curve.apply(xy.1)),
+                None => curves.right_stick(xy, &linear),
+            }
+        }
+    };
+    (
+        if config.invert_x { -x } else { x },
+        if config.invert_y { -y } else { y },
+    )
+}
+
+/// Trigger position (0..1)
+fn trigger(
+    config: &GamepadTrigger,
+    side: TriggerSide,
+    state: &ControllerState,
+    curves: &AxisCurves,
+) -> f32 {
+    let (l2, r2) = state.triggers.normalized();
+    let value = match (&config.curve, config.source.unwrap_or(side)) {
+        (Some(curve), TriggerSide::L2) => curve.apply(l2),
+        (Some(curve), TriggerSide::R2) => curve.apply(r2),
+        (None, source) => {
+            let (l2, r2) = curves.triggers((l2, r2), &ResponseCurve::default());
+            if source == TriggerSide::L2 {
+                l2
+            } else {
+                r2
+            }
+        }
+    };
+    if config.invert {
+        1.0 - value
+    } else {
+        value
+    }
+}
+
+/// Stick deflection from the gyro: turning right moves right, tilting up
+/// moves up
+fn gyro_deflection(config: &GyroStick, state: &ControllerState) -> (f32, f32) {
+    let gyro = state.gyroscope.to_rad_per_sec();
+    let deflection = |radians: f32| {
+        let dps = radians.to_degrees();
+        if dps.abs() < config.deadzone_dps {
+            return 0.0;
+        }
+        let value = (dps / config.full_scale_dps).clamp(-1.0, 1.0);
+        let value = match &config.curve {
+            Some(curve) => curve.apply(value),
+            None => value,
+        };
+        let min = config.min_deflection.clamp(0.0, 1.0);
+        (min + (1.0 - min) * value.abs()).copysign(value)
+    };
+    let (x, y) = (deflection(gyro.y), -deflection(gyro.x));
+    (
+        if config.invert_x { -x } else { x },
+        if config.invert_y { -y } else { y },
+    )
+}
+
+/// Events turning `prev` into `next`
+pub fn diff(prev: &PadState, next: &PadState) -> Frame {
+    let mut frame = Frame::new();
+    for (bit, (_, code)) in BUTTONS.iter().enumerate() {
+        let pressed = next.buttons & (1 << bit) != 0;
+        if (prev.buttons & (1 << bit) != 0) != pressed {
+            frame.push(InputEvent::Key {
+                code: *code,
+                pressed,
+            });
+        }
+    }
+    for (i, code) in AXES.iter().enumerate() {
+        if prev.axes[i] != next.axes[i] {
+            frame.push(InputEvent::Absolute {
+                code: *code,
+                value: next.axes[i],
+            });
+        }
+    }
+    frame
+}
+
+/// The virtual gamepad, sending only changes
+pub struct VirtualGamepad {
+    sink: Box<dyn EventSink>,
+    last: PadState,
+}
+
+impl VirtualGamepad {
+    /// Create the uinput device
+    pub fn create(config: &GamepadConfig) -> Result<Self> {
+        let device = UinputDevice::create(&device_spec(&config.name))?;
+        Ok(Self::new(Box::new(device)))
+    }
+
+    /// Gamepad writing to any sink
+    pub fn new(sink: Box<dyn EventSink>) -> Self {
+        Self {
+            sink,
+            last: PadState::default(),
+        }
+    }
+
+    pub fn update(&mut self, next: PadState) -> Result<()> {
+        let frame = diff(&self.last, &next);
+        if !frame.is_empty() {
+            self.sink.emit(&frame)?;
+        }
+        self.last = next;
+        Ok(())
+    }
+
+    /// Release everything and center the sticks (e.g. on disconnect)
+    pub fn neutral(&mut self) -> Result<()> {
+        self.update(PadState::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uinput::testing::{from_json, Recorder};
+    use serde_json::json;
+
+    fn centered() -> ControllerState {
+        let mut state = ControllerState::default();
+        state.left_stick.x = 128;
+        state.left_stick.y = 128;
+        state.right_stick.x = 128;
+        state.right_stick.y = 128;
+        state
+    }
+
+    fn pressed(pad: &PadState, button: GamepadButton) -> bool {
+        let bit = BUTTONS.iter().position(|(b, _)| *b == button).unwrap();
+        pad.buttons & (1 << bit) != 0
+    }
+
+    #[test]
+    fn test_default_layout() {
+        let config: GamepadConfig = from_json(json!({}));
+        let mut state = centered();
+        state.buttons.cross = true;
+        state.buttons.options = true;
+        state.buttons.dpad_left = true;
+        state.buttons.touchpad = true;
+        state.left_stick.x = 255;
+        state.triggers.r2 = 255;
+
+        let pad = map_state(&config, &state, &AxisCurves::default());
+        assert!(pressed(&pad, GamepadButton::A));
+        assert!(pressed(&pad, GamepadButton::Start));
+        assert_eq!(pad.buttons.count_ones(), 2);
+        assert_eq!(pad.axes[0], STICK_MAX);
+        assert_eq!(pad.axes[1], 0);
+        assert_eq!(pad.axes[5], TRIGGER_MAX);
+        assert_eq!(&pad.axes[6..], &[-1, 0]);
+    }
+
+    #[test]
+    fn test_remap_invert_and_swap() {
+        let config: GamepadConfig = from_json(json!({
+            "buttons": { "cross": "b", "circle": "a", "touchpad": "back", "options": "none" },
+            "left_stick": { "source": "right", "invert_y": true },
+            "right_trigger": { "source": "l2" }
+        }));
+        let mut state = centered();
+        state.buttons.cross = true;
+        state.buttons.touchpad = true;
+        state.buttons.options = true;
+        state.right_stick.y = 0;
+        state.triggers.l2 = 255;
+
+        let pad = map_state(&config, &state, &AxisCurves::default());
+        assert!(pressed(&pad, GamepadButton::B));
+        assert!(pressed(&pad, GamepadButton::Back));
+        assert_eq!(pad.buttons.count_ones(), 2);
+        // Right stick up, inverted onto the left stick: down
+        assert_eq!(pad.axes[1], STICK_MAX);
+        assert_eq!(pad.axes[3], -STICK_MAX);
+        assert_eq!(pad.axes[5], TRIGGER_MAX);
+    }
+
+    #[test]
+    fn test_curves() {
+        let mut state = centered();
+        state.left_stick.x = 192;
+
+        // The stick's analog curve applies unless the gamepad sets one
+        let quadratic: ResponseCurve = serde_json::from_value(json!("quadratic")).unwrap();
+        let curves = AxisCurves {
+            left_stick_x: Some(quadratic),
+            ..Default::default()
+        };
+        let config: GamepadConfig = from_json(json!({ "left_stick": { "deadzone": 0.0 } }));
+        let curved = map_state(&config, &state, &curves).axes[0];
+        let linear = map_state(&config, &state, &AxisCurves::default()).axes[0];
+        assert!(curved < linear / 2 + 200, "{} vs {}", curved, linear);
+
+        let config: GamepadConfig =
+            from_json(json!({ "left_stick": { "deadzone": 0.0, "curve": "linear" } }));
+        assert_eq!(map_state(&config, &state, &curves).axes[0], linear);
+    }
+
+    #[test]
+    fn test_gyro_blend() {
+        let config: GamepadConfig = from_json(json!({
+            "gyro": { "stick": "right", "full_scale_dps": 100.0, "weight": 0.5 }
+        }));
+        let mut state = centered();
+        // About 57 deg/s turning right
+        state.gyroscope.y = 1024;
+        let pad = map_state(&config, &state, &AxisCurves::default());
+        let expected = (0.5 * 57.3 / 100.0 * STICK_MAX as f32) as i32;
+        assert!((pad.axes[2] - expected).abs() < 100, "{}", pad.axes[2]);
+        assert_eq!(pad.axes[0], 0);
+
+        // Added to the stick, clamped at full deflection
+        state.right_stick.x = 255;
+        let pad = map_state(&config, &state, &AxisCurves::default());
+        assert_eq!(pad.axes[2], STICK_MAX);
+
+        // Below the deadzone: the stick alone
+        state.gyroscope.y = 10;
+        state.right_stick.x = 128;
+        let pad = map_state(&config, &state, &AxisCurves::default());
+        assert_eq!(pad.axes[2], 0);
+    }
+
+    #[test]
+    fn test_sends_only_changes() {
+        let recorder = Recorder::default();
+        let mut gamepad = VirtualGamepad::new(Box::new(recorder.clone()));
+
+        let mut pad = PadState::default();
+        gamepad.update(pad).unwrap();
+        pad.buttons = 1;
+        pad.axes[4] = 100;
+        gamepad.update(pad).unwrap();
+        gamepad.update(pad).unwrap();
+        gamepad.neutral().unwrap();
+
+        assert_eq!(
+            recorder.frames(),
+            vec![
+                vec![
+                    InputEvent::Key {
+                        code: codes::BTN_SOUTH,
+                        pressed: true
+                    },
+                    InputEvent::Absolute {
+                        code: codes::ABS_Z,
+                        value: 100
+                    },
+                ],
+                vec![
+                    InputEvent::Key {
+                        code: codes::BTN_SOUTH,
+                        pressed: false
+                    },
+                    InputEvent::Absolute {
+                        code: codes::ABS_Z,
+                        value: 0
+                    },
+                ],
+            ]
+        );
+    }
+}
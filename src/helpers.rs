@@ -0,0 +1,293 @@
+//! Template helpers
+//!
+//! Math, formatting and escaping helpers registered on every template
+//! registry. Helpers return JSON values, so they nest as subexpressions:
+//! `{{fixed (mul left_stick_x 100) 1}}`.
+//!
+//! Output is not HTML-escaped: templates feed shells, URLs and JSON, never
+//! HTML, so `{{json x}}` and `{{shell_quote x}}` are safe as written.
+
+use handlebars::{
+    no_escape, Context, Handlebars, Helper, HelperDef, RenderContext, RenderError,
+    RenderErrorReason, ScopedJson,
+};
+use serde_json::Value;
+
+/// Helper names with usage, shown by `init`
+pub const HELPERS: &[(&str, &str)] = &[
+    ("mul", "{{mul a b ...}}            product of the arguments"),
+    ("add", "{{add a b ...}}            sum of the arguments"),
+    ("clamp", "{{clamp x min max}}        limit x to [min, max]"),
+    ("map_range", "{{map_range x a b c d}}    map x from [a, b] to [c, d] (not clamped)"),
+    ("round", "{{round x [digits]}}       round to digits decimals (default 0)"),
+    ("fixed", "{{fixed x digits}}         format with exactly digits decimals"),
+    ("deg", "{{deg x}}                  radians to degrees"),
+    ("abs", "{{abs x}}                  absolute value"),
+    ("if_gt", "{{if_gt x limit then [else]}} then if x > limit, else else (default empty)"),
+    ("json", "{{json x}}                 x as JSON"),
+    ("shell_quote", "{{shell_quote x}}          x as a single shell word"),
+    ("url_encode", "{{url_encode x}}           percent-encode x for URLs"),
+    ("hex", "{{hex x [width]}}          integer as lowercase hex, zero-padded to width"),
+];
+
+/// Template registry with all helpers registered
+pub fn registry() -> Handlebars<'static> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(no_escape);
+    register(&mut handlebars);
+    handlebars
+}
+
+/// Register all helpers on an existing registry
+pub fn register(handlebars: &mut Handlebars) {
+    handlebars.register_helper("mul", Box::new(ValueHelper("mul", mul)));
+    handlebars.register_helper("add", Box::new(ValueHelper("add", add)));
+    handlebars.register_helper("clamp", Box::new(ValueHelper("clamp", clamp)));
+    handlebars.register_helper("map_range", Box::new(ValueHelper("map_range", map_range)));
+    handlebars.register_helper("round", Box::new(ValueHelper("round", round)));
+    handlebars.register_helper("fixed", Box::new(ValueHelper("fixed", fixed)));
+    handlebars.register_helper("deg", Box::new(ValueHelper("deg", deg)));
+    handlebars.register_helper("abs", Box::new(ValueHelper("abs", abs)));
+    handlebars.register_helper("if_gt", Box::new(ValueHelper("if_gt", if_gt)));
+    handlebars.register_helper("json", Box::new(ValueHelper("json", json)));
+    handlebars.register_helper("shell_quote", Box::new(ValueHelper("shell_quote", shell_quote)));
+    handlebars.register_helper("url_encode", Box::new(ValueHelper("url_encode", url_encode)));
+    handlebars.register_helper("hex", Box::new(ValueHelper("hex", hex)));
+}
+
+type HelperFn = fn(&[&Value]) -> Result<Value, String>;
+
+/// Adapts a plain function over parameter values to a handlebars helper
+struct ValueHelper(&'static str, HelperFn);
+
+impl HelperDef for ValueHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let params: Vec<&Value> = h.params().iter().map(|p| p.value()).collect();
+        (self.1)(&params)
+            .map(ScopedJson::Derived)
+            .map_err(|e| RenderErrorReason::Other(format!("{}: {}", self.0, e)).into())
+    }
+}
+
+fn param<'a>(params: &[&'a Value], index: usize) -> Result<&'a Value, String> {
+    params
+        .get(index)
+        .copied()
+        .ok_or_else(|| format!("missing argument {}", index + 1))
+}
+
+/// Numeric value of a parameter (numbers, numeric strings and booleans)
+fn number(params: &[&Value], index: usize) -> Result<f64, String> {
+    let value = param(params, index)?;
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+        _ => None,
+    }
+    .ok_or_else(|| format!("argument {} is not a number: {}", index + 1, value))
+}
+
+fn optional_number(params: &[&Value], index: usize, default: f64) -> Result<f64, String> {
+    if params.len() > index {
+        number(params, index)
+    } else {
+        Ok(default)
+    }
+}
+
+/// Whole numbers render without a trailing `.0`
+fn number_value(n: f64) -> Value {
+    if n.is_finite() && n.fract() == 0.0 && n.abs() < 9.0e15 {
+        Value::from(n as i64)
+    } else {
+        Value::from(n)
+    }
+}
+
+fn decimals(params: &[&Value], index: usize, default: f64) -> Result<usize, String> {
+    let digits = optional_number(params, index, default)?;
+    if !(0.0..=15.0).contains(&digits) {
+        return Err(format!("digits must be between 0 and 15, got {}", digits));
+    }
+    Ok(digits as usize)
+}
+
+fn all_numbers(params: &[&Value]) -> Result<Vec<f64>, String> {
+    if params.is_empty() {
+        return Err("needs at least one argument".to_string());
+    }
+    (0..params.len()).map(|i| number(params, i)).collect()
+}
+
+fn mul(params: &[&Value]) -> Result<Value, String> {
+    Ok(number_value(all_numbers(params)?.iter().product()))
+}
+
+fn add(params: &[&Value]) -> Result<Value, String> {
+    Ok(number_value(all_numbers(params)?.iter().sum()))
+}
+
+fn clamp(params: &[&Value]) -> Result<Value, String> {
+    let (x, min, max) = (number(params, 0)?, number(params, 1)?, number(params, 2)?);
+    if min > max {
+        return Err(format!("min {} is greater than max {}", min, max));
+    }
+    Ok(number_value(x.clamp(min, max)))
+}
+
+fn map_range(params: &[&Value]) -> Result<Value, String> {
+    let x = number(params, 0)?;
+    let (in_min, in_max) = (number(params, 1)?, number(params, 2)?);
+    let (out_min, out_max) = (number(params, 3)?, number(params, 4)?);
+    if in_min == in_max {
+        return Err("input range is empty".to_string());
+    }
+    let t = (x - in_min) / (in_max - in_min);
+    Ok(number_value(out_min + t * (out_max - out_min)))
+}
+
+fn round(params: &[&Value]) -> Result<Value, String> {
+    let x = number(params, 0)?;
+    let scale = 10f64.powi(decimals(params, 1, 0.0)? as i32);
+    Ok(number_value((x * scale).round() / scale))
+}
+
+fn fixed(params: &[&Value]) -> Result<Value, String> {
+    let x = number(params, 0)?;
+    let digits = decimals(params, 1, 2.0)?;
+    Ok(Value::String(format!("{:.*}", digits, x)))
+}
+
+fn deg(params: &[&Value]) -> Result<Value, String> {
+    Ok(number_value(number(params, 0)?.to_degrees()))
+}
+
+fn abs(params: &[&Value]) -> Result<Value, String> {
+    Ok(number_value(number(params, 0)?.abs()))
+}
+
+fn if_gt(params: &[&Value]) -> Result<Value, String> {
+    let (x, limit) = (number(params, 0)?, number(params, 1)?);
+    let then = param(params, 2)?;
+    let otherwise = params.get(3).copied().cloned().unwrap_or(Value::String(String::new()));
+    Ok(if x > limit { then.clone() } else { otherwise })
+}
+
+fn json(params: &[&Value]) -> Result<Value, String> {
+    serde_json::to_string(param(params, 0)?)
+        .map(Value::String)
+        .map_err(|e| e.to_string())
+}
+
+/// Plain text of a value (strings without quotes)
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn shell_quote(params: &[&Value]) -> Result<Value, String> {
+    let s = text(param(params, 0)?);
+    Ok(Value::String(format!("'{}'", s.replace('\'', r"'\''"))))
+}
+
+fn url_encode(params: &[&Value]) -> Result<Value, String> {
+    let s = text(param(params, 0)?);
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    Ok(Value::String(encoded))
+}
+
+fn hex(params: &[&Value]) -> Result<Value, String> {
+    let x = number(params, 0)?;
+    if x < 0.0 || x.fract() != 0.0 {
+        return Err(format!("expected a non-negative integer, got {}", x));
+    }
+    let width = decimals(params, 1, 0.0)?;
+    Ok(Value::String(format!("{:0width$x}", x as u64, width = width)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(template: &str, data: Value) -> String {
+        registry().render_template(template, &data).unwrap()
+    }
+
+    #[test]
+    fn test_math_helpers() {
+        let data = json!({ "x": 0.5, "y": -0.25, "rad": std::f64::consts::PI });
+        assert_eq!(render("{{mul x 100}}", data.clone()), "50");
+        assert_eq!(render("{{add x y 1}}", data.clone()), "1.25");
+        assert_eq!(render("{{clamp 7 0 5}} {{clamp y 0 1}}", data.clone()), "5 0");
+        assert_eq!(render("{{map_range x -1 1 0 255}}", data.clone()), "191.25");
+        assert_eq!(render("{{round 2.675 1}} {{round 2.5}}", data.clone()), "2.7 3");
+        assert_eq!(render("{{fixed y 3}} {{fixed 1 1}}", data.clone()), "-0.250 1.0");
+        assert_eq!(render("{{deg rad}}", data.clone()), "180");
+        assert_eq!(render("{{abs y}}", data.clone()), "0.25");
+    }
+
+    #[test]
+    fn test_nesting_and_conditionals() {
+        let data = json!({ "x": 0.1234, "trigger": 0.8 });
+        assert_eq!(render("{{fixed (mul x 100) 1}}", data.clone()), "12.3");
+        assert_eq!(render("{{if_gt trigger 0.5 \"fire\" \"idle\"}}", data.clone()), "fire");
+        assert_eq!(render("{{if_gt x 0.5 \"fire\"}}", data.clone()), "");
+        assert_eq!(
+            render("{{#if (if_gt trigger 0.5 true false)}}on{{/if}}", data),
+            "on"
+        );
+    }
+
+    #[test]
+    fn test_escaping_helpers() {
+        let data = json!({ "name": "it's \"here\" & there", "n": 255 });
+        assert_eq!(render("{{json name}}", data.clone()), r#""it's \"here\" & there""#);
+        assert_eq!(render("{{json n}}", data.clone()), "255");
+        assert_eq!(
+            render("echo {{shell_quote name}}", data.clone()),
+            r#"echo 'it'\''s "here" & there'"#
+        );
+        assert_eq!(
+            render("{{url_encode name}}", data.clone()),
+            "it%27s%20%22here%22%20%26%20there"
+        );
+        assert_eq!(render("#{{hex n 2}}{{hex 0 2}}{{hex 16}}", data), "#ff0010");
+    }
+
+    #[test]
+    fn test_helper_errors() {
+        let hb = registry();
+        assert!(hb.render_template("{{mul \"abc\" 2}}", &json!({})).is_err());
+        assert!(hb.render_template("{{clamp 1 5 0}}", &json!({})).is_err());
+        assert!(hb.render_template("{{hex -1}}", &json!({})).is_err());
+    }
+
+    #[test]
+    fn test_every_documented_helper_is_registered() {
+        // Every helper needs arguments, so a bare call fails only if registered
+        let hb = registry();
+        for (name, _) in HELPERS {
+            let template = format!("{{{{{}}}}}", name);
+            assert!(hb.render_template(&template, &json!({})).is_err(), "{} not registered", name);
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! HTTP action delivery
+//!
+//! Sends a request with retries and exponential backoff, and pulls values
+//! out of JSON responses for `response.extract`.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use reqwest::{Client, Request, RequestBuilder, Response};
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::config::HttpAuth;
+
+/// Longest wait between retries
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// What came back from a request, after retries
+#[derive(Debug, Clone, Default)]
+pub struct HttpOutcome {
+    /// Status of the last response (`None` if none arrived)
+    pub status: Option<u16>,
+    pub body: String,
+}
+
+/// Send `request`, retrying connection errors and 5xx responses up to
+/// `retries` times with a doubling delay starting at `backoff`
+pub async fn send_with_retries(
+    client: &Client,
+    mut request: Request,
+    retries: u32,
+    backoff: Duration,
+) -> HttpOutcome {
+    let mut attempt = 0;
+    loop {
+        // Streaming bodies cannot be copied; those get a single attempt
+        let next = if attempt < retries {
+            request.try_clone()
+        } else {
+            None
+        };
+        let Some(next) = next else {
+            return match client.execute(request).await {
+                Ok(response) => outcome(response).await,
+                Err(e) => {
+                    warn!("HTTP request failed: {}", e);
+                    HttpOutcome::default()
+                }
+            };
+        };
+
+        let failure = match client.execute(request).await {
+            Ok(response) if !response.status().is_server_error() => {
+                return outcome(response).await;
+            }
+            Ok(response) => format!("status {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+
+        let delay = retry_delay(backoff, attempt);
+        attempt += 1;
+        debug!(
+            "HTTP request failed ({}), retry {}/{} in {:?}",
+            failure, attempt, retries, delay
+        );
+        tokio::time::sleep(delay).await;
+        request = next;
+    }
+}
+
+async fn outcome(response: Response) -> HttpOutcome {
+    let status = response.status();
+    if !status.is_success() {
+        warn!("HTTP request failed with status: {}", status);
+    }
+    HttpOutcome {
+        status: Some(status.as_u16()),
+        body: response.text().await.unwrap_or_default(),
+    }
+}
+
+/// Delay before retry number `attempt + 1`
+pub fn retry_delay(backoff: Duration, attempt: u32) -> Duration {
+    backoff
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+/// Add credentials from the environment to a request
+pub fn apply_auth(request: RequestBuilder, auth: &HttpAuth) -> Result<RequestBuilder> {
+    let env = |name: &str| {
+        std::env::var(name)
+            .with_context(|| format!("HTTP auth: environment variable {} is not set", name))
+    };
+    Ok(match auth {
+        HttpAuth::Basic {
+            username_env,
+            password_env,
+        } => {
+            let password = password_env.as_deref().map(env).transpose()?;
+            request.basic_auth(env(username_env)?, password)
+        }
+        HttpAuth::Bearer { token_env } => request.bearer_auth(env(token_env)?),
+    })
+}
+
+/// Values for `response.extract` from a JSON body; missing fields are skipped
+pub fn extract(body: &Value, fields: &BTreeMap<String, String>) -> Vec<(String, Value)> {
+    fields
+        .iter()
+        .filter_map(|(var, path)| match lookup(body, path) {
+            Some(value) => Some((var.clone(), value.clone())),
+            None => {
+                debug!("Response has no field `{}` for variable {}", path, var);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Field at a dotted path (`position.x`, `items.0`) or JSON pointer (`/position/x`)
+fn lookup<'a>(body: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.starts_with('/') || path.is_empty() {
+        return body.pointer(path);
+    }
+    path.split('.')
+        .try_fold(body, |value, segment| match value {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => value.get(segment),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HttpResponseConfig;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answer one connection per status in `statuses`, in order; returns the
+    /// base URL and the request heads received
+    async fn serve(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let heads = Arc::new(Mutex::new(Vec::new()));
+        let seen = heads.clone();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0; 1024];
+                while !head.ends_with(b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    head.extend_from_slice(&buf[..n]);
+                }
+                seen.lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&head).into_owned());
+                let body = format!("{{\"status\":{}}}", status);
+                let reply = format!(
+                    "HTTP/1.1 {} X\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        (url, heads)
+    }
+
+    async fn get(url: &str, retries: u32) -> HttpOutcome {
+        let client = Client::new();
+        let request = client.get(url).build().unwrap();
+        send_with_retries(&client, request, retries, Duration::from_millis(1)).await
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors() {
+        let (url, heads) = serve(vec![503, 500, 200]).await;
+        let outcome = get(&url, 3).await;
+        assert_eq!(outcome.status, Some(200));
+        assert_eq!(outcome.body, r#"{"status":200}"#);
+        assert_eq!(heads.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retries_run_out() {
+        let (url, heads) = serve(vec![503, 502, 200]).await;
+        assert_eq!(get(&url, 1).await.status, Some(502));
+        assert_eq!(heads.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_not_retried() {
+        let (url, heads) = serve(vec![404, 200]).await;
+        assert_eq!(get(&url, 3).await.status, Some(404));
+        assert_eq!(heads.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_feedback_by_status_class() {
+        let response: HttpResponseConfig = serde_json::from_value(json!({
+            "on_success": { "led": { "r": 1, "g": 0, "b": 0 } },
+            "on_client_error": { "led": { "r": 2, "g": 0, "b": 0 } },
+            "on_server_error": { "led": { "r": 3, "g": 0, "b": 0 } },
+            "on_failure": { "led": { "r": 4, "g": 0, "b": 0 } }
+        }))
+        .unwrap();
+        let red = |outcome: HttpOutcome| {
+            response
+                .feedback(outcome.status)
+                .and_then(|feedback| feedback.led.as_ref())
+                .map(|led| led.r)
+        };
+
+        let (url, _) = serve(vec![201, 403, 500, 302]).await;
+        assert_eq!(red(get(&url, 0).await), Some(1));
+        assert_eq!(red(get(&url, 0).await), Some(2));
+        assert_eq!(red(get(&url, 0).await), Some(3));
+        // Redirects have no class of their own (no `location`, so not followed)
+        assert_eq!(red(get(&url, 0).await), None);
+
+        // Nothing listens on the port once the listener is dropped
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+        assert_eq!(red(get(&url, 0).await), Some(4));
+    }
+
+    #[tokio::test]
+    async fn test_auth_headers_from_env() {
+        std::env::set_var("DUALSENSE_CMD_TEST_TOKEN", "s3cret");
+        std::env::set_var("DUALSENSE_CMD_TEST_USER", "pen");
+        let bearer = json!({ "type": "bearer", "token_env": "DUALSENSE_CMD_TEST_TOKEN" });
+        let bearer: HttpAuth = serde_json::from_value(bearer).unwrap();
+        let basic: HttpAuth = serde_json::from_value(json!({
+            "type": "basic",
+            "username_env": "DUALSENSE_CMD_TEST_USER",
+            "password_env": "DUALSENSE_CMD_TEST_TOKEN"
+        }))
+        .unwrap();
+        let missing = json!({ "type": "bearer", "token_env": "DUALSENSE_CMD_TEST_UNSET" });
+        let missing: HttpAuth = serde_json::from_value(missing).unwrap();
+
+        let (url, heads) = serve(vec![200, 200]).await;
+        let client = Client::new();
+        for auth in [&bearer, &basic] {
+            let request = apply_auth(client.get(&url), auth).unwrap().build().unwrap();
+            send_with_retries(&client, request, 0, Duration::ZERO).await;
+        }
+        let heads = heads.lock().unwrap();
+        assert!(heads[0].contains("authorization: Bearer s3cret\r\n"));
+        // base64 of `pen:s3cret`
+        assert!(heads[1].contains("authorization: Basic cGVuOnMzY3JldA==\r\n"));
+        assert!(apply_auth(client.get(&url), &missing).is_err());
+    }
+
+    #[test]
+    fn test_extract_paths() {
+        let body = json!({
+            "position": { "x": 12.5, "y": -3 },
+            "pen": "down",
+            "queue": [{ "id": 7 }]
+        });
+        let fields: BTreeMap<String, String> = [
+            ("x", "position.x"),
+            ("y", "/position/y"),
+            ("pen", "pen"),
+            ("next", "queue.0.id"),
+            ("missing", "position.z"),
+        ]
+        .iter()
+        .map(|(var, path)| (var.to_string(), path.to_string()))
+        .collect();
+
+        assert_eq!(
+            extract(&body, &fields),
+            vec![
+                ("next".to_string(), json!(7)),
+                ("pen".to_string(), json!("down")),
+                ("x".to_string(), json!(12.5)),
+                ("y".to_string(), json!(-3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_cap() {
+        let backoff = Duration::from_millis(200);
+        let delays: Vec<_> = (0..4)
+            .map(|attempt| retry_delay(backoff, attempt))
+            .collect();
+        assert_eq!(
+            delays,
+            [200, 400, 800, 1600].map(Duration::from_millis).to_vec()
+        );
+        assert_eq!(retry_delay(backoff, 20), MAX_BACKOFF);
+    }
+}
@@ -1,7 +1,33 @@
+pub mod compose;
 pub mod config;
+pub mod curve;
+pub mod desktop;
 pub mod dualsense;
+pub mod events;
 pub mod executor;
+pub mod format;
+pub mod gamepad;
+pub mod helpers;
+pub mod http;
+pub mod lifecycle;
+pub mod midi;
+pub mod motion;
+pub mod mqtt;
+pub mod ndjson;
+pub mod osc;
+pub mod pipeline;
+pub mod process;
 pub mod profile;
 pub mod renderer;
+pub mod repeat;
+pub mod schema;
+pub mod socket;
 pub mod spatial;
+pub mod stick;
+pub mod templates;
+pub mod touchpad;
+pub mod trigger;
+pub mod uinput;
+pub mod vars;
+pub mod watch;
 pub mod websocket;
@@ -0,0 +1,146 @@
+//! Controller lifecycle events
+//!
+//! Battery and connection events that configs can hook actions onto.
+//! Battery thresholds use hysteresis so a level hovering around the
+//! threshold does not fire repeatedly.
+
+use crate::dualsense::Battery;
+
+/// Lifecycle event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleEvent {
+    Connect,
+    Disconnect,
+    Reconnect,
+    LowBattery,
+    ChargingStart,
+    ChargingStop,
+    FullyCharged,
+}
+
+impl LifecycleEvent {
+    /// `event_type` of the fired action, also logged
+    pub fn name(&self) -> &'static str {
+        match self {
+            LifecycleEvent::Connect => "connect",
+            LifecycleEvent::Disconnect => "disconnect",
+            LifecycleEvent::Reconnect => "reconnect",
+            LifecycleEvent::LowBattery => "low_battery",
+            LifecycleEvent::ChargingStart => "charging_start",
+            LifecycleEvent::ChargingStop => "charging_stop",
+            LifecycleEvent::FullyCharged => "fully_charged",
+        }
+    }
+}
+
+/// Tracks battery reports and emits battery lifecycle events
+#[derive(Debug, Clone)]
+pub struct BatteryMonitor {
+    low_percent: u8,
+    hysteresis_percent: u8,
+    last: Option<Battery>,
+    low_latched: bool,
+}
+
+impl BatteryMonitor {
+    pub fn new(low_percent: u8, hysteresis_percent: u8) -> Self {
+        Self {
+            low_percent,
+            hysteresis_percent,
+            last: None,
+            low_latched: false,
+        }
+    }
+
+    /// Change the thresholds, keeping the last report and low latch
+    pub fn set_thresholds(&mut self, low_percent: u8, hysteresis_percent: u8) {
+        self.low_percent = low_percent;
+        self.hysteresis_percent = hysteresis_percent;
+    }
+
+    /// Feed a battery report and return the events it caused.
+    /// The first report only establishes a baseline for charging edges.
+    pub fn update(&mut self, battery: &Battery) -> Vec<LifecycleEvent> {
+        let mut events = Vec::new();
+        let percent = battery.percentage();
+
+        // Low battery with hysteresis (charging suppresses the warning)
+        if !self.low_latched && !battery.charging && percent <= self.low_percent {
+            self.low_latched = true;
+            events.push(LifecycleEvent::LowBattery);
+        } else if self.low_latched
+            && (battery.charging
+                || percent > self.low_percent.saturating_add(self.hysteresis_percent))
+        {
+            self.low_latched = false;
+        }
+
+        if let Some(last) = self.last {
+            if !last.charging && battery.charging {
+                events.push(LifecycleEvent::ChargingStart);
+            }
+            if last.charging && !battery.charging {
+                events.push(LifecycleEvent::ChargingStop);
+            }
+            if !last.fully_charged && battery.fully_charged {
+                events.push(LifecycleEvent::FullyCharged);
+            }
+        }
+
+        self.last = Some(*battery);
+        events
+    }
+
+    /// Forget the last report (e.g. after a reconnect)
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn battery(level: u8, charging: bool) -> Battery {
+        Battery {
+            level,
+            charging,
+            fully_charged: false,
+        }
+    }
+
+    #[test]
+    fn test_low_battery_hysteresis() {
+        let mut monitor = BatteryMonitor::new(20, 10);
+        let events: Vec<_> = [3, 2, 3, 2, 3, 4, 2]
+            .iter()
+            .flat_map(|&level| monitor.update(&battery(level, false)))
+            .collect();
+        // Re-arms only after climbing above 30%
+        assert_eq!(
+            events,
+            vec![LifecycleEvent::LowBattery, LifecycleEvent::LowBattery]
+        );
+    }
+
+    #[test]
+    fn test_charging_edges() {
+        let mut monitor = BatteryMonitor::new(20, 5);
+        assert!(monitor.update(&battery(5, true)).is_empty());
+        assert_eq!(
+            monitor.update(&battery(5, false)),
+            vec![LifecycleEvent::ChargingStop]
+        );
+        assert_eq!(
+            monitor.update(&battery(5, true)),
+            vec![LifecycleEvent::ChargingStart]
+        );
+
+        let full = Battery {
+            level: 10,
+            charging: true,
+            fully_charged: true,
+        };
+        assert_eq!(monitor.update(&full), vec![LifecycleEvent::FullyCharged]);
+    }
+}
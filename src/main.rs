@@ -3,7 +3,7 @@
 //! Cross-platform CLI for mapping DualSense controller inputs
 //! to shell commands and WebSocket messages.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -17,15 +17,29 @@ use tokio::sync::Mutex;
 use tokio_tungstenite::connect_async;
 use tokio_tungstenite::tungstenite::Message;
 use tracing::{debug, error, info, warn, Level};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::EnvFilter;
 
+use dualsense_cmd::compose;
 use dualsense_cmd::config::{self, Config, TemplateContext};
 use dualsense_cmd::dualsense::{ConnectionType, ControllerState, DualSense, DualSenseError};
 use dualsense_cmd::executor::{ControllerCommand, Executor};
+use dualsense_cmd::format::{self, FileFormat};
+use dualsense_cmd::helpers;
+use dualsense_cmd::lifecycle::LifecycleEvent;
+use dualsense_cmd::ndjson::{NdjsonWriter, StateRecord, StreamOutput, Written};
 use dualsense_cmd::profile::{Profile, ProfileManager};
-use dualsense_cmd::spatial::{IntegrationConfig, SpatialState, VelocityCurve};
+use dualsense_cmd::spatial::{IntegrationConfig, SpatialState};
+use dualsense_cmd::watch::ConfigWatcher;
 use dualsense_cmd::websocket::WebSocketManager;
 use dualsense_cmd::renderer;
+use dualsense_cmd::schema;
+
+/// How often `run` logs action queue metrics (at debug level)
+const QUEUE_METRICS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long `run` waits for queued actions on exit
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
 
 /// DualSense controller command mapper
 #[derive(Parser)]
@@ -71,6 +85,25 @@ enum Commands {
         json: bool,
     },
 
+    /// Stream controller state as NDJSON (one JSON object per line)
+    Stream {
+        /// Where to write: `-` for stdout, or a file path
+        #[arg(short, long, default_value = "-")]
+        output: String,
+
+        /// Write to a FIFO at the output path, creating it if needed
+        #[arg(long)]
+        fifo: bool,
+
+        /// Records per second
+        #[arg(short, long, default_value_t = 60)]
+        rate: u32,
+
+        /// Include integrated spatial state (uses the config's `integration` settings)
+        #[arg(long)]
+        spatial: bool,
+    },
+
     /// Generate a sample configuration file
     Init {
         /// Output file path
@@ -82,9 +115,9 @@ enum Commands {
         preset: String,
     },
 
-    /// Validate a configuration file
+    /// Validate a configuration file or directory
     Validate {
-        /// Configuration file to validate
+        /// Configuration file or directory to validate
         file: PathBuf,
     },
 
@@ -106,6 +139,41 @@ enum Commands {
 
     /// Show supported protocol features and their status
     Features,
+
+    /// Inspect configuration files
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the effective configuration
+    Show {
+        /// Configuration file or directory (overrides -c)
+        path: Option<PathBuf>,
+
+        /// Print the merged document and the file each key came from
+        #[arg(long)]
+        resolved: bool,
+    },
+
+    /// Print the JSON Schema of the config format
+    Schema {
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Convert a config or profile between JSON, TOML and YAML (by extension)
+    Convert {
+        /// Input file
+        input: PathBuf,
+
+        /// Output file
+        output: PathBuf,
+    },
 }
 
 #[derive(Subcommand)]
@@ -191,12 +259,20 @@ async fn main() -> Result<()> {
         _ => Level::TRACE,
     };
 
+    // `stream` writes records to stdout, so its logs go to stderr
+    let log_writer = if matches!(cli.command, Commands::Stream { .. }) {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+
     tracing_subscriber::fmt()
         .with_env_filter(
             EnvFilter::from_default_env()
                 .add_directive(format!("dualsense_cmd={}", log_level).parse().unwrap()),
         )
         .with_target(false)
+        .with_writer(log_writer)
         .init();
 
     match cli.command {
@@ -206,18 +282,36 @@ async fn main() -> Result<()> {
         }
         Commands::List => list_controllers().await,
         Commands::Monitor { raw, json } => monitor_controller(raw, json).await,
+        Commands::Stream {
+            output,
+            fifo,
+            rate,
+            spatial,
+        } => {
+            stream_state(
+                StreamOutput::parse(&output, fifo),
+                rate,
+                spatial,
+                cli.config,
+            )
+            .await
+        }
         Commands::Init { output, preset } => init_config(output, &preset).await,
         Commands::Validate { file } => validate_config(file).await,
         Commands::TestWs { url } => test_websocket(&url).await,
         Commands::ThreeD => run_3d_viewer().await,
         Commands::Profile { action } => handle_profile_command(action).await,
         Commands::Features => show_features().await,
+        Commands::Config { action } => handle_config_command(action, cli.config).await,
     }
 }
 
 async fn run_mapper(config_path: PathBuf, dry_run: bool) -> Result<()> {
     // Load configuration
-    let config = Config::load_dir(&config_path)
+    let resolved = compose::resolve_path(&config_path)
+        .with_context(|| format!("Failed to load config from {:?}", config_path))?;
+    let mut watcher = ConfigWatcher::new(config_path.clone(), resolved.files.clone());
+    let mut config = Config::from_resolved(resolved)
         .with_context(|| format!("Failed to load config from {:?}", config_path))?;
 
     info!("Loaded configuration: {}", config.name);
@@ -258,13 +352,7 @@ async fn run_mapper(config_path: PathBuf, dry_run: bool) -> Result<()> {
     let (cmd_tx, mut cmd_rx) = mpsc::channel::<ControllerCommand>(32);
 
     // Set initial LED color
-    if let Some(led_config) = &config.led.connected_color {
-        controller
-            .set_led_color(led_config.r, led_config.g, led_config.b)
-            .ok();
-    } else {
-        controller.set_led_color(0, 128, 255).ok(); // Default blue
-    }
+    apply_connected_color(&controller, &config);
 
     // Set up WebSocket if configured
     let ws_manager = if let Some(ws_config) = &config.websocket {
@@ -281,7 +369,7 @@ async fn run_mapper(config_path: PathBuf, dry_run: bool) -> Result<()> {
     };
 
     // Create executor
-    let mut executor = Executor::new(config.clone(), cmd_tx.clone());
+    let mut executor = Executor::new(config.clone(), cmd_tx.clone())?;
 
     // Start WebSocket connection in background if configured
     let ws_sender = if let Some(manager) = &ws_manager {
@@ -342,40 +430,12 @@ async fn run_mapper(config_path: PathBuf, dry_run: bool) -> Result<()> {
     println!();
 
     // Calculate poll interval
-    let poll_interval = Duration::from_micros(1_000_000 / config.poll_rate as u64);
-    let mut last_state_update = Instant::now();
+    let mut poll_interval = poll_interval_for(&config);
     let mut last_frame_time = Instant::now();
-    let state_interval = config
-        .websocket
-        .as_ref()
-        .map(|ws| Duration::from_millis(ws.state_interval_ms))
-        .unwrap_or(Duration::from_millis(0));
+    let mut last_metrics_log = Instant::now();
 
     // Set up spatial integration if configured
-    let mut spatial_state = config.integration.as_ref().map(|int_config| {
-        let velocity_curve = match int_config.velocity_curve.to_lowercase().as_str() {
-            "quadratic" => VelocityCurve::Quadratic,
-            "cubic" => VelocityCurve::Cubic,
-            _ => VelocityCurve::Linear,
-        };
-
-        let gyro_weight = int_config
-            .orientation_filter
-            .as_ref()
-            .map(|f| f.gyro_weight)
-            .unwrap_or(0.98);
-
-        let spatial_config = IntegrationConfig {
-            velocity_curve,
-            max_linear_speed: int_config.max_linear_speed,
-            max_angular_speed: int_config.max_angular_speed,
-            linear_damping: int_config.linear_damping,
-            angular_damping: int_config.angular_damping,
-            smoothing_alpha: int_config.smoothing_alpha,
-            gyro_weight,
-            deadzone: config.deadzone,
-        };
-
+    let mut spatial_state = integration_config_for(&config).map(|spatial_config| {
         info!(
             "Spatial integration enabled: max_speed={} mm/s, damping={}, curve={:?}",
             spatial_config.max_linear_speed,
@@ -393,6 +453,17 @@ async fn run_mapper(config_path: PathBuf, dry_run: bool) -> Result<()> {
         );
     }
 
+    if !dry_run {
+        if let Err(e) = executor
+            .fire_lifecycle(LifecycleEvent::Connect, controller.state())
+            .await
+        {
+            error!("Error running connect action: {}", e);
+        }
+    }
+
+    let mut led_blink: Option<LedBlink> = None;
+
     // Main loop
     while running.load(Ordering::SeqCst) {
         // Calculate delta time
@@ -417,13 +488,14 @@ async fn run_mapper(config_path: PathBuf, dry_run: bool) -> Result<()> {
                 if !dry_run {
                     if let Err(e) = executor.process_state_change(&prev_state, &current_state).await {
                         error!("Error processing state change: {}", e);
+                        if let Some(color) = &config.led.error_color {
+                            controller.set_led_color(color.r, color.g, color.b).ok();
+                        }
                     }
                 }
 
                 // Send periodic state updates if configured
-                if state_interval.as_millis() > 0
-                    && last_state_update.elapsed() >= state_interval
-                {
+                if executor.state_due() {
                     let ctx = TemplateContext::from_controller(
                         &current_state,
                         spatial_state.as_ref(),
@@ -431,7 +503,6 @@ async fn run_mapper(config_path: PathBuf, dry_run: bool) -> Result<()> {
                     if let Err(e) = executor.send_state_update(&ctx).await {
                         debug!("Error sending state update: {}", e);
                     }
-                    last_state_update = Instant::now();
                 }
             }
             Err(DualSenseError::Timeout) => {
@@ -439,7 +510,104 @@ async fn run_mapper(config_path: PathBuf, dry_run: bool) -> Result<()> {
             }
             Err(e) => {
                 error!("Controller error: {}", e);
-                break;
+                let last_state = controller.state().clone();
+                if !dry_run {
+                    if let Err(e) = executor
+                        .fire_lifecycle(LifecycleEvent::Disconnect, &last_state)
+                        .await
+                    {
+                        error!("Error running disconnect action: {}", e);
+                    }
+                }
+
+                if !config.lifecycle.reconnect {
+                    break;
+                }
+
+                println!(
+                    "{} Controller lost, waiting to reconnect...",
+                    "!".bright_yellow()
+                );
+                let delay = Duration::from_millis(config.lifecycle.reconnect_delay_ms);
+                let Some(new_controller) = reconnect_controller(&running, delay).await else {
+                    break;
+                };
+
+                controller = new_controller;
+                led_blink = None;
+                apply_connected_color(&controller, &config);
+                println!("{} Controller reconnected", "✓".bright_green());
+
+                if !dry_run {
+                    if let Err(e) = executor
+                        .fire_lifecycle(LifecycleEvent::Reconnect, controller.state())
+                        .await
+                    {
+                        error!("Error running reconnect action: {}", e);
+                    }
+                }
+            }
+        }
+
+        // Auto-repeat held buttons on the executor's own clock
+        if !dry_run {
+            if let Err(e) = executor.tick().await {
+                error!("Error repeating action: {}", e);
+            }
+        }
+
+        if last_metrics_log.elapsed() >= QUEUE_METRICS_INTERVAL {
+            debug!("Action queue: {}", executor.queue_metrics().summary());
+            last_metrics_log = Instant::now();
+        }
+
+        // Hot-reload the config when its files change
+        if watcher.poll() {
+            // The executor goes first: it rejects configs with broken templates
+            let reloaded = reload_config(&config_path).and_then(|(new_config, files)| {
+                executor.set_config(new_config.clone())?;
+                Ok((new_config, files))
+            });
+            match reloaded {
+                Ok((new_config, files)) => {
+                    watcher.set_files(files);
+
+                    if new_config.websocket.as_ref().map(|ws| &ws.url)
+                        != config.websocket.as_ref().map(|ws| &ws.url)
+                    {
+                        warn!("WebSocket changes take effect after restarting `run`");
+                    }
+
+                    match (&mut spatial_state, integration_config_for(&new_config)) {
+                        (Some(spatial), Some(spatial_config)) => spatial.set_config(spatial_config),
+                        (state, spatial_config) => *state = spatial_config.map(SpatialState::new),
+                    }
+
+                    poll_interval = poll_interval_for(&new_config);
+                    config = new_config;
+
+                    // Confirm with a short flash back to the connected color
+                    apply_connected_color(&controller, &config);
+                    let restore = controller.get_output_state().led_color;
+                    led_blink = Some(LedBlink::new((255, 255, 255), restore, 2, 80));
+
+                    info!("Reloaded configuration: {}", config.name);
+                    println!(
+                        "{} Reloaded config: {}",
+                        "✓".bright_green(),
+                        config.name.bright_yellow()
+                    );
+                }
+                Err(e) => {
+                    error!("Config reload failed, keeping previous config: {:#}", e);
+                }
+            }
+        }
+
+        // Advance LED blink
+        if let Some(blink) = &mut led_blink {
+            if !blink.tick(&controller) {
+                led_blink = None;
             }
         }
 
@@ -449,6 +617,19 @@ async fn run_mapper(config_path: PathBuf, dry_run: bool) -> Result<()> {
                 ControllerCommand::SetLed(r, g, b) => {
                     controller.set_led_color(r, g, b).ok();
                 }
+                ControllerCommand::BlinkLed(r, g, b, count, interval_ms) => {
+                    let restore = led_blink
+                        .take()
+                        .map(|blink| blink.restore)
+                        .unwrap_or_else(|| controller.get_output_state().led_color);
+                    led_blink = Some(LedBlink::new((r, g, b), restore, count, interval_ms));
+                }
+                ControllerCommand::SetL2Effect(effect) => {
+                    controller.set_l2_trigger_effect(effect).ok();
+                }
+                ControllerCommand::SetR2Effect(effect) => {
+                    controller.set_r2_trigger_effect(effect).ok();
+                }
                 ControllerCommand::SetRumble(left, right, duration_ms) => {
                     controller.set_rumble(left, right).ok();
                     if duration_ms > 0 {
@@ -464,6 +645,16 @@ async fn run_mapper(config_path: PathBuf, dry_run: bool) -> Result<()> {
         }
     }
 
+    // Let queued actions finish; controller commands are no longer applied
+    drop(cmd_rx);
+    match tokio::time::timeout(SHUTDOWN_TIMEOUT, executor.shutdown()).await {
+        Ok(metrics) => info!("Action queue: {}", metrics.summary()),
+        Err(_) => warn!(
+            "Queued actions still running after {:?}, exiting anyway",
+            SHUTDOWN_TIMEOUT
+        ),
+    }
+
     // Clean up - explicitly close to ensure device is released
     controller.close();
     drop(controller); // Explicitly drop to release HID device
@@ -472,6 +663,107 @@ async fn run_mapper(config_path: PathBuf, dry_run: bool) -> Result<()> {
     Ok(())
 }
 
+/// Set the configured "connected" lightbar color
+fn apply_connected_color(controller: &DualSense, config: &Config) {
+    if let Some(led_config) = &config.led.connected_color {
+        controller
+            .set_led_color(led_config.r, led_config.g, led_config.b)
+            .ok();
+    } else {
+        controller.set_led_color(0, 128, 255).ok(); // Default blue
+    }
+}
+
+/// Re-read the config, returning it with the files it was resolved from
+fn reload_config(config_path: &Path) -> Result<(Config, Vec<PathBuf>)> {
+    let resolved = compose::resolve_path(config_path)?;
+    let files = resolved.files.clone();
+    let config = Config::from_resolved(resolved)?;
+    Ok((config, files))
+}
+
+fn poll_interval_for(config: &Config) -> Duration {
+    Duration::from_micros(1_000_000 / config.poll_rate.max(1) as u64)
+}
+
+/// Spatial integration settings, if the config enables it
+fn integration_config_for(config: &Config) -> Option<IntegrationConfig> {
+    config.integration.as_ref().map(|int_config| {
+        let gyro_weight = int_config
+            .orientation_filter
+            .as_ref()
+            .map(|f| f.gyro_weight)
+            .unwrap_or(0.98);
+
+        IntegrationConfig {
+            velocity_curve: int_config.velocity_curve.clone(),
+            curves: int_config.curves.clone(),
+            max_linear_speed: int_config.max_linear_speed,
+            max_angular_speed: int_config.max_angular_speed,
+            linear_damping: int_config.linear_damping,
+            angular_damping: int_config.angular_damping,
+            smoothing_alpha: int_config.smoothing_alpha,
+            gyro_weight,
+            deadzone: config.deadzone,
+        }
+    })
+}
+
+/// Retry connecting until a controller appears or shutdown is requested
+async fn reconnect_controller(running: &AtomicBool, delay: Duration) -> Option<DualSense> {
+    while running.load(Ordering::SeqCst) {
+        tokio::time::sleep(delay).await;
+        match DualSense::find_and_connect() {
+            Ok(controller) => return Some(controller),
+            Err(e) => debug!("Reconnect attempt failed: {}", e),
+        }
+    }
+    None
+}
+
+/// Lightbar blink driven from the main loop
+struct LedBlink {
+    color: (u8, u8, u8),
+    restore: (u8, u8, u8),
+    remaining_toggles: u32,
+    interval: Duration,
+    next_toggle: Instant,
+    lit: bool,
+}
+
+impl LedBlink {
+    fn new(color: (u8, u8, u8), restore: (u8, u8, u8), count: u32, interval_ms: u64) -> Self {
+        Self {
+            color,
+            restore,
+            remaining_toggles: count.saturating_mul(2),
+            interval: Duration::from_millis(interval_ms),
+            next_toggle: Instant::now(),
+            lit: false,
+        }
+    }
+
+    /// Toggle the lightbar when due. Returns false once finished.
+    fn tick(&mut self, controller: &DualSense) -> bool {
+        if Instant::now() < self.next_toggle {
+            return true;
+        }
+
+        if self.remaining_toggles == 0 {
+            let (r, g, b) = self.restore;
+            controller.set_led_color(r, g, b).ok();
+            return false;
+        }
+
+        self.lit = !self.lit;
+        let (r, g, b) = if self.lit { self.color } else { self.restore };
+        controller.set_led_color(r, g, b).ok();
+        self.remaining_toggles -= 1;
+        self.next_toggle = Instant::now() + self.interval;
+        true
+    }
+}
+
 async fn list_controllers() -> Result<()> {
     use hidapi::HidApi;
 
@@ -578,6 +870,98 @@ async fn monitor_controller(raw: bool, json: bool) -> Result<()> {
     Ok(())
 }
 
+async fn stream_state(
+    output: StreamOutput,
+    rate: u32,
+    spatial: bool,
+    config_path: PathBuf,
+) -> Result<()> {
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+
+    ctrlc::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+    })
+    .expect("Error setting Ctrl-C handler");
+
+    // Spatial tuning from the config if there is one, else the defaults
+    let mut spatial_state = spatial.then(|| {
+        let spatial_config = match compose::resolve_path(&config_path)
+            .and_then(Config::from_resolved)
+        {
+            Ok(config) => integration_config_for(&config).unwrap_or_default(),
+            Err(e) => {
+                warn!(
+                    "Using default spatial settings, could not load {}: {:#}",
+                    config_path.display(),
+                    e
+                );
+                IntegrationConfig::default()
+            }
+        };
+        SpatialState::new(spatial_config)
+    });
+
+    let mut writer = NdjsonWriter::open(output.clone())?;
+
+    // stdout carries the records, so status goes to stderr
+    eprintln!(
+        "{} Searching for DualSense controller...",
+        "→".bright_blue()
+    );
+
+    let mut controller =
+        DualSense::find_and_connect().context("Failed to connect to DualSense controller")?;
+
+    eprintln!(
+        "{} Streaming to {} at {} Hz",
+        "✓".bright_green(),
+        output,
+        rate.max(1)
+    );
+
+    let interval = Duration::from_micros(1_000_000 / rate.max(1) as u64);
+    let poll_timeout = interval.as_millis().clamp(1, 16) as i32;
+    let started = Instant::now();
+    let mut last_frame_time = Instant::now();
+    let mut last_write: Option<Instant> = None;
+
+    while running.load(Ordering::SeqCst) {
+        match controller.poll(poll_timeout) {
+            Ok(state) => {
+                if let Some(spatial) = &mut spatial_state {
+                    spatial.integrate(state, last_frame_time.elapsed().as_secs_f32());
+                }
+                last_frame_time = Instant::now();
+            }
+            Err(DualSenseError::Timeout) => {}
+            Err(e) => {
+                error!("Controller error: {}", e);
+                break;
+            }
+        }
+
+        if last_write.is_some_and(|at| at.elapsed() < interval) {
+            continue;
+        }
+        last_write = Some(Instant::now());
+
+        let record = StateRecord::new(
+            started.elapsed().as_millis() as u64,
+            controller.state(),
+            spatial_state.as_ref(),
+        );
+        if writer.write(&record)? == Written::Closed {
+            debug!("Output closed, stopping stream");
+            break;
+        }
+    }
+
+    controller.close();
+    eprintln!("{} Streaming stopped", "✓".bright_green());
+    Ok(())
+}
+
 fn print_state_json(state: &ControllerState) {
     let ctx = TemplateContext::from(state);
     if let Ok(json) = serde_json::to_string(&ctx) {
@@ -743,20 +1127,22 @@ async fn init_config(output: PathBuf, preset: &str) -> Result<()> {
             poll_rate: 60,
             deadzone: 0.15,
             http: Some(HttpConfig {
-                base_url: "http://localhost:9700".to_string(),
+                base_url: "http://localhost:9700".into(),
                 headers: [("Content-Type".to_string(), "application/json".to_string())]
                     .into_iter()
                     .collect(),
                 timeout_ms: 5000,
+                auth: None,
             }),
             buttons: ButtonMappings {
                 cross: Some(ActionConfig {
-                    trigger: "press".to_string(),
+                    trigger: EventType::Press,
                     http: Some(HttpRequest {
-                        method: "POST".to_string(),
-                        path: "/pen/down".to_string(),
+                        method: HttpMethod::Post,
+                        path: "/pen/down".into(),
                         body: None,
                         headers: Default::default(),
+                        ..Default::default()
                     }),
                     rumble: Some(RumbleConfig {
                         left: 50,
@@ -766,76 +1152,83 @@ async fn init_config(output: PathBuf, preset: &str) -> Result<()> {
                     ..Default::default()
                 }),
                 circle: Some(ActionConfig {
-                    trigger: "press".to_string(),
+                    trigger: EventType::Press,
                     http: Some(HttpRequest {
-                        method: "POST".to_string(),
-                        path: "/pen/up".to_string(),
+                        method: HttpMethod::Post,
+                        path: "/pen/up".into(),
                         body: None,
                         headers: Default::default(),
+                        ..Default::default()
                     }),
                     ..Default::default()
                 }),
                 triangle: Some(ActionConfig {
-                    trigger: "press".to_string(),
+                    trigger: EventType::Press,
                     http: Some(HttpRequest {
-                        method: "POST".to_string(),
-                        path: "/home".to_string(),
+                        method: HttpMethod::Post,
+                        path: "/home".into(),
                         body: None,
                         headers: Default::default(),
+                        ..Default::default()
                     }),
                     ..Default::default()
                 }),
                 dpad_up: Some(ActionConfig {
-                    trigger: "press".to_string(),
+                    trigger: EventType::Press,
                     http: Some(HttpRequest {
-                        method: "POST".to_string(),
-                        path: "/move".to_string(),
-                        body: Some(r#"{"dx": 0, "dy": -5, "units": "mm"}"#.to_string()),
+                        method: HttpMethod::Post,
+                        path: "/move".into(),
+                        body: Some(r#"{"dx": 0, "dy": -5, "units": "mm"}"#.into()),
                         headers: Default::default(),
+                        ..Default::default()
                     }),
                     debounce_ms: 100,
                     ..Default::default()
                 }),
                 dpad_down: Some(ActionConfig {
-                    trigger: "press".to_string(),
+                    trigger: EventType::Press,
                     http: Some(HttpRequest {
-                        method: "POST".to_string(),
-                        path: "/move".to_string(),
-                        body: Some(r#"{"dx": 0, "dy": 5, "units": "mm"}"#.to_string()),
+                        method: HttpMethod::Post,
+                        path: "/move".into(),
+                        body: Some(r#"{"dx": 0, "dy": 5, "units": "mm"}"#.into()),
                         headers: Default::default(),
+                        ..Default::default()
                     }),
                     debounce_ms: 100,
                     ..Default::default()
                 }),
                 dpad_left: Some(ActionConfig {
-                    trigger: "press".to_string(),
+                    trigger: EventType::Press,
                     http: Some(HttpRequest {
-                        method: "POST".to_string(),
-                        path: "/move".to_string(),
-                        body: Some(r#"{"dx": -5, "dy": 0, "units": "mm"}"#.to_string()),
+                        method: HttpMethod::Post,
+                        path: "/move".into(),
+                        body: Some(r#"{"dx": -5, "dy": 0, "units": "mm"}"#.into()),
                         headers: Default::default(),
+                        ..Default::default()
                     }),
                     debounce_ms: 100,
                     ..Default::default()
                 }),
                 dpad_right: Some(ActionConfig {
-                    trigger: "press".to_string(),
+                    trigger: EventType::Press,
                     http: Some(HttpRequest {
-                        method: "POST".to_string(),
-                        path: "/move".to_string(),
-                        body: Some(r#"{"dx": 5, "dy": 0, "units": "mm"}"#.to_string()),
+                        method: HttpMethod::Post,
+                        path: "/move".into(),
+                        body: Some(r#"{"dx": 5, "dy": 0, "units": "mm"}"#.into()),
                         headers: Default::default(),
+                        ..Default::default()
                     }),
                     debounce_ms: 100,
                     ..Default::default()
                 }),
                 options: Some(ActionConfig {
-                    trigger: "press".to_string(),
+                    trigger: EventType::Press,
                     http: Some(HttpRequest {
-                        method: "POST".to_string(),
-                        path: "/stop".to_string(),
+                        method: HttpMethod::Post,
+                        path: "/stop".into(),
                         body: None,
                         headers: Default::default(),
+                        ..Default::default()
                     }),
                     rumble: Some(RumbleConfig {
                         left: 255,
@@ -849,15 +1242,16 @@ async fn init_config(output: PathBuf, preset: &str) -> Result<()> {
             analog: AnalogMappings {
                 left_stick: Some(StickMapping {
                     on_move: Some(ActionConfig {
-                        trigger: "change".to_string(),
+                        trigger: EventType::Change,
                         http: Some(HttpRequest {
-                            method: "POST".to_string(),
-                            path: "/move".to_string(),
+                            method: HttpMethod::Post,
+                            path: "/move".into(),
                             body: Some(
                                 r#"{"dx": {{left_stick_x}}, "dy": {{left_stick_y}}, "units": "mm"}"#
-                                    .to_string(),
+                                    .into(),
                             ),
                             headers: Default::default(),
+                            ..Default::default()
                         }),
                         ..Default::default()
                     }),
@@ -884,40 +1278,40 @@ async fn init_config(output: PathBuf, preset: &str) -> Result<()> {
                 reconnect_delay_ms: 1000,
                 max_reconnect_attempts: 0,
                 state_format: Some(
-                    r#"{"type":"state","data":{"lx":{{left_stick_x}},"ly":{{left_stick_y}},"rx":{{right_stick_x}},"ry":{{right_stick_y}},"l2":{{l2_trigger}},"r2":{{r2_trigger}},"roll":{{roll}},"pitch":{{pitch}},"yaw":{{yaw}}}}"#.to_string()
+                    r#"{"type":"state","data":{"lx":{{left_stick_x}},"ly":{{left_stick_y}},"rx":{{right_stick_x}},"ry":{{right_stick_y}},"l2":{{l2_trigger}},"r2":{{r2_trigger}},"roll":{{roll}},"pitch":{{pitch}},"yaw":{{yaw}}}}"#.into()
                 ),
                 state_interval_ms: 16, // ~60fps
                 binary: false,
             }),
             buttons: ButtonMappings {
                 cross: Some(ActionConfig {
-                    trigger: "press".to_string(),
+                    trigger: EventType::Press,
                     websocket: Some(WebSocketMessage {
-                        message: r#"{"type":"button","button":"cross","state":"pressed"}"#.to_string(),
+                        message: r#"{"type":"button","button":"cross","state":"pressed"}"#.into(),
                         binary: false,
                     }),
                     ..Default::default()
                 }),
                 circle: Some(ActionConfig {
-                    trigger: "press".to_string(),
+                    trigger: EventType::Press,
                     websocket: Some(WebSocketMessage {
-                        message: r#"{"type":"button","button":"circle","state":"pressed"}"#.to_string(),
+                        message: r#"{"type":"button","button":"circle","state":"pressed"}"#.into(),
                         binary: false,
                     }),
                     ..Default::default()
                 }),
                 square: Some(ActionConfig {
-                    trigger: "press".to_string(),
+                    trigger: EventType::Press,
                     websocket: Some(WebSocketMessage {
-                        message: r#"{"type":"button","button":"square","state":"pressed"}"#.to_string(),
+                        message: r#"{"type":"button","button":"square","state":"pressed"}"#.into(),
                         binary: false,
                     }),
                     ..Default::default()
                 }),
                 triangle: Some(ActionConfig {
-                    trigger: "press".to_string(),
+                    trigger: EventType::Press,
                     websocket: Some(WebSocketMessage {
-                        message: r#"{"type":"button","button":"triangle","state":"pressed"}"#.to_string(),
+                        message: r#"{"type":"button","button":"triangle","state":"pressed"}"#.into(),
                         binary: false,
                     }),
                     ..Default::default()
@@ -941,36 +1335,36 @@ async fn init_config(output: PathBuf, preset: &str) -> Result<()> {
             },
             buttons: ButtonMappings {
                 cross: Some(ActionConfig {
-                    trigger: "press".to_string(),
-                    command: Some("echo 'Cross pressed'".to_string()),
+                    trigger: EventType::Press,
+                    command: Some("echo 'Cross pressed'".into()),
                     ..Default::default()
                 }),
                 circle: Some(ActionConfig {
-                    trigger: "press".to_string(),
-                    command: Some("echo 'Circle pressed'".to_string()),
+                    trigger: EventType::Press,
+                    command: Some("echo 'Circle pressed'".into()),
                     ..Default::default()
                 }),
                 dpad_up: Some(ActionConfig {
-                    trigger: "press".to_string(),
-                    command: Some("echo 'Up'".to_string()),
+                    trigger: EventType::Press,
+                    command: Some("echo 'Up'".into()),
                     debounce_ms: 200,
                     ..Default::default()
                 }),
                 dpad_down: Some(ActionConfig {
-                    trigger: "press".to_string(),
-                    command: Some("echo 'Down'".to_string()),
+                    trigger: EventType::Press,
+                    command: Some("echo 'Down'".into()),
                     debounce_ms: 200,
                     ..Default::default()
                 }),
                 dpad_left: Some(ActionConfig {
-                    trigger: "press".to_string(),
-                    command: Some("echo 'Left'".to_string()),
+                    trigger: EventType::Press,
+                    command: Some("echo 'Left'".into()),
                     debounce_ms: 200,
                     ..Default::default()
                 }),
                 dpad_right: Some(ActionConfig {
-                    trigger: "press".to_string(),
-                    command: Some("echo 'Right'".to_string()),
+                    trigger: EventType::Press,
+                    command: Some("echo 'Right'".into()),
                     debounce_ms: 200,
                     ..Default::default()
                 }),
@@ -993,6 +1387,12 @@ async fn init_config(output: PathBuf, preset: &str) -> Result<()> {
         "✓".bright_green(),
         output.display()
     );
+
+    println!("\n{}", "Template Helpers:".bright_yellow());
+    for (_, usage) in helpers::HELPERS {
+        println!("  {}", usage);
+    }
+
     println!(
         "\nRun with: {} run -c {}",
         "dualsense-cmd".bright_cyan(),
@@ -1005,7 +1405,41 @@ async fn init_config(output: PathBuf, preset: &str) -> Result<()> {
 async fn validate_config(file: PathBuf) -> Result<()> {
     print!("Validating {}... ", file.display());
 
-    match Config::load(&file) {
+    let resolved = match compose::resolve_path(&file) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            println!("{}", "FAILED".bright_red());
+            println!("\nError: {:#}", e);
+            return Err(e);
+        }
+    };
+
+    let errors = schema::validate_document(&resolved.value);
+    if !errors.is_empty() {
+        println!("{}", "FAILED".bright_red());
+        println!();
+        for error in &errors {
+            let origin = resolved
+                .origins
+                .iter()
+                .filter(|(key, _)| error.key == **key || error.key.starts_with(&format!("{}.", key)))
+                .map(|(_, source)| source)
+                .next()
+                .or_else(|| (resolved.files.len() == 1).then(|| &resolved.files[0]));
+            match origin {
+                Some(source) => println!(
+                    "  {}: {} {}",
+                    error.path.bright_yellow(),
+                    error.message,
+                    format!("({})", source.display()).dimmed()
+                ),
+                None => println!("  {}: {}", error.path.bright_yellow(), error.message),
+            }
+        }
+        anyhow::bail!("{} problem(s) found", errors.len());
+    }
+
+    match Config::from_resolved(resolved) {
         Ok(config) => {
             println!("{}", "OK".bright_green());
             println!("\nConfiguration: {}", config.name.bright_yellow());
@@ -1413,6 +1847,99 @@ async fn handle_profile_command(action: ProfileCommands) -> Result<()> {
     Ok(())
 }
 
+async fn handle_config_command(action: ConfigCommands, config_path: PathBuf) -> Result<()> {
+    match action {
+        ConfigCommands::Show { path, resolved } => {
+            let path = path.unwrap_or(config_path);
+            let document = compose::resolve_path(&path)?;
+
+            if !resolved {
+                let config = Config::from_resolved(document)
+                    .with_context(|| format!("Failed to load config from {}", path.display()))?;
+                println!("{}", serde_json::to_string_pretty(&config)?);
+                return Ok(());
+            }
+
+            // Fail early on documents that do not deserialize
+            Config::from_resolved(document.clone())
+                .with_context(|| format!("Failed to load config from {}", path.display()))?;
+
+            println!("{}", serde_json::to_string_pretty(&document.value)?);
+            println!();
+            println!("{}", "Key Origins".bright_white().bold());
+            println!("{}", "══════════════════════════════════════".dimmed());
+            let width = document.origins.keys().map(|k| k.len()).max().unwrap_or(0);
+            for (key, source) in &document.origins {
+                println!(
+                    "  {:width$}  {}",
+                    key.bright_cyan(),
+                    source.display().to_string().dimmed(),
+                    width = width
+                );
+            }
+        }
+
+        ConfigCommands::Schema { output } => {
+            let content = serde_json::to_string_pretty(&schema::config_schema())?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, content)
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    println!("{} Wrote schema: {}", "✓".bright_green(), path.display());
+                }
+                None => println!("{}", content),
+            }
+        }
+
+        ConfigCommands::Convert { input, output } => {
+            let from = FileFormat::from_path(&input)?;
+            let to = FileFormat::from_path(&output)?;
+
+            let content = std::fs::read_to_string(&input)
+                .with_context(|| format!("Failed to read {}", input.display()))?;
+            let document: serde_json::Value = from
+                .parse(&content)
+                .with_context(|| format!("Failed to parse {}", input.display()))?;
+
+            if to == FileFormat::Toml && format::has_null_items(&document) {
+                anyhow::bail!(
+                    "Cannot convert {} to TOML: an array contains null, which TOML can't represent",
+                    input.display()
+                );
+            }
+            let converted = to.value_to_string(&document)?;
+
+            // Check the output reads back as the same document
+            let mut expected = document.clone();
+            if to == FileFormat::Toml {
+                format::strip_nulls(&mut expected);
+            }
+            let round_trip: serde_json::Value = to.parse(&converted)?;
+            if round_trip != expected {
+                anyhow::bail!("Conversion from {} to {} is not lossless", from, to);
+            }
+
+            std::fs::write(&output, converted)
+                .with_context(|| format!("Failed to write {}", output.display()))?;
+
+            println!(
+                "{} Converted {} -> {}",
+                "✓".bright_green(),
+                input.display(),
+                output.display()
+            );
+            if to == FileFormat::Toml && format::has_nulls(&document) {
+                println!(
+                    "{} TOML has no null: null entries were omitted (same as unset)",
+                    "!".bright_yellow()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
 async fn show_features() -> Result<()> {
     println!("{}", "DualSense Protocol Features".bright_white().bold());
     println!("{}", "═══════════════════════════════════════════════════════════════════".dimmed());
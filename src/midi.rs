@@ -0,0 +1,485 @@
+//! MIDI output
+//!
+//! Buttons play notes while held and analog inputs drive controllers (CC)
+//! or pitch bend. A controller is 7-bit, or 14-bit as an MSB/LSB pair on
+//! `cc` and `cc + 32`; pitch bend is always 14-bit. Messages go to a
+//! virtual port (an ALSA sequencer client on Linux) or an existing port.
+
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_2;
+
+use anyhow::{anyhow, Context, Result};
+use midir::{MidiOutput, MidiOutputConnection};
+
+use crate::config::{
+    MidiConfig, MidiControl, MidiControlMappings, MidiNote, MidiNoteMappings, MidiPressure,
+    TemplateContext,
+};
+use crate::touchpad::{TOUCHPAD_HEIGHT, TOUCHPAD_WIDTH};
+
+/// Client name the port is created under
+const CLIENT_NAME: &str = "dualsense-cmd";
+
+/// Largest 14-bit value
+const MAX_14BIT: u16 = 0x3fff;
+
+/// A channel voice message. Channels are 0-15 here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiMessage {
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOff {
+        channel: u8,
+        note: u8,
+    },
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+    /// 14-bit, 8192 is centered
+    PitchBend {
+        channel: u8,
+        value: u16,
+    },
+}
+
+impl MidiMessage {
+    pub fn encode(&self) -> [u8; 3] {
+        match *self {
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => [0x90 | (channel & 0x0f), note & 0x7f, velocity & 0x7f],
+            MidiMessage::NoteOff { channel, note } => [0x80 | (channel & 0x0f), note & 0x7f, 0],
+            MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => [0xb0 | (channel & 0x0f), controller & 0x7f, value & 0x7f],
+            MidiMessage::PitchBend { channel, value } => [
+                0xe0 | (channel & 0x0f),
+                (value & 0x7f) as u8,
+                ((value >> 7) & 0x7f) as u8,
+            ],
+        }
+    }
+}
+
+/// Scale a 0-1 input to the control's range (0-127, or 0-16383)
+pub fn control_value(control: &MidiControl, unit: f32) -> u16 {
+    let unit = if control.invert { 1.0 - unit } else { unit }.clamp(0.0, 1.0);
+    let max = if control.cc.is_none() || control.high_resolution {
+        MAX_14BIT
+    } else {
+        127
+    };
+    (unit * max as f32).round() as u16
+}
+
+/// Messages carrying a value from [`control_value`]
+pub fn control_messages(control: &MidiControl, channel: u8, value: u16) -> Vec<MidiMessage> {
+    match control.cc {
+        None => vec![MidiMessage::PitchBend { channel, value }],
+        // MSB first: receivers reset the LSB when the MSB changes
+        Some(cc) if control.high_resolution => vec![
+            MidiMessage::ControlChange {
+                channel,
+                controller: cc,
+                value: (value >> 7) as u8,
+            },
+            MidiMessage::ControlChange {
+                channel,
+                controller: cc + 32,
+                value: (value & 0x7f) as u8,
+            },
+        ],
+        Some(cc) => vec![MidiMessage::ControlChange {
+            channel,
+            controller: cc,
+            value: value as u8,
+        }],
+    }
+}
+
+/// Config channel (1-16) to wire channel (0-15)
+fn wire_channel(channel: Option<u8>, default: u8) -> u8 {
+    channel.unwrap_or(default).clamp(1, 16) - 1
+}
+
+/// -1..1 to 0..1
+fn bipolar(value: f32) -> f32 {
+    (value + 1.0) / 2.0
+}
+
+fn note_inputs<'a>(
+    notes: &'a MidiNoteMappings,
+    ctx: &TemplateContext,
+) -> [(&'static str, Option<&'a MidiNote>, bool); 19] {
+    [
+        ("cross", notes.cross.as_ref(), ctx.cross),
+        ("circle", notes.circle.as_ref(), ctx.circle),
+        ("square", notes.square.as_ref(), ctx.square),
+        ("triangle", notes.triangle.as_ref(), ctx.triangle),
+        ("dpad_up", notes.dpad_up.as_ref(), ctx.dpad_up),
+        ("dpad_down", notes.dpad_down.as_ref(), ctx.dpad_down),
+        ("dpad_left", notes.dpad_left.as_ref(), ctx.dpad_left),
+        ("dpad_right", notes.dpad_right.as_ref(), ctx.dpad_right),
+        ("l1", notes.l1.as_ref(), ctx.l1),
+        ("r1", notes.r1.as_ref(), ctx.r1),
+        ("l2_button", notes.l2_button.as_ref(), ctx.l2_button),
+        ("r2_button", notes.r2_button.as_ref(), ctx.r2_button),
+        ("l3", notes.l3.as_ref(), ctx.l3),
+        ("r3", notes.r3.as_ref(), ctx.r3),
+        ("options", notes.options.as_ref(), ctx.options),
+        ("create", notes.create.as_ref(), ctx.create),
+        ("ps", notes.ps.as_ref(), ctx.ps),
+        ("touchpad", notes.touchpad.as_ref(), ctx.touchpad),
+        ("mute", notes.mute.as_ref(), ctx.mute),
+    ]
+}
+
+/// Inputs as 0-1 values; the touchpad only while touched
+fn control_inputs<'a>(
+    controls: &'a MidiControlMappings,
+    ctx: &TemplateContext,
+) -> [(&'static str, Option<&'a MidiControl>, Option<f32>); 10] {
+    let stick = |value: f32| Some(bipolar(value));
+    let tilt = |radians: f32| Some(bipolar(radians / FRAC_PI_2));
+    let touch =
+        |position: u16, size: f32| ctx.touch1_active.then(|| position as f32 / (size - 1.0));
+    let c = controls;
+    [
+        (
+            "left_stick_x",
+            c.left_stick_x.as_ref(),
+            stick(ctx.left_stick_x),
+        ),
+        (
+            "left_stick_y",
+            c.left_stick_y.as_ref(),
+            stick(ctx.left_stick_y),
+        ),
+        (
+            "right_stick_x",
+            c.right_stick_x.as_ref(),
+            stick(ctx.right_stick_x),
+        ),
+        (
+            "right_stick_y",
+            c.right_stick_y.as_ref(),
+            stick(ctx.right_stick_y),
+        ),
+        ("l2_trigger", c.l2_trigger.as_ref(), Some(ctx.l2_trigger)),
+        ("r2_trigger", c.r2_trigger.as_ref(), Some(ctx.r2_trigger)),
+        ("tilt_roll", c.tilt_roll.as_ref(), tilt(ctx.roll)),
+        ("tilt_pitch", c.tilt_pitch.as_ref(), tilt(ctx.pitch)),
+        (
+            "touch_x",
+            c.touch_x.as_ref(),
+            touch(ctx.touch1_x, TOUCHPAD_WIDTH),
+        ),
+        (
+            "touch_y",
+            c.touch_y.as_ref(),
+            touch(ctx.touch1_y, TOUCHPAD_HEIGHT),
+        ),
+    ]
+}
+
+/// Turns controller states into MIDI messages, sending only changes
+#[derive(Debug, Default)]
+pub struct MidiMapper {
+    /// Sounding notes by button: (channel, note), to release what was
+    /// played even if the mapping changed since
+    held: HashMap<&'static str, (u8, u8)>,
+    /// Last value sent per analog input
+    sent: HashMap<&'static str, u16>,
+}
+
+impl MidiMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Messages for what changed since the last update, grouped by input
+    pub fn update(
+        &mut self,
+        config: &MidiConfig,
+        ctx: &TemplateContext,
+    ) -> Vec<(&'static str, Vec<MidiMessage>)> {
+        let mut out = Vec::new();
+
+        for (name, note, pressed) in note_inputs(&config.notes, ctx) {
+            match (self.held.get(name).copied(), pressed, note) {
+                (None, true, Some(note)) => {
+                    let channel = wire_channel(note.channel, config.channel);
+                    let velocity = match note.velocity_from {
+                        Some(MidiPressure::L2) => velocity(ctx.l2_trigger),
+                        Some(MidiPressure::R2) => velocity(ctx.r2_trigger),
+                        None => note.velocity.clamp(1, 127),
+                    };
+                    self.held.insert(name, (channel, note.note));
+                    out.push((
+                        name,
+                        vec![MidiMessage::NoteOn {
+                            channel,
+                            note: note.note,
+                            velocity,
+                        }],
+                    ));
+                }
+                (Some((channel, note)), false, _) => {
+                    self.held.remove(name);
+                    out.push((name, vec![MidiMessage::NoteOff { channel, note }]));
+                }
+                _ => {}
+            }
+        }
+
+        for (name, control, unit) in control_inputs(&config.controls, ctx) {
+            let (Some(control), Some(unit)) = (control, unit) else {
+                continue;
+            };
+            let value = control_value(control, unit);
+            if self.sent.get(name) == Some(&value) {
+                continue;
+            }
+            self.sent.insert(name, value);
+            let channel = wire_channel(control.channel, config.channel);
+            out.push((name, control_messages(control, channel, value)));
+        }
+
+        out
+    }
+
+    /// Note offs for every sounding note (on reload and exit). Controllers
+    /// are resent on the next update.
+    pub fn release_all(&mut self) -> Vec<MidiMessage> {
+        self.sent.clear();
+        self.held
+            .drain()
+            .map(|(_, (channel, note))| MidiMessage::NoteOff { channel, note })
+            .collect()
+    }
+}
+
+/// Note velocity from trigger pressure (a note on needs at least 1)
+fn velocity(pressure: f32) -> u8 {
+    ((pressure * 127.0).round() as u8).clamp(1, 127)
+}
+
+/// Open MIDI output port
+pub struct MidiPort {
+    connection: MidiOutputConnection,
+}
+
+impl MidiPort {
+    /// Create the virtual port, or connect to `connect_to`
+    pub fn open(config: &MidiConfig) -> Result<Self> {
+        let output =
+            MidiOutput::new(CLIENT_NAME).map_err(|e| anyhow!("Failed to open MIDI: {}", e))?;
+
+        let connection = match &config.connect_to {
+            Some(wanted) => {
+                let port = output
+                    .ports()
+                    .into_iter()
+                    .find(|port| {
+                        output
+                            .port_name(port)
+                            .is_ok_and(|name| name.contains(wanted.as_str()))
+                    })
+                    .with_context(|| format!("No MIDI output port matching `{}`", wanted))?;
+                output
+                    .connect(&port, &config.port_name)
+                    .map_err(|e| anyhow!("Failed to connect to MIDI port `{}`: {}", wanted, e))?
+            }
+            None => create_virtual(output, &config.port_name)?,
+        };
+        Ok(Self { connection })
+    }
+
+    pub fn send(&mut self, message: &MidiMessage) -> Result<()> {
+        self.connection
+            .send(&message.encode())
+            .map_err(|e| anyhow!("Failed to send MIDI message: {}", e))
+    }
+}
+
+#[cfg(unix)]
+fn create_virtual(output: MidiOutput, name: &str) -> Result<MidiOutputConnection> {
+    use midir::os::unix::VirtualOutput;
+
+    output
+        .create_virtual(name)
+        .map_err(|e| anyhow!("Failed to create virtual MIDI port `{}`: {}", name, e))
+}
+
+#[cfg(not(unix))]
+fn create_virtual(_output: MidiOutput, _name: &str) -> Result<MidiOutputConnection> {
+    anyhow::bail!("Virtual MIDI ports are not supported on this platform; set `midi.connect_to`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dualsense::ControllerState;
+    use serde_json::json;
+
+    fn midi_config(value: serde_json::Value) -> MidiConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_encode() {
+        let on = MidiMessage::NoteOn {
+            channel: 0,
+            note: 60,
+            velocity: 100,
+        };
+        assert_eq!(on.encode(), [0x90, 60, 100]);
+        let off = MidiMessage::NoteOff {
+            channel: 9,
+            note: 36,
+        };
+        assert_eq!(off.encode(), [0x89, 36, 0]);
+        let cc = MidiMessage::ControlChange {
+            channel: 15,
+            controller: 1,
+            value: 64,
+        };
+        assert_eq!(cc.encode(), [0xbf, 1, 64]);
+        let bend = MidiMessage::PitchBend {
+            channel: 0,
+            value: 8192,
+        };
+        assert_eq!(bend.encode(), [0xe0, 0x00, 0x40]);
+        let bend = MidiMessage::PitchBend {
+            channel: 1,
+            value: MAX_14BIT,
+        };
+        assert_eq!(bend.encode(), [0xe1, 0x7f, 0x7f]);
+    }
+
+    #[test]
+    fn test_control_resolution() {
+        let coarse: MidiControl = serde_json::from_value(json!({ "cc": 1 })).unwrap();
+        assert_eq!(control_value(&coarse, 1.0), 127);
+        assert_eq!(
+            control_messages(&coarse, 0, 127),
+            vec![MidiMessage::ControlChange {
+                channel: 0,
+                controller: 1,
+                value: 127
+            }]
+        );
+
+        let fine: MidiControl =
+            serde_json::from_value(json!({ "cc": 1, "high_resolution": true })).unwrap();
+        let value = control_value(&fine, 0.5);
+        assert_eq!(value, 8192);
+        assert_eq!(
+            control_messages(&fine, 0, value),
+            vec![
+                MidiMessage::ControlChange {
+                    channel: 0,
+                    controller: 1,
+                    value: 64
+                },
+                MidiMessage::ControlChange {
+                    channel: 0,
+                    controller: 33,
+                    value: 0
+                },
+            ]
+        );
+
+        // Pitch bend, centered with the stick at rest
+        let bend: MidiControl = serde_json::from_value(json!({ "invert": true })).unwrap();
+        assert_eq!(control_value(&bend, bipolar(0.0)), 8192);
+        assert_eq!(control_value(&bend, 1.0), 0);
+    }
+
+    #[test]
+    fn test_mapper() {
+        let config = midi_config(json!({
+            "channel": 2,
+            "notes": {
+                "cross": { "note": 60, "velocity_from": "r2" },
+                "circle": { "note": 62, "channel": 10 }
+            },
+            "controls": {
+                "left_stick_x": { "cc": 1 },
+                "touch_x": { "cc": 2 }
+            }
+        }));
+        let mut mapper = MidiMapper::new();
+
+        let mut ctx = TemplateContext::from(&ControllerState::default());
+        ctx.left_stick_x = 0.0;
+        ctx.cross = true;
+        ctx.r2_trigger = 0.5;
+        let messages = mapper.update(&config, &ctx);
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "cross",
+                    vec![MidiMessage::NoteOn {
+                        channel: 1,
+                        note: 60,
+                        velocity: 64
+                    }]
+                ),
+                (
+                    "left_stick_x",
+                    vec![MidiMessage::ControlChange {
+                        channel: 1,
+                        controller: 1,
+                        value: 64
+                    }]
+                ),
+            ]
+        );
+
+        // Nothing changed, nothing sent
+        assert!(mapper.update(&config, &ctx).is_empty());
+
+        ctx.cross = false;
+        ctx.circle = true;
+        let messages = mapper.update(&config, &ctx);
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "cross",
+                    vec![MidiMessage::NoteOff {
+                        channel: 1,
+                        note: 60
+                    }]
+                ),
+                (
+                    "circle",
+                    vec![MidiMessage::NoteOn {
+                        channel: 9,
+                        note: 62,
+                        velocity: 100
+                    }]
+                ),
+            ]
+        );
+
+        assert_eq!(
+            mapper.release_all(),
+            vec![MidiMessage::NoteOff {
+                channel: 9,
+                note: 62
+            }]
+        );
+    }
+}
@@ -0,0 +1,349 @@
+//! Motion gesture detection
+//!
+//! Detects discrete events from the IMU: shakes (accelerometer magnitude),
+//! tilts past an angle (gravity direction), flicks and twists (gyro peaks)
+//! and rate-limited orientation changes.
+
+use nalgebra::{UnitQuaternion, Vector3};
+
+use crate::config::MotionMappings;
+
+/// Detected motion event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionEvent {
+    Shake,
+    OrientationChange,
+    TiltLeft,
+    TiltRight,
+    TiltForward,
+    TiltBack,
+    FlickLeft,
+    FlickRight,
+    FlickUp,
+    FlickDown,
+    TwistLeft,
+    TwistRight,
+}
+
+impl MotionEvent {
+    /// Event part of its debounce key (`motion_shake`) and its `event_type`
+    pub fn name(&self) -> &'static str {
+        match self {
+            MotionEvent::Shake => "shake",
+            MotionEvent::OrientationChange => "orientation_change",
+            MotionEvent::TiltLeft => "tilt_left",
+            MotionEvent::TiltRight => "tilt_right",
+            MotionEvent::TiltForward => "tilt_forward",
+            MotionEvent::TiltBack => "tilt_back",
+            MotionEvent::FlickLeft => "flick_left",
+            MotionEvent::FlickRight => "flick_right",
+            MotionEvent::FlickUp => "flick_up",
+            MotionEvent::FlickDown => "flick_down",
+            MotionEvent::TwistLeft => "twist_left",
+            MotionEvent::TwistRight => "twist_right",
+        }
+    }
+}
+
+/// Motion detector tuning
+#[derive(Debug, Clone, Copy)]
+pub struct MotionSettings {
+    /// Accelerometer magnitude (G) that counts as a shake
+    pub shake_threshold: f32,
+    /// Minimum time between shakes in ms
+    pub shake_refractory_ms: u64,
+    /// Tilt angle (degrees) that fires a tilt event
+    pub tilt_threshold_deg: f32,
+    /// Tilt must fall this far below the threshold to re-arm
+    pub tilt_hysteresis_deg: f32,
+    /// Gyro rate (rad/s) around X/Y that counts as a flick
+    pub flick_threshold: f32,
+    /// Gyro rate (rad/s) around Z that counts as a twist
+    pub twist_threshold: f32,
+    /// Minimum time between flicks/twists in ms (suppresses the rebound)
+    pub flick_refractory_ms: u64,
+    /// Minimum orientation change (degrees) for an orientation event
+    pub orientation_min_delta_deg: f32,
+    /// Minimum time between orientation events in ms
+    pub orientation_rate_ms: u64,
+}
+
+impl Default for MotionSettings {
+    fn default() -> Self {
+        MotionMappings::default().motion_settings()
+    }
+}
+
+/// Tilt axis latch with hysteresis
+#[derive(Debug, Clone, Copy, Default)]
+struct TiltLatch {
+    /// -1 = negative side latched, 0 = neutral, 1 = positive side latched
+    side: i8,
+}
+
+impl TiltLatch {
+    /// Returns the side that was newly entered, if any
+    fn update(&mut self, angle: f32, threshold: f32, hysteresis: f32) -> Option<i8> {
+        let release = (threshold - hysteresis).max(0.0);
+        match self.side {
+            0 if angle >= threshold => {
+                self.side = 1;
+                Some(1)
+            }
+            0 if angle <= -threshold => {
+                self.side = -1;
+                Some(-1)
+            }
+            1 if angle < release => {
+                self.side = 0;
+                self.update(angle, threshold, hysteresis)
+            }
+            -1 if angle > -release => {
+                self.side = 0;
+                self.update(angle, threshold, hysteresis)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// IMU motion detector
+#[derive(Debug, Clone, Default)]
+pub struct MotionDetector {
+    settings: MotionSettings,
+    last_shake_ms: Option<u64>,
+    last_flick_ms: Option<u64>,
+    roll_latch: TiltLatch,
+    pitch_latch: TiltLatch,
+    last_orientation: Option<(UnitQuaternion<f32>, u64)>,
+}
+
+impl MotionDetector {
+    pub fn new(settings: MotionSettings) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    /// Change the tuning, keeping cooldowns and tilt latches
+    pub fn set_settings(&mut self, settings: MotionSettings) {
+        self.settings = settings;
+    }
+
+    /// Feed an IMU sample (gyro in rad/s, accel in G) taken at `now_ms`
+    pub fn update(
+        &mut self,
+        gyro: Vector3<f32>,
+        accel: Vector3<f32>,
+        orientation: UnitQuaternion<f32>,
+        now_ms: u64,
+    ) -> Vec<MotionEvent> {
+        let mut events = Vec::new();
+
+        // Shake: raw accelerometer magnitude, with a refractory period
+        if accel.norm() >= self.settings.shake_threshold
+            && !within(self.last_shake_ms, now_ms, self.settings.shake_refractory_ms)
+        {
+            self.last_shake_ms = Some(now_ms);
+            events.push(MotionEvent::Shake);
+        }
+
+        // Tilt from the gravity direction (X=right, Y=forward, Z=up).
+        // Skipped while shaking, where accel no longer points at gravity.
+        if accel.norm() < self.settings.shake_threshold && accel.norm() > 0.1 {
+            let (roll, pitch) = tilt_angles(accel);
+            let threshold = self.settings.tilt_threshold_deg;
+            let hysteresis = self.settings.tilt_hysteresis_deg;
+
+            match self.roll_latch.update(roll, threshold, hysteresis) {
+                Some(1) => events.push(MotionEvent::TiltRight),
+                Some(_) => events.push(MotionEvent::TiltLeft),
+                None => {}
+            }
+            match self.pitch_latch.update(pitch, threshold, hysteresis) {
+                Some(1) => events.push(MotionEvent::TiltForward),
+                Some(_) => events.push(MotionEvent::TiltBack),
+                None => {}
+            }
+        }
+
+        // Flicks and twists: first gyro sample past the threshold
+        if !within(self.last_flick_ms, now_ms, self.settings.flick_refractory_ms) {
+            let flick = self.settings.flick_threshold;
+            let twist = self.settings.twist_threshold;
+            let peak = if gyro.z.abs() >= twist {
+                Some(if gyro.z > 0.0 {
+                    MotionEvent::TwistLeft
+                } else {
+                    MotionEvent::TwistRight
+                })
+            } else if gyro.x.abs() >= flick && gyro.x.abs() >= gyro.y.abs() {
+                Some(if gyro.x > 0.0 {
+                    MotionEvent::FlickUp
+                } else {
+                    MotionEvent::FlickDown
+                })
+            } else if gyro.y.abs() >= flick {
+                Some(if gyro.y > 0.0 {
+                    MotionEvent::FlickRight
+                } else {
+                    MotionEvent::FlickLeft
+                })
+            } else {
+                None
+            };
+
+            if let Some(event) = peak {
+                self.last_flick_ms = Some(now_ms);
+                events.push(event);
+            }
+        }
+
+        // Orientation change: rate-limited, and only past a minimum angle
+        match self.last_orientation {
+            None => self.last_orientation = Some((orientation, now_ms)),
+            Some((last, last_ms)) => {
+                let delta = last.angle_to(&orientation).to_degrees();
+                if delta >= self.settings.orientation_min_delta_deg
+                    && now_ms.saturating_sub(last_ms) >= self.settings.orientation_rate_ms
+                {
+                    self.last_orientation = Some((orientation, now_ms));
+                    events.push(MotionEvent::OrientationChange);
+                }
+            }
+        }
+
+        events
+    }
+}
+
+/// Roll and pitch (degrees) from the gravity vector
+pub fn tilt_angles(accel: Vector3<f32>) -> (f32, f32) {
+    let roll = accel.x.atan2(accel.z).to_degrees();
+    let pitch = accel.y.atan2(accel.z).to_degrees();
+    (roll, pitch)
+}
+
+fn within(last: Option<u64>, now_ms: u64, window_ms: u64) -> bool {
+    last.map(|t| now_ms.saturating_sub(t) < window_ms).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAT: Vector3<f32> = Vector3::new(0.0, 0.0, 1.0);
+
+    fn rest() -> Vector3<f32> {
+        Vector3::zeros()
+    }
+
+    /// Run a synthetic (time_ms, gyro, accel) sequence and collect events
+    fn run(
+        detector: &mut MotionDetector,
+        samples: &[(u64, Vector3<f32>, Vector3<f32>)],
+    ) -> Vec<MotionEvent> {
+        samples
+            .iter()
+            .flat_map(|&(t, g, a)| detector.update(g, a, UnitQuaternion::identity(), t))
+            .collect()
+    }
+
+    fn tilted_roll(deg: f32) -> Vector3<f32> {
+        let r = deg.to_radians();
+        Vector3::new(r.sin(), 0.0, r.cos())
+    }
+
+    #[test]
+    fn test_shake_with_refractory() {
+        let mut d = MotionDetector::default();
+        let hard = Vector3::new(2.5, 0.3, 1.0);
+        let events = run(
+            &mut d,
+            &[
+                (0, rest(), FLAT),
+                (10, rest(), hard),
+                (20, rest(), -hard),
+                (30, rest(), hard),
+                (600, rest(), hard),
+            ],
+        );
+        assert_eq!(events, vec![MotionEvent::Shake, MotionEvent::Shake]);
+    }
+
+    #[test]
+    fn test_tilt_hysteresis() {
+        let mut d = MotionDetector::default();
+        let events = run(
+            &mut d,
+            &[
+                (0, rest(), FLAT),
+                (10, rest(), tilted_roll(31.0)),
+                // Hovering around the threshold does not re-fire
+                (20, rest(), tilted_roll(28.0)),
+                (30, rest(), tilted_roll(32.0)),
+                // Back below threshold - hysteresis re-arms
+                (40, rest(), tilted_roll(10.0)),
+                (50, rest(), tilted_roll(35.0)),
+                (60, rest(), tilted_roll(-40.0)),
+            ],
+        );
+        assert_eq!(
+            events,
+            vec![
+                MotionEvent::TiltRight,
+                MotionEvent::TiltRight,
+                MotionEvent::TiltLeft
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tilt_forward_back() {
+        let mut d = MotionDetector::default();
+        let forward = Vector3::new(0.0, 0.7, 0.7);
+        let back = Vector3::new(0.0, -0.7, 0.7);
+        let events = run(&mut d, &[(0, rest(), forward), (10, rest(), back)]);
+        assert_eq!(events, vec![MotionEvent::TiltForward, MotionEvent::TiltBack]);
+    }
+
+    #[test]
+    fn test_flick_ignores_rebound() {
+        let mut d = MotionDetector::default();
+        let events = run(
+            &mut d,
+            &[
+                (0, Vector3::new(0.0, 3.0, 0.0), FLAT),
+                (10, Vector3::new(0.0, 9.0, 0.0), FLAT),
+                (20, Vector3::new(0.0, 12.0, 0.0), FLAT),
+                (60, Vector3::new(0.0, -8.0, 0.0), FLAT),
+                (400, Vector3::new(-7.0, 1.0, 0.0), FLAT),
+            ],
+        );
+        assert_eq!(events, vec![MotionEvent::FlickRight, MotionEvent::FlickDown]);
+    }
+
+    #[test]
+    fn test_twist() {
+        let mut d = MotionDetector::default();
+        let events = run(&mut d, &[(0, Vector3::new(0.5, 0.5, -7.5), FLAT)]);
+        assert_eq!(events, vec![MotionEvent::TwistRight]);
+    }
+
+    #[test]
+    fn test_orientation_rate_limit() {
+        let mut d = MotionDetector::new(MotionSettings {
+            orientation_rate_ms: 100,
+            ..Default::default()
+        });
+        let step = |deg: f32| UnitQuaternion::from_euler_angles(0.0, 0.0, deg.to_radians());
+
+        let mut events = Vec::new();
+        for (t, deg) in [(0, 0.0), (50, 10.0), (120, 11.0), (130, 30.0), (300, 11.5)] {
+            events.extend(d.update(rest(), FLAT, step(deg), t));
+        }
+        // 50ms is too soon, 120ms fires, 130ms is too soon, 300ms moved < 2 degrees
+        assert_eq!(events, vec![MotionEvent::OrientationChange]);
+    }
+}
@@ -0,0 +1,243 @@
+//! MQTT publishing
+//!
+//! Keeps one broker connection for `mqtt` actions and state updates. The
+//! event loop runs on its own task and reconnects after errors; publishes
+//! never wait on the network, so a missing broker can't stall the action
+//! worker. With a `last_will`, the broker announces the controller offline
+//! when the connection drops, and the status is set online on each connect
+//! and offline again on a clean exit.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rumqttc::{
+    AsyncClient, Event, EventLoop, Incoming, LastWill, MqttOptions, Outgoing, QoS,
+    TlsConfiguration, Transport,
+};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::config::{MqttConfig, MqttLastWill};
+
+/// Publishes waiting for the connection before new ones are dropped
+const REQUEST_CAPACITY: usize = 64;
+
+/// Wait between reconnect attempts
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// How long a clean exit waits for the status and disconnect to go out
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Shortest keepalive the client accepts
+const MIN_KEEPALIVE: Duration = Duration::from_secs(5);
+
+/// Connection to an MQTT broker
+pub struct MqttPublisher {
+    client: AsyncClient,
+    last_will: Option<MqttLastWill>,
+    event_loop: JoinHandle<()>,
+}
+
+impl MqttPublisher {
+    /// Start connecting to the broker in `config` (needs a Tokio runtime).
+    /// Fails only on bad settings, e.g. a credential variable that isn't set.
+    pub fn connect(config: &MqttConfig) -> Result<Self> {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(config.keepalive_s).max(MIN_KEEPALIVE));
+
+        let env = |name: &str| {
+            std::env::var(name)
+                .with_context(|| format!("MQTT: environment variable {} is not set", name))
+        };
+        if let Some(username_env) = &config.username_env {
+            let password = config.password_env.as_deref().map(env).transpose()?;
+            options.set_credentials(env(username_env)?, password.unwrap_or_default());
+        }
+        if config.tls {
+            options.set_transport(Transport::tls_with_config(TlsConfiguration::Native));
+        }
+        if let Some(will) = &config.last_will {
+            options.set_last_will(LastWill::new(
+                &will.topic,
+                will.payload.clone(),
+                qos(will.qos)?,
+                will.retain,
+            ));
+        }
+
+        let (client, event_loop) = AsyncClient::new(options, REQUEST_CAPACITY);
+        let broker = format!("{}:{}", config.host, config.port);
+        let event_loop = tokio::spawn(drive(
+            event_loop,
+            client.clone(),
+            config.last_will.clone(),
+            broker,
+        ));
+
+        Ok(Self {
+            client,
+            last_will: config.last_will.clone(),
+            event_loop,
+        })
+    }
+
+    /// Queue a message; fails if too many are already waiting
+    pub fn publish(
+        &self,
+        topic: &str,
+        payload: Vec<u8>,
+        qos_level: u8,
+        retain: bool,
+    ) -> Result<()> {
+        self.client
+            .try_publish(topic, qos(qos_level)?, retain, payload)
+            .with_context(|| format!("Failed to publish to MQTT topic {}", topic))
+    }
+
+    /// Announce the controller offline and disconnect
+    pub async fn shutdown(mut self) {
+        if let Some(will) = &self.last_will {
+            let qos = qos(will.qos).unwrap_or(QoS::AtMostOnce);
+            self.client
+                .try_publish(&will.topic, qos, will.retain, will.payload.as_bytes())
+                .ok();
+        }
+        self.client.try_disconnect().ok();
+        if tokio::time::timeout(DISCONNECT_TIMEOUT, &mut self.event_loop)
+            .await
+            .is_err()
+        {
+            debug!("MQTT broker unreachable, dropping connection");
+            self.event_loop.abort();
+        }
+    }
+}
+
+/// Poll the connection until a clean disconnect, reconnecting after errors
+async fn drive(
+    mut event_loop: EventLoop,
+    client: AsyncClient,
+    last_will: Option<MqttLastWill>,
+    broker: String,
+) {
+    let mut connected = true;
+    loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Incoming::ConnAck(_))) => {
+                info!("MQTT connected to {}", broker);
+                connected = true;
+                let online = last_will
+                    .as_ref()
+                    .and_then(|will| Some((will, will.online_payload.as_ref()?)));
+                if let Some((will, payload)) = online {
+                    let qos = qos(will.qos).unwrap_or(QoS::AtMostOnce);
+                    client
+                        .try_publish(&will.topic, qos, will.retain, payload.as_bytes())
+                        .ok();
+                }
+            }
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+            Ok(_) => {}
+            Err(e) => {
+                // Warn once per outage
+                if connected {
+                    warn!("MQTT connection to {} failed: {}", broker, e);
+                    connected = false;
+                } else {
+                    debug!("MQTT reconnect to {} failed: {}", broker, e);
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+fn qos(level: u8) -> Result<QoS> {
+    match level {
+        0 => Ok(QoS::AtMostOnce),
+        1 => Ok(QoS::AtLeastOnce),
+        2 => Ok(QoS::ExactlyOnce),
+        _ => anyhow::bail!("MQTT QoS must be 0, 1 or 2, got {}", level),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Read one MQTT packet: (first header byte, body)
+    async fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let header = stream.read_u8().await.unwrap();
+        let mut len = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = stream.read_u8().await.unwrap();
+            len |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+        }
+        let mut body = vec![0; len];
+        stream.read_exact(&mut body).await.unwrap();
+        (header, body)
+    }
+
+    /// Topic, payload and retain flag of a QoS 0 PUBLISH
+    async fn read_publish(stream: &mut TcpStream) -> (String, String, bool) {
+        let (header, body) = read_packet(stream).await;
+        assert_eq!(header >> 4, 3, "expected PUBLISH");
+        let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+        let topic = String::from_utf8(body[2..2 + topic_len].to_vec()).unwrap();
+        let payload = String::from_utf8(body[2 + topic_len..].to_vec()).unwrap();
+        (topic, payload, header & 1 == 1)
+    }
+
+    #[tokio::test]
+    async fn test_publish_with_last_will() {
+        // In-process stand-in for a broker
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let config: MqttConfig = serde_json::from_value(json!({
+            "host": "127.0.0.1",
+            "port": port,
+            "last_will": { "topic": "pad/status", "payload": "offline", "online_payload": "online" }
+        }))
+        .unwrap();
+
+        let publisher = MqttPublisher::connect(&config).unwrap();
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let (header, connect) = read_packet(&mut stream).await;
+        assert_eq!(header, 0x10, "expected CONNECT");
+        // Protocol name (6 bytes), level, then flags with the will bit
+        assert_ne!(connect[7] & 0x04, 0);
+        assert!(connect.windows(7).any(|w| w == b"offline"));
+        stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap();
+
+        let online = read_publish(&mut stream).await;
+        assert_eq!(online, ("pad/status".into(), "online".into(), true));
+
+        publisher
+            .publish("pad/cross", b"pressed".to_vec(), 0, false)
+            .unwrap();
+        let message = read_publish(&mut stream).await;
+        assert_eq!(message, ("pad/cross".into(), "pressed".into(), false));
+
+        let shutdown = tokio::spawn(publisher.shutdown());
+        let offline = read_publish(&mut stream).await;
+        assert_eq!(offline, ("pad/status".into(), "offline".into(), true));
+        let (header, _) = read_packet(&mut stream).await;
+        assert_eq!(header, 0xE0, "expected DISCONNECT");
+        shutdown.await.unwrap();
+    }
+
+    #[test]
+    fn test_qos_levels() {
+        assert_eq!(qos(1).unwrap(), QoS::AtLeastOnce);
+        assert!(qos(3).is_err());
+    }
+}
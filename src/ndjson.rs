@@ -0,0 +1,314 @@
+//! NDJSON state streaming
+//!
+//! Writes one JSON object per line, for other processes to tail: to
+//! stdout, a regular file, or a FIFO. A FIFO is created if missing and
+//! opened without blocking, so the stream keeps polling while nobody
+//! reads; records are dropped until a reader connects, and again after it
+//! goes away (EPIPE) until the next one does. A closed stdout ends the
+//! stream.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tracing::info;
+
+use crate::dualsense::ControllerState;
+use crate::spatial::{SpatialMode, SpatialState};
+
+/// One streamed line: the full controller state, plus the integrated
+/// spatial state when enabled
+#[derive(Debug, Serialize)]
+pub struct StateRecord<'a> {
+    /// Milliseconds since the stream started
+    pub time_ms: u64,
+    #[serde(flatten)]
+    pub state: &'a ControllerState,
+    /// Sensor-fusion orientation `[w, x, y, z]`
+    pub orientation: [f32; 4],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spatial: Option<SpatialRecord>,
+}
+
+/// Integrated position, motion and orientation
+#[derive(Debug, Serialize)]
+pub struct SpatialRecord {
+    pub mode: SpatialMode,
+    /// mm
+    pub position: [f32; 3],
+    /// Smoothed velocity, mm/s
+    pub velocity: [f32; 3],
+    /// G
+    pub linear_accel: [f32; 3],
+    /// rad/s
+    pub angular_velocity: [f32; 3],
+    /// `[w, x, y, z]`
+    pub orientation: [f32; 4],
+}
+
+impl<'a> StateRecord<'a> {
+    pub fn new(time_ms: u64, state: &'a ControllerState, spatial: Option<&SpatialState>) -> Self {
+        let q = state.orientation.quaternion();
+        Self {
+            time_ms,
+            state,
+            orientation: [q.w, q.i, q.j, q.k],
+            spatial: spatial.map(|spatial| {
+                let q = spatial.orientation();
+                SpatialRecord {
+                    mode: spatial.mode,
+                    position: spatial.position,
+                    velocity: spatial.smoothed_velocity(),
+                    linear_accel: spatial.linear_accel,
+                    angular_velocity: spatial.angular_velocity,
+                    orientation: [q.w, q.x, q.y, q.z],
+                }
+            }),
+        }
+    }
+}
+
+/// Where records go
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamOutput {
+    Stdout,
+    File(PathBuf),
+    Fifo(PathBuf),
+}
+
+impl StreamOutput {
+    /// `-` is stdout; other paths are files, or FIFOs with `fifo`
+    pub fn parse(output: &str, fifo: bool) -> Self {
+        match output {
+            "-" => StreamOutput::Stdout,
+            path if fifo => StreamOutput::Fifo(PathBuf::from(path)),
+            path => StreamOutput::File(PathBuf::from(path)),
+        }
+    }
+}
+
+impl std::fmt::Display for StreamOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamOutput::Stdout => write!(f, "stdout"),
+            StreamOutput::File(path) => write!(f, "{}", path.display()),
+            StreamOutput::Fifo(path) => write!(f, "FIFO {}", path.display()),
+        }
+    }
+}
+
+/// What became of a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Written {
+    Sent,
+    /// No reader on the FIFO
+    Dropped,
+    /// stdout was closed; the stream should stop
+    Closed,
+}
+
+/// Line-per-record writer
+pub struct NdjsonWriter {
+    output: StreamOutput,
+    sink: Option<Box<dyn Write + Send>>,
+}
+
+impl NdjsonWriter {
+    /// Open the output, creating the file or FIFO
+    pub fn open(output: StreamOutput) -> Result<Self> {
+        let sink: Option<Box<dyn Write + Send>> = match &output {
+            StreamOutput::Stdout => Some(Box::new(io::stdout())),
+            StreamOutput::File(path) => {
+                Some(Box::new(File::create(path).with_context(|| {
+                    format!("Failed to create {}", path.display())
+                })?))
+            }
+            StreamOutput::Fifo(path) => {
+                fifo::create(path)?;
+                None
+            }
+        };
+        Ok(Self { output, sink })
+    }
+
+    pub fn write<T: Serialize>(&mut self, record: &T) -> Result<Written> {
+        let mut line = serde_json::to_vec(record).context("Failed to serialize record")?;
+        line.push(b'\n');
+
+        if self.sink.is_none() {
+            let StreamOutput::Fifo(path) = &self.output else {
+                return Ok(Written::Closed);
+            };
+            match fifo::open_writer(path)? {
+                Some(file) => {
+                    info!("Reader connected to {}", path.display());
+                    self.sink = Some(Box::new(file));
+                }
+                None => return Ok(Written::Dropped),
+            }
+        }
+
+        let Some(sink) = self.sink.as_mut() else {
+            return Ok(Written::Dropped);
+        };
+        match sink.write_all(&line).and_then(|_| sink.flush()) {
+            Ok(()) => Ok(Written::Sent),
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                self.sink = None;
+                match &self.output {
+                    StreamOutput::Fifo(path) => {
+                        info!("Reader disconnected from {}", path.display());
+                        Ok(Written::Dropped)
+                    }
+                    _ => Ok(Written::Closed),
+                }
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to write to {}", self.output)),
+        }
+    }
+}
+
+#[cfg(unix)]
+mod fifo {
+    use std::ffi::CString;
+    use std::fs::{File, OpenOptions};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+
+    use anyhow::{Context, Result};
+
+    /// Make a FIFO at `path` unless one is there already
+    pub fn create(path: &Path) -> Result<()> {
+        match std::fs::metadata(path) {
+            Ok(metadata) if metadata.file_type().is_fifo() => return Ok(()),
+            Ok(_) => anyhow::bail!("{} exists and is not a FIFO", path.display()),
+            Err(_) => {}
+        }
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        // SAFETY: plain syscall on a valid C string
+        if unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) } != 0 {
+            return Err(std::io::Error::last_os_error())
+                .with_context(|| format!("Failed to create FIFO {}", path.display()));
+        }
+        Ok(())
+    }
+
+    /// Open the write end if a reader is there (`None` otherwise). Writes
+    /// then block while the reader catches up.
+    pub fn open_writer(path: &Path) -> Result<Option<File>> {
+        let file = match OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+        {
+            Ok(file) => file,
+            Err(e) if e.raw_os_error() == Some(libc::ENXIO) => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to open FIFO {}", path.display()))
+            }
+        };
+        // SAFETY: fcntl on a descriptor we own
+        unsafe {
+            let flags = libc::fcntl(file.as_raw_fd(), libc::F_GETFL);
+            libc::fcntl(file.as_raw_fd(), libc::F_SETFL, flags & !libc::O_NONBLOCK);
+        }
+        Ok(Some(file))
+    }
+}
+
+#[cfg(not(unix))]
+mod fifo {
+    use std::fs::File;
+    use std::path::Path;
+
+    use anyhow::Result;
+
+    pub fn create(_path: &Path) -> Result<()> {
+        anyhow::bail!("FIFOs are only supported on Unix")
+    }
+
+    pub fn open_writer(_path: &Path) -> Result<Option<File>> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_record_fields() {
+        let state = ControllerState::default();
+        let record = StateRecord::new(5, &state, None);
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value["time_ms"], 5);
+        assert!(value["buttons"].is_object());
+        assert!(value["left_stick"].is_object());
+        assert_eq!(value["orientation"].as_array().unwrap().len(), 4);
+        assert!(value.get("spatial").is_none());
+    }
+
+    #[test]
+    fn test_file_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stream.ndjson");
+        let mut writer = NdjsonWriter::open(StreamOutput::File(path.clone())).unwrap();
+        for i in 0..2 {
+            let written = writer.write(&serde_json::json!({ "i": i })).unwrap();
+            assert_eq!(written, Written::Sent);
+        }
+        drop(writer);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![serde_json::json!({"i": 0}), serde_json::json!({"i": 1})]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fifo_survives_reader_disconnect() {
+        use std::io::Read;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stream.fifo");
+        let open_reader = || {
+            std::fs::OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(&path)
+                .unwrap()
+        };
+        let record = serde_json::json!({ "x": 1 });
+
+        let mut writer = NdjsonWriter::open(StreamOutput::Fifo(path.clone())).unwrap();
+        assert_eq!(writer.write(&record).unwrap(), Written::Dropped);
+
+        let mut reader = open_reader();
+        assert_eq!(writer.write(&record).unwrap(), Written::Sent);
+        let mut buf = [0u8; 64];
+        let len = reader.read(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"{\"x\":1}\n");
+
+        // EPIPE: dropped, not an error
+        drop(reader);
+        assert_eq!(writer.write(&record).unwrap(), Written::Dropped);
+        assert_eq!(writer.write(&record).unwrap(), Written::Dropped);
+
+        let mut reader = open_reader();
+        assert_eq!(writer.write(&record).unwrap(), Written::Sent);
+        let len = reader.read(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"{\"x\":1}\n");
+    }
+}
@@ -0,0 +1,319 @@
+//! OSC (Open Sound Control) output over UDP
+//!
+//! Encodes OSC 1.0 messages and bundles and sends them from a UDP socket.
+//! Arguments come from template context fields, typed by the field (sticks
+//! and the quaternion are floats, buttons and counters ints) unless the
+//! config names a type.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use tokio::net::UdpSocket;
+
+use crate::config::{OscArg, OscLiteral, OscMessageConfig, OscType};
+use crate::templates::Template;
+
+/// An OSC argument
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscValue {
+    Int(i32),
+    Float(f32),
+    String(String),
+    Bool(bool),
+}
+
+/// An OSC message
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscValue>,
+}
+
+/// What goes into one datagram
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscPacket {
+    Message(OscMessage),
+    /// Messages to apply together, timetagged "immediately"
+    Bundle(Vec<OscMessage>),
+}
+
+impl OscPacket {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match self {
+            OscPacket::Message(message) => encode_message(message, &mut buf),
+            OscPacket::Bundle(messages) => {
+                write_string("#bundle", &mut buf);
+                // Timetag 1: "immediately"
+                buf.extend_from_slice(&1u64.to_be_bytes());
+                for message in messages {
+                    let mut element = Vec::new();
+                    encode_message(message, &mut element);
+                    buf.extend_from_slice(&(element.len() as i32).to_be_bytes());
+                    buf.extend_from_slice(&element);
+                }
+            }
+        }
+        buf
+    }
+}
+
+fn encode_message(message: &OscMessage, buf: &mut Vec<u8>) {
+    write_string(&message.address, buf);
+
+    let mut tags = String::from(",");
+    for arg in &message.args {
+        tags.push(match arg {
+            OscValue::Int(_) => 'i',
+            OscValue::Float(_) => 'f',
+            OscValue::String(_) => 's',
+            OscValue::Bool(true) => 'T',
+            OscValue::Bool(false) => 'F',
+        });
+    }
+    write_string(&tags, buf);
+
+    for arg in &message.args {
+        match arg {
+            OscValue::Int(i) => buf.extend_from_slice(&i.to_be_bytes()),
+            OscValue::Float(f) => buf.extend_from_slice(&f.to_be_bytes()),
+            OscValue::String(s) => write_string(s, buf),
+            // Carried in the type tag
+            OscValue::Bool(_) => {}
+        }
+    }
+}
+
+/// Null-terminated, padded to a multiple of 4 bytes
+fn write_string(s: &str, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(s.as_bytes());
+    let padding = 4 - s.len() % 4;
+    buf.resize(buf.len() + padding, 0);
+}
+
+/// Build a message from its config. `fields` is the serialized template
+/// context; `render` renders template strings.
+pub fn build_message(
+    config: &OscMessageConfig,
+    fields: &Value,
+    render: impl Fn(&Template) -> Result<String>,
+) -> Result<OscMessage> {
+    let address = render(&config.address).context("Failed to render OSC address")?;
+    let args = config
+        .args
+        .iter()
+        .map(|arg| match arg {
+            OscArg::Field { field, kind } => {
+                let value = lookup(fields, field)
+                    .with_context(|| format!("Unknown OSC argument field `{}`", field))?;
+                convert(value, *kind)
+            }
+            OscArg::Template { value, kind } => {
+                let rendered = render(value).context("Failed to render OSC argument")?;
+                Ok(parse_rendered(&rendered, *kind))
+            }
+            OscArg::Literal(literal) => Ok(match literal {
+                OscLiteral::Bool(b) => OscValue::Bool(*b),
+                OscLiteral::Int(i) => OscValue::Int(saturate(*i)),
+                OscLiteral::Float(f) => OscValue::Float(*f as f32),
+                OscLiteral::String(s) => OscValue::String(s.clone()),
+            }),
+        })
+        .collect::<Result<_>>()?;
+    Ok(OscMessage { address, args })
+}
+
+/// Context field by name; `vars.name` reaches into variables
+fn lookup<'a>(fields: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(fields, |value, key| value.get(key))
+}
+
+/// Typed argument from a context value
+fn convert(value: &Value, kind: Option<OscType>) -> Result<OscValue> {
+    let number = || match value {
+        Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => value.as_f64(),
+    };
+    let text = || match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+
+    Ok(match kind {
+        None => match value {
+            Value::Bool(b) => OscValue::Int(*b as i32),
+            Value::Number(n) if n.is_f64() => OscValue::Float(n.as_f64().unwrap_or(0.0) as f32),
+            Value::Number(n) => OscValue::Int(saturate(n.as_i64().unwrap_or(i64::MAX))),
+            Value::String(s) => OscValue::String(s.clone()),
+            Value::Null => anyhow::bail!("OSC argument has no value"),
+            other => OscValue::String(other.to_string()),
+        },
+        Some(OscType::Int) => OscValue::Int(number().map_or(0, |n| n.round() as i32)),
+        Some(OscType::Float) => OscValue::Float(number().unwrap_or(0.0) as f32),
+        Some(OscType::String) => OscValue::String(text()),
+        Some(OscType::Bool) => OscValue::Bool(crate::vars::is_truthy(value)),
+    })
+}
+
+/// Typed argument from a rendered template (inferred: int, float, bool, string)
+fn parse_rendered(rendered: &str, kind: Option<OscType>) -> OscValue {
+    let trimmed = rendered.trim();
+    match kind {
+        Some(OscType::String) => OscValue::String(rendered.to_string()),
+        Some(kind) => {
+            let value = serde_json::from_str(trimmed).unwrap_or(Value::Null);
+            convert(&value, Some(kind)).unwrap_or(OscValue::Int(0))
+        }
+        None => {
+            if let Ok(i) = trimmed.parse::<i32>() {
+                OscValue::Int(i)
+            } else if let Ok(f) = trimmed.parse::<f32>() {
+                OscValue::Float(f)
+            } else if let Ok(b) = trimmed.parse::<bool>() {
+                OscValue::Bool(b)
+            } else {
+                OscValue::String(rendered.to_string())
+            }
+        }
+    }
+}
+
+fn saturate(i: i64) -> i32 {
+    i.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+/// UDP socket sending OSC packets to one target
+pub struct OscSender {
+    socket: UdpSocket,
+    target: String,
+}
+
+impl OscSender {
+    /// Bind to `bind` (e.g. `0.0.0.0:0`) and send to `target` (`host:port`)
+    pub async fn bind(bind: &str, target: &str) -> Result<Self> {
+        let socket = UdpSocket::bind(bind)
+            .await
+            .with_context(|| format!("Failed to bind OSC socket to {}", bind))?;
+        Ok(Self {
+            socket,
+            target: target.to_string(),
+        })
+    }
+
+    pub async fn send(&self, packet: &OscPacket) -> Result<()> {
+        self.socket
+            .send_to(&packet.encode(), &self.target)
+            .await
+            .with_context(|| format!("Failed to send OSC packet to {}", self.target))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(address: &str, args: Vec<OscValue>) -> OscMessage {
+        OscMessage {
+            address: address.to_string(),
+            args,
+        }
+    }
+
+    #[test]
+    fn test_encode_message() {
+        let packet = OscPacket::Message(message(
+            "/pad",
+            vec![
+                OscValue::Int(1),
+                OscValue::Float(0.5),
+                OscValue::String("hi".to_string()),
+                OscValue::Bool(true),
+            ],
+        ));
+        let mut expected = b"/pad\0\0\0\0,ifsT\0\0\0".to_vec();
+        expected.extend_from_slice(&1i32.to_be_bytes());
+        expected.extend_from_slice(&0.5f32.to_be_bytes());
+        expected.extend_from_slice(b"hi\0\0");
+        assert_eq!(packet.encode(), expected);
+    }
+
+    #[test]
+    fn test_encode_bundle() {
+        let packet = OscPacket::Bundle(vec![message("/a", vec![OscValue::Int(7)])]);
+        let mut expected = b"#bundle\0".to_vec();
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        expected.extend_from_slice(&12i32.to_be_bytes());
+        expected.extend_from_slice(b"/a\0\0,i\0\0");
+        expected.extend_from_slice(&7i32.to_be_bytes());
+        assert_eq!(packet.encode(), expected);
+    }
+
+    #[test]
+    fn test_argument_types() {
+        let fields = json!({
+            "cross": true,
+            "left_stick_x": 0.25,
+            "battery_percent": 80,
+            "event_source": "cross",
+            "vars": { "mode": 2 }
+        });
+        let args: Vec<OscArg> = serde_json::from_value(json!([
+            { "field": "cross" },
+            { "field": "left_stick_x" },
+            { "field": "battery_percent", "type": "float" },
+            { "field": "vars.mode" },
+            { "value": "{{event_source}}" },
+            { "value": "0.5" },
+            3,
+            "text"
+        ]))
+        .unwrap();
+        let config = OscMessageConfig {
+            address: "/pad/{{event_source}}".into(),
+            args,
+        };
+        let render = |t: &Template| Ok(t.replace("{{event_source}}", "cross"));
+
+        let built = build_message(&config, &fields, render).unwrap();
+        assert_eq!(built.address, "/pad/cross");
+        assert_eq!(
+            built.args,
+            vec![
+                OscValue::Int(1),
+                OscValue::Float(0.25),
+                OscValue::Float(80.0),
+                OscValue::Int(2),
+                OscValue::String("cross".to_string()),
+                OscValue::Float(0.5),
+                OscValue::Int(3),
+                OscValue::String("text".to_string()),
+            ]
+        );
+
+        let unknown = OscMessageConfig {
+            address: "/x".into(),
+            args: vec![OscArg::Field {
+                field: "nope".to_string(),
+                kind: None,
+            }],
+        };
+        assert!(build_message(&unknown, &fields, render).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_over_udp() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let target = receiver.local_addr().unwrap().to_string();
+        let sender = OscSender::bind("127.0.0.1:0", &target).await.unwrap();
+
+        let packet = OscPacket::Message(message("/ping", vec![OscValue::Int(42)]));
+        sender.send(&packet).await.unwrap();
+
+        let mut buf = [0u8; 64];
+        let len = receiver.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], packet.encode().as_slice());
+    }
+}
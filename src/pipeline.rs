@@ -0,0 +1,418 @@
+//! Bounded job queue between event detection and action execution
+//!
+//! The poll loop pushes jobs and an async worker pops them, so a slow
+//! action never delays the next HID read. Each job has a key (the input or
+//! stream that produced it): jobs of one key run one at a time in the order
+//! they were queued, while jobs of different keys run side by side, up to a
+//! small cap, so one slow output doesn't hold up the others. A barrier job
+//! (a reload) waits for everything queued before it and runs alone. When
+//! the queue is full the overflow policy decides what gives.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+/// What to do when a job arrives at a full queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued job
+    DropOldest,
+    /// Replace a queued continuous job (state update, MIDI controller) with
+    /// the newer one for the same key, else drop the oldest
+    #[default]
+    Coalesce,
+    /// Wait for space (stalls the poll loop while the queue is full)
+    Block,
+}
+
+/// Queue counters, for logging
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueueMetrics {
+    /// Jobs waiting right now
+    pub depth: usize,
+    /// Deepest the queue has been
+    pub max_depth: usize,
+    pub enqueued: u64,
+    pub started: u64,
+    /// Jobs dropped by `drop_oldest` (or `coalesce` without a match)
+    pub dropped: u64,
+    /// Continuous jobs replaced by a newer one with the same key
+    pub coalesced: u64,
+    /// Mean time from queueing to start (ms)
+    pub avg_latency_ms: f64,
+    /// Longest time from queueing to start (ms)
+    pub max_latency_ms: f64,
+}
+
+/// How the queue treats a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobKind {
+    /// An event, e.g. a press: dropped on overflow, never replaced
+    Discrete,
+    /// Latest value of something continuous: a newer job with the same
+    /// key replaces it under `coalesce`
+    Continuous,
+    /// Control message: never dropped
+    Control,
+    /// Control message that waits for the jobs before it and runs alone
+    Barrier,
+}
+
+struct Queued<T> {
+    key: String,
+    job: T,
+    queued_at: Instant,
+    kind: JobKind,
+}
+
+impl<T> Queued<T> {
+    fn droppable(&self) -> bool {
+        matches!(self.kind, JobKind::Discrete | JobKind::Continuous)
+    }
+}
+
+struct QueueState<T> {
+    jobs: VecDeque<Queued<T>>,
+    /// Keys of the jobs handed out and not done yet
+    running: HashSet<String>,
+    barrier_running: bool,
+    closed: bool,
+    metrics: QueueMetrics,
+    total_latency_ms: f64,
+}
+
+/// Bounded multi-producer job queue, handing out one job per key at a time
+pub struct JobQueue<T> {
+    state: Mutex<QueueState<T>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    /// Most jobs handed out at once
+    workers: usize,
+    job_ready: Notify,
+    space_ready: Notify,
+}
+
+impl<T> JobQueue<T> {
+    pub fn new(capacity: usize, policy: OverflowPolicy, workers: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                jobs: VecDeque::new(),
+                running: HashSet::new(),
+                barrier_running: false,
+                closed: false,
+                metrics: QueueMetrics::default(),
+                total_latency_ms: 0.0,
+            }),
+            capacity: capacity.max(1),
+            policy,
+            workers: workers.max(1),
+            job_ready: Notify::new(),
+            space_ready: Notify::new(),
+        }
+    }
+
+    /// Queue an event job, applying the overflow policy. Returns false once closed.
+    pub async fn push(&self, key: String, job: T) -> bool {
+        self.push_as(key, job, JobKind::Discrete).await
+    }
+
+    /// Queue the latest value of something continuous (a state snapshot,
+    /// a MIDI controller); under `coalesce` it replaces a queued one with
+    /// the same key in place. Returns false once closed.
+    pub async fn push_continuous(&self, key: String, job: T) -> bool {
+        self.push_as(key, job, JobKind::Continuous).await
+    }
+
+    async fn push_as(&self, key: String, job: T, kind: JobKind) -> bool {
+        let mut job = Some(job);
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.closed {
+                    return false;
+                }
+                if state.jobs.len() < self.capacity || self.policy != OverflowPolicy::Block {
+                    let job = job.take().expect("job is queued once");
+                    if let Some(job) = self.overflow(&mut state, &key, kind, job) {
+                        Self::enqueue(&mut state, key, job, kind);
+                        self.job_ready.notify_one();
+                    }
+                    return true;
+                }
+            }
+            self.space_ready.notified().await;
+        }
+    }
+
+    /// Queue a job regardless of capacity that overflow never drops (for
+    /// control messages). Returns false once closed.
+    pub fn push_unbounded(&self, key: String, job: T) -> bool {
+        self.push_control(key, job, JobKind::Control)
+    }
+
+    /// Queue a control job that starts once every job queued before it is
+    /// done, with nothing else running until it is. Returns false once closed.
+    pub fn push_barrier(&self, key: String, job: T) -> bool {
+        self.push_control(key, job, JobKind::Barrier)
+    }
+
+    fn push_control(&self, key: String, job: T, kind: JobKind) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return false;
+        }
+        Self::enqueue(&mut state, key, job, kind);
+        self.job_ready.notify_one();
+        true
+    }
+
+    /// Make room for a job on a full queue (non-blocking policies). A
+    /// continuous job under `coalesce` takes over the queued one with its
+    /// key instead, keeping its place; then there is nothing left to queue.
+    fn overflow(&self, state: &mut QueueState<T>, key: &str, kind: JobKind, job: T) -> Option<T> {
+        if state.jobs.len() < self.capacity {
+            return Some(job);
+        }
+        if self.policy == OverflowPolicy::Coalesce && kind == JobKind::Continuous {
+            let queued = state
+                .jobs
+                .iter_mut()
+                .find(|q| q.kind == JobKind::Continuous && q.key == key);
+            if let Some(queued) = queued {
+                queued.job = job;
+                state.metrics.coalesced += 1;
+                return None;
+            }
+        }
+        // With only control jobs queued the queue briefly runs over capacity
+        if let Some(index) = state.jobs.iter().position(Queued::droppable) {
+            state.jobs.remove(index);
+            state.metrics.dropped += 1;
+        }
+        Some(job)
+    }
+
+    fn enqueue(state: &mut QueueState<T>, key: String, job: T, kind: JobKind) {
+        state.jobs.push_back(Queued {
+            key,
+            job,
+            queued_at: Instant::now(),
+            kind,
+        });
+        state.metrics.enqueued += 1;
+        state.metrics.depth = state.jobs.len();
+        state.metrics.max_depth = state.metrics.max_depth.max(state.jobs.len());
+    }
+
+    /// Next job that may start, waiting for one: the oldest whose key has
+    /// nothing running, while fewer than `workers` jobs run. Hand the key
+    /// back to [`done`](Self::done) when the job finishes. `None` once
+    /// closed and drained.
+    pub async fn pop(&self) -> Option<(String, T)> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(index) = self.next_ready(&state) {
+                    let queued = state.jobs.remove(index).expect("index is in range");
+                    let latency_ms = queued.queued_at.elapsed().as_secs_f64() * 1000.0;
+                    state.total_latency_ms += latency_ms;
+                    let total_latency_ms = state.total_latency_ms;
+                    let metrics = &mut state.metrics;
+                    metrics.started += 1;
+                    metrics.depth -= 1;
+                    metrics.max_latency_ms = metrics.max_latency_ms.max(latency_ms);
+                    metrics.avg_latency_ms = total_latency_ms / metrics.started as f64;
+                    state.running.insert(queued.key.clone());
+                    state.barrier_running = queued.kind == JobKind::Barrier;
+                    self.space_ready.notify_one();
+                    return Some((queued.key, queued.job));
+                }
+                if state.closed && state.jobs.is_empty() {
+                    return None;
+                }
+            }
+            self.job_ready.notified().await;
+        }
+    }
+
+    fn next_ready(&self, state: &QueueState<T>) -> Option<usize> {
+        if state.barrier_running || state.running.len() >= self.workers {
+            return None;
+        }
+        for (index, queued) in state.jobs.iter().enumerate() {
+            if queued.kind == JobKind::Barrier {
+                return (index == 0 && state.running.is_empty()).then_some(index);
+            }
+            if !state.running.contains(&queued.key) {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    /// Mark the job popped with `key` finished, so the next one of its key
+    /// (or a waiting barrier) can start
+    pub fn done(&self, key: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.running.remove(key);
+        state.barrier_running = false;
+        self.job_ready.notify_one();
+    }
+
+    /// Stop accepting jobs; queued jobs are still handed out
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.job_ready.notify_one();
+        self.space_ready.notify_one();
+    }
+
+    pub fn metrics(&self) -> QueueMetrics {
+        self.state.lock().unwrap().metrics
+    }
+}
+
+impl QueueMetrics {
+    /// One-line summary for logs
+    pub fn summary(&self) -> String {
+        format!(
+            "depth {} (max {}), {} queued, {} dropped, {} coalesced, latency avg {:.2} ms / max {:.2} ms",
+            self.depth,
+            self.max_depth,
+            self.enqueued,
+            self.dropped,
+            self.coalesced,
+            self.avg_latency_ms,
+            self.max_latency_ms
+        )
+    }
+}
+
+/// How long a blocked push may take before it is worth a warning
+pub const BLOCK_WARN_AFTER: Duration = Duration::from_millis(50);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures_util::FutureExt;
+
+    async fn drain(queue: &JobQueue<u32>) -> Vec<u32> {
+        queue.close();
+        let mut jobs = Vec::new();
+        while let Some((key, job)) = queue.pop().await {
+            queue.done(&key);
+            jobs.push(job);
+        }
+        jobs
+    }
+
+    /// Job that may start right now, if any
+    fn try_pop(queue: &JobQueue<u32>) -> Option<u32> {
+        queue.pop().now_or_never().flatten().map(|(_, job)| job)
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let queue = JobQueue::new(2, OverflowPolicy::DropOldest, 1);
+        for (i, key) in ["a", "b", "a"].iter().enumerate() {
+            queue.push(key.to_string(), i as u32).await;
+        }
+        assert_eq!(queue.metrics().dropped, 1);
+        assert_eq!(drain(&queue).await, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_keeps_position() {
+        let queue = JobQueue::new(3, OverflowPolicy::Coalesce, 1);
+        queue.push_continuous("move".to_string(), 1).await;
+        queue.push("press".to_string(), 2).await;
+        queue.push("other".to_string(), 3).await;
+        queue.push_continuous("move".to_string(), 4).await;
+
+        assert_eq!(queue.metrics().coalesced, 1);
+        assert_eq!(drain(&queue).await, vec![4, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_only_replaces_continuous_jobs() {
+        let queue = JobQueue::new(2, OverflowPolicy::Coalesce, 1);
+        queue.push("press".to_string(), 1).await;
+        queue.push_continuous("move".to_string(), 2).await;
+        // Presses are never merged: the oldest job goes instead
+        queue.push("press".to_string(), 3).await;
+
+        let metrics = queue.metrics();
+        assert_eq!((metrics.coalesced, metrics.dropped), (0, 1));
+        assert_eq!(drain(&queue).await, vec![2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_keys_run_side_by_side_in_order() {
+        let queue = JobQueue::new(8, OverflowPolicy::Coalesce, 2);
+        queue.push("a".to_string(), 1).await;
+        queue.push("a".to_string(), 2).await;
+        queue.push("b".to_string(), 3).await;
+        queue.push("c".to_string(), 4).await;
+
+        // `a` is busy, so `b` overtakes its second job; then the cap is reached
+        assert_eq!(try_pop(&queue), Some(1));
+        assert_eq!(try_pop(&queue), Some(3));
+        assert_eq!(try_pop(&queue), None);
+
+        queue.done("a");
+        assert_eq!(try_pop(&queue), Some(2));
+        queue.done("b");
+        assert_eq!(try_pop(&queue), Some(4));
+    }
+
+    #[tokio::test]
+    async fn test_barrier_runs_alone() {
+        let queue = JobQueue::new(8, OverflowPolicy::Coalesce, 4);
+        queue.push("a".to_string(), 1).await;
+        queue.push_barrier("config".to_string(), 2);
+        queue.push("b".to_string(), 3).await;
+
+        assert_eq!(try_pop(&queue), Some(1));
+        assert_eq!(try_pop(&queue), None);
+        queue.done("a");
+        assert_eq!(try_pop(&queue), Some(2));
+        assert_eq!(try_pop(&queue), None);
+        queue.done("config");
+        assert_eq!(try_pop(&queue), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_block_waits_for_space() {
+        let queue = std::sync::Arc::new(JobQueue::new(1, OverflowPolicy::Block, 1));
+        queue.push("a".to_string(), 1).await;
+
+        let producer = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.push("a".to_string(), 2).await })
+        };
+        tokio::task::yield_now().await;
+        assert_eq!(queue.metrics().depth, 1);
+
+        assert_eq!(queue.pop().await, Some(("a".to_string(), 1)));
+        queue.done("a");
+        assert!(producer.await.unwrap());
+        assert_eq!(queue.pop().await, Some(("a".to_string(), 2)));
+        assert_eq!(queue.metrics().max_depth, 1);
+    }
+
+    #[tokio::test]
+    async fn test_control_jobs_are_never_dropped() {
+        let queue = JobQueue::new(2, OverflowPolicy::DropOldest, 1);
+        queue.push_unbounded("config".to_string(), 1);
+        queue.push("a".to_string(), 2).await;
+        queue.push_unbounded("config".to_string(), 3);
+        queue.push("a".to_string(), 4).await;
+        assert_eq!(queue.metrics().dropped, 1);
+        assert_eq!(drain(&queue).await, vec![1, 3, 4]);
+    }
+}
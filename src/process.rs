@@ -0,0 +1,462 @@
+//! Supervised child processes for shell actions
+//!
+//! Each command runs in its own process group with an optional timeout, so
+//! a kill takes down everything it started. Commands can be limited to a
+//! number of concurrent runs, with a policy for when the limit is hit, and
+//! whatever is still running is killed on shutdown.
+
+use std::collections::{BTreeMap, HashMap};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+use tokio::sync::{Notify, Semaphore};
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+/// How long output may stay open after the command exits, e.g. held by a
+/// background process it started, before the group is killed
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+/// What to do when a command already runs `max_concurrent` times
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitPolicy {
+    /// Don't start the new run
+    #[default]
+    Skip,
+    /// Start the new run once a running one exits
+    Queue,
+    /// Kill the oldest run and start the new one
+    Replace,
+}
+
+/// A command ready to launch
+#[derive(Debug, Clone, Default)]
+pub struct ProcessSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub working_dir: Option<String>,
+    pub env: Vec<(String, String)>,
+    /// Kill the command after this long
+    pub timeout: Option<Duration>,
+    /// Pipe stdout/stderr instead of inheriting them
+    pub capture: bool,
+}
+
+/// How a command ended
+#[derive(Debug, Clone, Default)]
+pub struct ProcessOutput {
+    /// Exit code (`None` if it did not start or was killed by a signal)
+    pub code: Option<i32>,
+    pub success: bool,
+    pub timed_out: bool,
+    /// Killed by `replace` or shutdown
+    pub killed: bool,
+    /// Captured output (empty unless `capture` is set)
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// A started run, with its kill switch
+struct Run {
+    key: String,
+    kill: Arc<Notify>,
+    /// Already told to stop by `replace`, so not picked again
+    killed: bool,
+}
+
+#[derive(Default)]
+struct SupervisorState {
+    /// Concurrency slots per command key, with the limit they were made for
+    slots: HashMap<String, (usize, Arc<Semaphore>)>,
+    /// Kill switches of started runs by id (ids grow, so oldest first)
+    running: BTreeMap<u64, Run>,
+    tasks: Vec<JoinHandle<()>>,
+    next_id: u64,
+    closed: bool,
+}
+
+impl SupervisorState {
+    fn slots_for(&mut self, key: &str, max: usize) -> Arc<Semaphore> {
+        let max = max.max(1);
+        match self.slots.get(key) {
+            Some((limit, slots)) if *limit == max => slots.clone(),
+            // New command, or the limit changed on reload
+            _ => {
+                let slots = Arc::new(Semaphore::new(max));
+                self.slots.insert(key.to_string(), (max, slots.clone()));
+                slots
+            }
+        }
+    }
+
+    fn kill_oldest(&mut self, key: &str) {
+        let oldest = self
+            .running
+            .values_mut()
+            .find(|run| run.key == key && !run.killed);
+        if let Some(run) = oldest {
+            run.killed = true;
+            run.kill.notify_one();
+        }
+    }
+}
+
+/// Launches commands and tracks them until they exit
+#[derive(Clone, Default)]
+pub struct ProcessSupervisor {
+    state: Arc<Mutex<SupervisorState>>,
+}
+
+impl ProcessSupervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Launch `spec` under `key` (the action it came from), at most
+    /// `limit.0` at once. `on_exit` receives the result. Returns false if
+    /// the run was skipped.
+    pub fn spawn(
+        &self,
+        key: &str,
+        spec: ProcessSpec,
+        limit: Option<(usize, LimitPolicy)>,
+        on_exit: impl FnOnce(ProcessOutput) + Send + 'static,
+    ) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return false;
+        }
+        state.tasks.retain(|task| !task.is_finished());
+
+        let mut permit = None;
+        let mut slots = None;
+        if let Some((max, policy)) = limit {
+            let limited = state.slots_for(key, max);
+            match limited.clone().try_acquire_owned() {
+                Ok(acquired) => permit = Some(acquired),
+                Err(_) => match policy {
+                    LimitPolicy::Skip => return false,
+                    LimitPolicy::Queue => {}
+                    LimitPolicy::Replace => state.kill_oldest(key),
+                },
+            }
+            slots = Some(limited);
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        let kill = Arc::new(Notify::new());
+        state.running.insert(
+            id,
+            Run {
+                key: key.to_string(),
+                kill: kill.clone(),
+                killed: false,
+            },
+        );
+
+        let supervisor = self.clone();
+        let task = tokio::spawn(async move {
+            // Wait for a slot unless one was free (`queue` and `replace`)
+            let permit = match (permit, slots) {
+                (Some(permit), _) => Some(permit),
+                (None, Some(slots)) => tokio::select! {
+                    acquired = slots.acquire_owned() => acquired.ok(),
+                    _ = kill.notified() => None,
+                },
+                (None, None) => None,
+            };
+            if limit.is_some() && permit.is_none() {
+                supervisor.finish(id);
+                return;
+            }
+
+            let output = run(spec, &kill).await;
+            supervisor.finish(id);
+            on_exit(output);
+        });
+        state.tasks.push(task);
+        true
+    }
+
+    fn finish(&self, id: u64) {
+        self.state.lock().unwrap().running.remove(&id);
+    }
+
+    /// Runs started or waiting for a slot
+    pub fn running(&self) -> usize {
+        self.state.lock().unwrap().running.len()
+    }
+
+    /// Kill every run still going and wait for them to exit
+    pub async fn shutdown(&self) {
+        let tasks = {
+            let mut state = self.state.lock().unwrap();
+            state.closed = true;
+            for run in state.running.values() {
+                run.kill.notify_one();
+            }
+            for (_, slots) in state.slots.values() {
+                slots.close();
+            }
+            std::mem::take(&mut state.tasks)
+        };
+        if !tasks.is_empty() {
+            debug!("Stopping {} command(s)", tasks.len());
+        }
+        for task in tasks {
+            task.await.ok();
+        }
+    }
+}
+
+/// Run one command to completion, timeout or kill
+async fn run(spec: ProcessSpec, kill: &Notify) -> ProcessOutput {
+    let mut command = Command::new(&spec.program);
+    command.args(&spec.args).kill_on_drop(true);
+    if let Some(dir) = &spec.working_dir {
+        command.current_dir(dir);
+    }
+    for (key, value) in &spec.env {
+        command.env(key, value);
+    }
+    if spec.capture {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    // Lead a new process group, so killing it takes down grandchildren too
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let mut output = ProcessOutput::default();
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            error!("Failed to spawn command `{}`: {}", spec.program, e);
+            return output;
+        }
+    };
+    // Reaped children have no id, so keep it for killing the group later
+    let pid = child.id();
+    let mut stdout = child.stdout.take().map(read_all);
+    let mut stderr = child.stderr.take().map(read_all);
+
+    let timeout = async {
+        match spec.timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    let status = tokio::select! {
+        status = child.wait() => status.ok(),
+        _ = timeout => {
+            output.timed_out = true;
+            None
+        }
+        _ = kill.notified() => {
+            output.killed = true;
+            None
+        }
+    };
+    let status = match status {
+        Some(status) => Some(status),
+        None => {
+            kill_group(pid, &mut child);
+            child.wait().await.ok()
+        }
+    };
+
+    if let Some(status) = status {
+        output.code = status.code();
+        output.success = status.success();
+    }
+    {
+        let mut reads = std::pin::pin!(collect_output(&mut stdout, &mut stderr));
+        let collected = match tokio::time::timeout(OUTPUT_GRACE, &mut reads).await {
+            Ok(collected) => Some(collected),
+            Err(_) => {
+                debug!(
+                    "Output of `{}` still open after exit, killing its group",
+                    spec.program
+                );
+                kill_group(pid, &mut child);
+                tokio::time::timeout(OUTPUT_GRACE, reads).await.ok()
+            }
+        };
+        if let Some((out, err)) = collected {
+            output.stdout = out;
+            output.stderr = err;
+        }
+    }
+    // Left running only if something outside the group still holds a pipe
+    for task in stdout.iter().chain(&stderr) {
+        task.abort();
+    }
+    output
+}
+
+async fn collect_output(
+    stdout: &mut Option<JoinHandle<String>>,
+    stderr: &mut Option<JoinHandle<String>>,
+) -> (String, String) {
+    let mut out = String::new();
+    if let Some(task) = stdout {
+        out = task.await.unwrap_or_default();
+    }
+    let mut err = String::new();
+    if let Some(task) = stderr {
+        err = task.await.unwrap_or_default();
+    }
+    (out, err)
+}
+
+fn read_all(mut pipe: impl AsyncRead + Unpin + Send + 'static) -> JoinHandle<String> {
+    tokio::spawn(async move {
+        let mut buf = Vec::new();
+        pipe.read_to_end(&mut buf).await.ok();
+        String::from_utf8_lossy(&buf).into_owned()
+    })
+}
+
+/// Kill the command and everything in its process group; `pid` is the
+/// group leader's, taken at spawn
+fn kill_group(pid: Option<u32>, child: &mut Child) {
+    #[cfg(not(unix))]
+    let _ = pid;
+    #[cfg(unix)]
+    if let Some(pid) = pid {
+        // SAFETY: plain syscall; the child leads its own group (`process_group(0)`)
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+        }
+    }
+    child.start_kill().ok();
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+
+    fn sh(script: &str) -> ProcessSpec {
+        ProcessSpec {
+            program: "/bin/sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            capture: true,
+            ..Default::default()
+        }
+    }
+
+    fn spawn(
+        supervisor: &ProcessSupervisor,
+        spec: ProcessSpec,
+        limit: Option<(usize, LimitPolicy)>,
+    ) -> Option<oneshot::Receiver<ProcessOutput>> {
+        let (tx, rx) = oneshot::channel();
+        let started = supervisor.spawn("cmd", spec, limit, move |output| {
+            tx.send(output).ok();
+        });
+        started.then_some(rx)
+    }
+
+    #[tokio::test]
+    async fn test_capture_output() {
+        let supervisor = ProcessSupervisor::new();
+        let rx = spawn(&supervisor, sh("echo out; echo err >&2; exit 3"), None).unwrap();
+        let output = rx.await.unwrap();
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+        assert_eq!(output.code, Some(3));
+        assert!(!output.success);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_kills_group() {
+        let supervisor = ProcessSupervisor::new();
+        let spec = ProcessSpec {
+            timeout: Some(Duration::from_millis(100)),
+            // The background sleep holds stdout open until the group dies
+            ..sh("sleep 5 & sleep 5")
+        };
+        let rx = spawn(&supervisor, spec, None).unwrap();
+        let output = tokio::time::timeout(Duration::from_secs(2), rx)
+            .await
+            .expect("group killed")
+            .unwrap();
+        assert!(output.timed_out);
+        assert!(!output.success);
+    }
+
+    #[tokio::test]
+    async fn test_background_output_does_not_hang() {
+        let supervisor = ProcessSupervisor::new();
+        // The command exits at once, but its background sleep keeps stdout open
+        let rx = spawn(&supervisor, sh("echo out; sleep 5 &"), None).unwrap();
+        let output = tokio::time::timeout(Duration::from_secs(3), rx)
+            .await
+            .expect("group killed after exit")
+            .unwrap();
+        assert!(output.success);
+        assert_eq!(output.stdout, "out\n");
+    }
+
+    #[tokio::test]
+    async fn test_limit_skip_and_replace() {
+        let supervisor = ProcessSupervisor::new();
+        let first = spawn(&supervisor, sh("sleep 5"), Some((1, LimitPolicy::Skip))).unwrap();
+        assert!(spawn(&supervisor, sh("true"), Some((1, LimitPolicy::Skip))).is_none());
+
+        let second = spawn(&supervisor, sh("echo new"), Some((1, LimitPolicy::Replace))).unwrap();
+        assert!(first.await.unwrap().killed);
+        assert_eq!(second.await.unwrap().stdout, "new\n");
+    }
+
+    #[tokio::test]
+    async fn test_replace_in_quick_succession() {
+        let supervisor = ProcessSupervisor::new();
+        let replace = Some((1, LimitPolicy::Replace));
+        let first = spawn(&supervisor, sh("sleep 5"), replace).unwrap();
+        // Before the first has exited, so it must not be picked twice
+        let second = spawn(&supervisor, sh("sleep 5"), replace).unwrap();
+        let third = spawn(&supervisor, sh("echo new"), replace).unwrap();
+        let output = tokio::time::timeout(Duration::from_secs(2), third)
+            .await
+            .expect("both older runs killed")
+            .unwrap();
+        assert_eq!(output.stdout, "new\n");
+        assert!(first.await.unwrap().killed);
+        // Killed, or dropped while still waiting for a slot
+        assert!(second.await.map_or(true, |output| output.killed));
+    }
+
+    #[tokio::test]
+    async fn test_limit_queue_runs_in_turn() {
+        let supervisor = ProcessSupervisor::new();
+        let first = spawn(
+            &supervisor,
+            sh("sleep 0.2; echo 1"),
+            Some((1, LimitPolicy::Queue)),
+        )
+        .unwrap();
+        let second = spawn(&supervisor, sh("echo 2"), Some((1, LimitPolicy::Queue))).unwrap();
+        assert_eq!(supervisor.running(), 2);
+        assert_eq!(first.await.unwrap().stdout, "1\n");
+        assert_eq!(second.await.unwrap().stdout, "2\n");
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_kills_running() {
+        let supervisor = ProcessSupervisor::new();
+        let rx = spawn(&supervisor, sh("sleep 5"), None).unwrap();
+        tokio::time::timeout(Duration::from_secs(2), supervisor.shutdown())
+            .await
+            .expect("shutdown is prompt");
+        assert!(rx.await.unwrap().killed);
+        assert!(spawn(&supervisor, sh("true"), None).is_none());
+    }
+}
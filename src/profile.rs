@@ -16,6 +16,7 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
 use crate::dualsense::{MuteLedState, OutputState, PlayerLeds, TriggerEffect, TriggerEffectMode};
+use crate::format::FileFormat;
 
 /// Profile directory environment variable
 pub const PROFILE_DIR_ENV: &str = "DUALSENSE_HOME";
@@ -248,19 +249,22 @@ impl Profile {
         }
     }
 
-    /// Load a profile from a JSON file
+    /// Load a profile from a JSON, TOML or YAML file
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
+        let format = FileFormat::from_path(path)?;
         let content = fs::read_to_string(path)
             .with_context(|| format!("Failed to read profile: {}", path.display()))?;
-        let profile: Profile = serde_json::from_str(&content)
+        let profile: Profile = format
+            .parse(&content)
             .with_context(|| format!("Failed to parse profile: {}", path.display()))?;
         Ok(profile)
     }
 
-    /// Save the profile to a JSON file
+    /// Save the profile, picking the format from the extension
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let content = serde_json::to_string_pretty(self)?;
+        let path = path.as_ref();
+        let content = FileFormat::from_path(path)?.to_string(self)?;
         fs::write(path, content)?;
         Ok(())
     }
@@ -411,7 +415,7 @@ impl ProfileManager {
             let entry = entry?;
             let path = entry.path();
 
-            if path.extension().map(|e| e == "json").unwrap_or(false) {
+            if FileFormat::is_supported(&path) {
                 if let Ok(profile) = Profile::load(&path) {
                     let file_name = path
                         .file_stem()
@@ -462,10 +466,14 @@ impl ProfileManager {
         self.profile_path(name).exists()
     }
 
-    /// Get the path for a profile
+    /// Get the path for a profile (an existing file in any format, else JSON)
     fn profile_path(&self, name: &str) -> PathBuf {
         let id = Self::name_to_id(name);
-        self.profiles_dir.join(format!("{}.json", id))
+        FileFormat::ALL
+            .iter()
+            .map(|format| self.profiles_dir.join(format!("{}.{}", id, format.extension())))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| self.profiles_dir.join(format!("{}.json", id)))
     }
 
     /// Convert profile name to file ID (lowercase, no spaces)
@@ -0,0 +1,124 @@
+//! Auto-repeat for held inputs
+//!
+//! Keyboard-style key repeat: after an initial delay an action repeats at
+//! an interval that can shrink with each repeat, down to a floor. Timing
+//! runs on the executor's clock, not on when HID reports arrive.
+
+use crate::config::RepeatConfig;
+
+/// Auto-repeat tuning
+#[derive(Debug, Clone, Copy)]
+pub struct RepeatSettings {
+    /// Delay before the first repeat (ms)
+    pub delay_ms: u64,
+    /// Interval between repeats (ms)
+    pub interval_ms: u64,
+    /// Factor applied to the interval after each repeat (1.0 = constant)
+    pub acceleration: f32,
+    /// Shortest interval acceleration can reach (ms)
+    pub min_interval_ms: u64,
+    /// Stop after this many repeats
+    pub max_count: Option<u32>,
+}
+
+impl Default for RepeatSettings {
+    fn default() -> Self {
+        RepeatConfig::default().repeat_settings()
+    }
+}
+
+/// Repeat timer of one held input
+#[derive(Debug, Clone)]
+pub struct AutoRepeat {
+    settings: RepeatSettings,
+    next_ms: u64,
+    interval_ms: f32,
+    count: u32,
+}
+
+impl AutoRepeat {
+    /// Start repeating for an input pressed at `now_ms`
+    pub fn start(settings: RepeatSettings, now_ms: u64) -> Self {
+        Self {
+            settings,
+            next_ms: now_ms + settings.delay_ms,
+            interval_ms: settings.interval_ms as f32,
+            count: 0,
+        }
+    }
+
+    /// Repeats fired so far
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Whether a repeat is due at `now_ms`. A late tick fires once and
+    /// schedules from now instead of bursting to catch up.
+    pub fn poll(&mut self, now_ms: u64) -> bool {
+        if now_ms < self.next_ms || self.is_done() {
+            return false;
+        }
+
+        self.count += 1;
+        if self.count > 1 {
+            let min = self.settings.min_interval_ms as f32;
+            self.interval_ms = (self.interval_ms * self.settings.acceleration).max(min);
+        }
+        self.next_ms = (self.next_ms + self.interval_ms as u64).max(now_ms + 1);
+        true
+    }
+
+    /// Reached `max_count`
+    pub fn is_done(&self) -> bool {
+        self.settings.max_count.is_some_and(|max| self.count >= max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fire_times(repeat: &mut AutoRepeat, until_ms: u64) -> Vec<u64> {
+        (0..=until_ms).filter(|&t| repeat.poll(t)).collect()
+    }
+
+    #[test]
+    fn test_delay_then_interval() {
+        let mut repeat = AutoRepeat::start(
+            RepeatSettings {
+                delay_ms: 300,
+                interval_ms: 100,
+                ..Default::default()
+            },
+            0,
+        );
+        assert_eq!(fire_times(&mut repeat, 650), vec![300, 400, 500, 600]);
+    }
+
+    #[test]
+    fn test_acceleration_and_max_count() {
+        let mut repeat = AutoRepeat::start(
+            RepeatSettings {
+                delay_ms: 100,
+                interval_ms: 100,
+                acceleration: 0.5,
+                min_interval_ms: 30,
+                max_count: Some(5),
+            },
+            1000,
+        );
+        assert_eq!(
+            fire_times(&mut repeat, 2000),
+            vec![1100, 1200, 1250, 1280, 1310]
+        );
+        assert!(repeat.is_done());
+    }
+
+    #[test]
+    fn test_late_tick_does_not_burst() {
+        let mut repeat = AutoRepeat::start(RepeatSettings::default(), 0);
+        assert!(repeat.poll(1000));
+        assert!(!repeat.poll(1000));
+        assert!(repeat.poll(1100));
+    }
+}
@@ -0,0 +1,898 @@
+//! Config schema and validation
+//!
+//! Exports a JSON Schema for [`Config`] (for editor autocompletion) and
+//! validates config documents against it, collecting every error with its
+//! JSON path instead of stopping at the first one like serde does.
+//! Template strings are compiled and checked for unknown context fields.
+
+use std::fmt;
+
+use handlebars::template::{HelperTemplate, Parameter, Template, TemplateElement};
+use serde_json::{Map, Value};
+
+use crate::config::{Config, TemplateContext};
+use crate::dualsense::ControllerState;
+use crate::helpers;
+
+/// Helpers built into handlebars (custom ones are in `helpers::HELPERS`)
+pub const BUILTIN_HELPERS: &[&str] = &[
+    "if", "unless", "each", "with", "lookup", "raw", "log", "eq", "ne", "gt", "gte", "lt", "lte",
+    "and", "or", "not", "len",
+];
+
+/// Context fields whose keys are user-defined, so nested paths are not checked
+const OPEN_FIELDS: &[&str] = &["vars"];
+
+/// Block helpers that change the context, so paths inside are not checked
+const SCOPE_HELPERS: &[&str] = &["each", "with"];
+
+/// Config keys holding template strings (`path`/`base_url` only under `http`,
+/// `value` only in `vars` operations and OSC `args`, `address` only in OSC
+/// messages, `topic`/`payload`/`state_topic` only under `mqtt`, plus every
+/// value under `query`)
+const TEMPLATE_KEYS: &[&str] = &["command", "message", "body", "state_format"];
+const HTTP_TEMPLATE_KEYS: &[&str] = &["path", "base_url"];
+const MQTT_TEMPLATE_KEYS: &[&str] = &["topic", "payload", "state_topic"];
+
+/// A single validation problem
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// JSON path of the offending value, e.g. `$.buttons.cross.trigger`
+    pub path: String,
+    /// Dotted object key path (as used for key origins)
+    pub key: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    /// Error at a JSON path such as `$.touchpad.regions[0].action`
+    pub fn at(path: impl Into<String>, message: impl Into<String>) -> Self {
+        let path = path.into();
+        // Keys inside arrays have no origin of their own
+        let key = path
+            .trim_start_matches("$.")
+            .split('[')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        Self {
+            path,
+            key,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// JSON Schema for the config file format
+pub fn config_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(Config)).expect("schema serializes")
+}
+
+/// Validate a (resolved) config document, returning every problem found
+pub fn validate_document(document: &Value) -> Vec<ValidationError> {
+    let mut errors = check_document(document);
+    check_templates(document, &mut Location::default(), &mut errors);
+    errors
+}
+
+/// Problems that keep a document from loading: values outside the schema
+/// (ranges included), then anything serde or `Config::problems` rejects
+pub fn check_document(document: &Value) -> Vec<ValidationError> {
+    let schema = config_schema();
+    let mut errors = Vec::new();
+    let mut location = Location::default();
+    check_schema(&schema, &schema, document, &mut location, &mut errors);
+
+    // Anything the schema subset misses still fails in serde
+    if errors.is_empty() {
+        match serde_json::from_value::<Config>(document.clone()) {
+            Ok(config) => errors.extend(config.problems()),
+            Err(e) => errors.push(ValidationError {
+                path: "$".to_string(),
+                key: String::new(),
+                message: e.to_string(),
+            }),
+        }
+    }
+    errors
+}
+
+/// Current position while walking a document
+#[derive(Debug, Default, Clone)]
+struct Location {
+    path: String,
+    keys: Vec<String>,
+    in_array: bool,
+}
+
+impl Location {
+    fn push_key(&mut self, key: &str) -> Location {
+        let saved = self.clone();
+        self.path.push('.');
+        self.path.push_str(key);
+        if !self.in_array {
+            self.keys.push(key.to_string());
+        }
+        saved
+    }
+
+    fn push_index(&mut self, index: usize) -> Location {
+        let saved = self.clone();
+        self.path.push_str(&format!("[{}]", index));
+        self.in_array = true;
+        saved
+    }
+
+    fn json_path(&self) -> String {
+        format!("${}", self.path)
+    }
+
+    fn error(&self, message: String) -> ValidationError {
+        ValidationError {
+            path: self.json_path(),
+            key: self.keys.join("."),
+            message,
+        }
+    }
+}
+
+fn resolve_ref<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema.get("$ref").and_then(Value::as_str) {
+        Some(reference) => reference
+            .strip_prefix("#/definitions/")
+            .and_then(|name| root.get("definitions")?.get(name))
+            .map(|target| resolve_ref(root, target))
+            .unwrap_or(schema),
+        None => schema,
+    }
+}
+
+/// Schema extension marking a string `enum` as case-insensitive
+pub const IGNORE_CASE_KEY: &str = "x-ignore-case";
+
+/// Schema of a string enum whose `names` match in any case
+pub fn ignore_case_enum(description: &str, names: &[&str]) -> schemars::schema::Schema {
+    serde_json::from_value(serde_json::json!({
+        "description": description,
+        "type": "string",
+        "enum": names,
+        (IGNORE_CASE_KEY): true
+    }))
+    .expect("valid schema")
+}
+
+fn contains_ignore_case(allowed: &[Value], value: &Value) -> bool {
+    let Some(value) = value.as_str() else {
+        return false;
+    };
+    allowed
+        .iter()
+        .filter_map(Value::as_str)
+        .any(|name| name.eq_ignore_ascii_case(value))
+}
+
+/// Validate `value` against the subset of JSON Schema that schemars emits
+fn check_schema(
+    root: &Value,
+    schema: &Value,
+    value: &Value,
+    at: &mut Location,
+    errors: &mut Vec<ValidationError>,
+) {
+    let schema = resolve_ref(root, schema);
+
+    if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        for branch in all {
+            check_schema(root, branch, value, at, errors);
+        }
+    }
+
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(branches) = schema.get(keyword).and_then(Value::as_array) {
+            check_alternatives(root, branches, value, at, errors);
+        }
+    }
+
+    if let Some(expected) = schema.get("type") {
+        if !type_matches(expected, value) {
+            errors.push(at.error(format!(
+                "expected {}, got {}",
+                type_names(expected),
+                json_type(value)
+            )));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        let ignore_case = schema.get(IGNORE_CASE_KEY) == Some(&Value::Bool(true));
+        if !(allowed.contains(value) || ignore_case && contains_ignore_case(allowed, value)) {
+            errors.push(at.error(format!(
+                "unknown value {}, expected one of {}",
+                value,
+                join_values(allowed)
+            )));
+        }
+    }
+
+    if let Some(number) = value.as_f64() {
+        check_range(schema, number, at, errors);
+    }
+
+    match value {
+        Value::Object(map) => check_object(root, schema, map, at, errors),
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    let saved = at.push_index(i);
+                    check_schema(root, item_schema, item, at, errors);
+                    *at = saved;
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_alternatives(
+    root: &Value,
+    branches: &[Value],
+    value: &Value,
+    at: &mut Location,
+    errors: &mut Vec<ValidationError>,
+) {
+    let mut best: Option<Vec<ValidationError>> = None;
+    let mut enum_values = Vec::new();
+
+    for branch in branches {
+        let branch = resolve_ref(root, branch);
+        if let Some(values) = branch.get("enum").and_then(Value::as_array) {
+            enum_values.extend(values.iter().cloned());
+        }
+        // A `null` branch only matters for null values
+        if !value.is_null() && branch.get("type") == Some(&Value::from("null")) {
+            continue;
+        }
+
+        let mut branch_errors = Vec::new();
+        check_schema(root, branch, value, &mut at.clone(), &mut branch_errors);
+        if branch_errors.is_empty() {
+            return;
+        }
+        if best.as_ref().is_none_or(|b| branch_errors.len() < b.len()) {
+            best = Some(branch_errors);
+        }
+    }
+
+    if !enum_values.is_empty() && enum_values.len() == branches.len() {
+        errors.push(at.error(format!(
+            "unknown value {}, expected one of {}",
+            value,
+            join_values(&enum_values)
+        )));
+    } else if let Some(best) = best {
+        errors.extend(best);
+    }
+}
+
+fn check_object(
+    root: &Value,
+    schema: &Value,
+    map: &Map<String, Value>,
+    at: &mut Location,
+    errors: &mut Vec<ValidationError>,
+) {
+    let properties = schema.get("properties").and_then(Value::as_object);
+
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !map.contains_key(name) {
+                errors.push(at.error(format!("missing required field `{}`", name)));
+            }
+        }
+    }
+
+    for (key, child) in map {
+        let saved = at.push_key(key);
+        match properties.and_then(|p| p.get(key)) {
+            Some(child_schema) => check_schema(root, child_schema, child, at, errors),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    let known: Vec<&str> = properties
+                        .map(|p| p.keys().map(String::as_str).collect())
+                        .unwrap_or_default();
+                    let mut message = format!("unknown field `{}`", key);
+                    if let Some(suggestion) = closest(key, &known) {
+                        message.push_str(&format!(", did you mean `{}`?", suggestion));
+                    }
+                    errors.push(at.error(message));
+                }
+                Some(extra) if extra.is_object() => check_schema(root, extra, child, at, errors),
+                _ => {}
+            },
+        }
+        *at = saved;
+    }
+}
+
+fn check_range(schema: &Value, number: f64, at: &Location, errors: &mut Vec<ValidationError>) {
+    let (mut min, mut max) = match schema.get("format").and_then(Value::as_str) {
+        Some("uint8") => (Some(0.0), Some(u8::MAX as f64)),
+        Some("uint16") => (Some(0.0), Some(u16::MAX as f64)),
+        Some("uint32") => (Some(0.0), Some(u32::MAX as f64)),
+        Some("uint64") | Some("uint") => (Some(0.0), None),
+        Some("int8") => (Some(i8::MIN as f64), Some(i8::MAX as f64)),
+        Some("int16") => (Some(i16::MIN as f64), Some(i16::MAX as f64)),
+        Some("int32") => (Some(i32::MIN as f64), Some(i32::MAX as f64)),
+        _ => (None, None),
+    };
+    if let Some(m) = schema.get("minimum").and_then(Value::as_f64) {
+        min = Some(min.map_or(m, |x: f64| x.max(m)));
+    }
+    if let Some(m) = schema.get("maximum").and_then(Value::as_f64) {
+        max = Some(max.map_or(m, |x: f64| x.min(m)));
+    }
+
+    if let Some(min) = min.filter(|&min| number < min) {
+        errors.push(at.error(format!("{} is below the minimum {}", number, min)));
+    }
+    if let Some(max) = max.filter(|&max| number > max) {
+        errors.push(at.error(format!("{} is above the maximum {}", number, max)));
+    }
+}
+
+fn type_matches(expected: &Value, value: &Value) -> bool {
+    match expected {
+        Value::String(name) => match name.as_str() {
+            "null" => value.is_null(),
+            "boolean" => value.is_boolean(),
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            _ => true,
+        },
+        Value::Array(names) => names.iter().any(|name| type_matches(name, value)),
+        _ => true,
+    }
+}
+
+fn type_names(expected: &Value) -> String {
+    match expected {
+        Value::Array(names) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" or "),
+        other => other.as_str().unwrap_or("value").to_string(),
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn join_values(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Closest known name within a small edit distance
+fn closest<'a>(name: &str, known: &[&'a str]) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|k| (edit_distance(name, k), *k))
+        .filter(|(d, _)| *d <= 2)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, k)| k)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { prev } else { prev + 1 };
+            prev = row[j + 1];
+            row[j + 1] = cost.min(row[j] + 1).min(prev + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Compile every template string and check the fields it references
+fn check_templates(value: &Value, at: &mut Location, errors: &mut Vec<ValidationError>) {
+    visit_templates(value, None, at, &mut |at, source| {
+        errors.extend(check_template(source).into_iter().map(|m| at.error(m)));
+    });
+}
+
+/// Every template string in a config document, with its JSON path
+pub fn template_sources(document: &Value) -> Vec<(String, String)> {
+    let mut sources = Vec::new();
+    visit_templates(document, None, &mut Location::default(), &mut |at, source| {
+        sources.push((at.json_path(), source.to_string()));
+    });
+    sources
+}
+
+/// Call `f` for each template string; `parent` is the enclosing object key
+fn visit_templates(
+    value: &Value,
+    parent: Option<&str>,
+    at: &mut Location,
+    f: &mut dyn FnMut(&Location, &str),
+) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let saved = at.push_key(key);
+                let is_template = TEMPLATE_KEYS.contains(&key.as_str())
+                    || (parent == Some("http") && HTTP_TEMPLATE_KEYS.contains(&key.as_str()))
+                    || (parent == Some("mqtt") && MQTT_TEMPLATE_KEYS.contains(&key.as_str()))
+                    || (parent == Some("vars") && key == "value")
+                    || (matches!(parent, Some("osc" | "state_messages")) && key == "address")
+                    || (parent == Some("args") && key == "value");
+                // Query parameters of an http request: every value is a template
+                let is_template_map = parent == Some("http") && key == "query";
+                match child {
+                    Value::String(source) if is_template => f(at, source),
+                    Value::Object(params) if is_template_map => {
+                        for (name, param) in params {
+                            if let Value::String(source) = param {
+                                let saved = at.push_key(name);
+                                f(at, source);
+                                *at = saved;
+                            }
+                        }
+                    }
+                    // Argv-style `command` lists: every string is a template
+                    Value::Array(items) if is_template => {
+                        for (i, item) in items.iter().enumerate() {
+                            if let Value::String(source) = item {
+                                let saved = at.push_index(i);
+                                f(at, source);
+                                *at = saved;
+                            }
+                        }
+                    }
+                    _ => visit_templates(child, Some(key), at, f),
+                }
+                *at = saved;
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                let saved = at.push_index(i);
+                visit_templates(item, parent, at, f);
+                *at = saved;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Problems with a single template string
+pub fn check_template(source: &str) -> Vec<String> {
+    let template = match Template::compile(source) {
+        Ok(template) => template,
+        Err(e) => return vec![format!("template does not compile: {}", e)],
+    };
+
+    let fields = template_fields();
+    let mut problems = Vec::new();
+    walk_template(&template, &fields, &mut problems);
+    problems
+}
+
+fn is_helper(name: &str) -> bool {
+    BUILTIN_HELPERS.contains(&name) || helpers::HELPERS.iter().any(|(helper, _)| *helper == name)
+}
+
+/// Serialized empty template context, used to look up known fields
+fn template_fields() -> Value {
+    serde_json::to_value(TemplateContext::from(&ControllerState::default()))
+        .unwrap_or(Value::Null)
+}
+
+fn walk_template(template: &Template, fields: &Value, problems: &mut Vec<String>) {
+    for element in &template.elements {
+        walk_element(element, fields, problems);
+    }
+}
+
+fn walk_element(element: &TemplateElement, fields: &Value, problems: &mut Vec<String>) {
+    match element {
+        TemplateElement::Expression(helper)
+        | TemplateElement::HtmlExpression(helper)
+        | TemplateElement::HelperBlock(helper) => walk_helper(helper, fields, problems),
+        _ => {}
+    }
+}
+
+fn walk_helper(helper: &HelperTemplate, fields: &Value, problems: &mut Vec<String>) {
+    let is_call = !helper.params.is_empty() || !helper.hash.is_empty() || helper.block;
+    let name = helper.name.as_name();
+
+    match name {
+        Some(name) if is_call || is_helper(name) => {
+            if !is_helper(name) {
+                problems.push(format!("unknown helper `{}`", name));
+            }
+        }
+        _ => check_parameter(&helper.name, fields, problems),
+    }
+
+    for param in helper.params.iter().chain(helper.hash.values()) {
+        check_parameter(param, fields, problems);
+    }
+
+    // Inside `each`/`with` paths are relative to a different context
+    if name.is_some_and(|n| SCOPE_HELPERS.contains(&n)) {
+        return;
+    }
+    for inner in helper.template.iter().chain(helper.inverse.iter()) {
+        walk_template(inner, fields, problems);
+    }
+}
+
+fn check_parameter(param: &Parameter, fields: &Value, problems: &mut Vec<String>) {
+    let raw = match param {
+        Parameter::Name(name) => name.as_str(),
+        Parameter::Path(handlebars::Path::Relative((_, raw))) => raw.as_str(),
+        Parameter::Subexpression(sub) => {
+            walk_element(sub.as_element(), fields, problems);
+            return;
+        }
+        _ => return,
+    };
+
+    if !field_exists(raw, fields) {
+        problems.push(format!("unknown template field `{}`", raw));
+    }
+}
+
+fn field_exists(raw: &str, fields: &Value) -> bool {
+    let raw = raw.trim_start_matches("../").trim_start_matches("./");
+    let raw = raw.strip_prefix("this.").unwrap_or(raw);
+    if raw.is_empty() || raw == "this" || raw.starts_with('@') {
+        return true;
+    }
+
+    if raw
+        .split(['.', '/'])
+        .next()
+        .is_some_and(|first| OPEN_FIELDS.contains(&first))
+    {
+        return true;
+    }
+
+    let mut current = fields;
+    for segment in raw.split(['.', '/']) {
+        let segment = segment.trim_start_matches('[').trim_end_matches(']');
+        match current {
+            Value::Object(map) => match map.get(segment) {
+                Some(next) => current = next,
+                None => return false,
+            },
+            // Array elements and scalars are not checked further
+            _ => return true,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HttpMethod;
+    use serde_json::json;
+
+    fn paths(errors: &[ValidationError]) -> Vec<&str> {
+        errors.iter().map(|e| e.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_example_configs_are_valid() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("config");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let document = crate::compose::resolve_file(&path).unwrap().value;
+            let errors = validate_document(&document);
+            assert!(errors.is_empty(), "{}: {:?}", path.display(), errors);
+        }
+    }
+
+    #[test]
+    fn test_reports_every_error_with_path() {
+        let document = json!({
+            "poll_rate": -5,
+            "buttons": {
+                "cross": { "triger": "press", "command": "echo" },
+                "circle": { "trigger": "relase" }
+            },
+            "led": { "connected_color": { "r": 300, "g": 0, "b": 0 } },
+            "touchpad": { "regions": [{ "name": "a", "x": 0, "y": 0, "width": 1, "action": {} }] }
+        });
+
+        let errors = validate_document(&document);
+        assert_eq!(
+            paths(&errors),
+            vec![
+                "$.buttons.circle.trigger",
+                "$.buttons.cross.triger",
+                "$.led.connected_color.r",
+                "$.poll_rate",
+                "$.touchpad.regions[0]",
+            ]
+        );
+        assert!(errors[0].message.contains("expected one of press, release, hold, change"));
+        assert!(errors[1].message.contains("did you mean `trigger`?"));
+        assert_eq!(errors[1].key, "buttons.cross.triger");
+        assert!(errors[4].message.contains("missing required field `height`"));
+    }
+
+    #[test]
+    fn test_release_threshold_above_threshold() {
+        let document = json!({
+            "analog": { "left_stick": { "threshold": 0.5, "release_threshold": 0.7 } }
+        });
+        let errors = validate_document(&document);
+        assert_eq!(paths(&errors), vec!["$.analog.left_stick.release_threshold"]);
+        assert_eq!(errors[0].key, "analog.left_stick.release_threshold");
+
+        let config = crate::compose::ResolvedConfig {
+            value: document,
+            ..Default::default()
+        };
+        assert!(Config::from_resolved(config).is_err());
+    }
+
+    #[test]
+    fn test_midi_channels_and_high_resolution_cc() {
+        let document = json!({ "midi": {
+            "notes": { "cross": { "note": 60, "channel": 17 } },
+            "controls": { "left_stick_x": { "cc": 1, "channel": 0 } }
+        } });
+        assert_eq!(
+            paths(&validate_document(&document)),
+            vec![
+                "$.midi.controls.left_stick_x.channel",
+                "$.midi.notes.cross.channel"
+            ]
+        );
+
+        let document = json!({ "midi": { "controls": {
+            "left_stick_x": { "cc": 31, "high_resolution": true },
+            "left_stick_y": { "cc": 32, "high_resolution": true },
+            "right_stick_x": { "cc": 64 }
+        } } });
+        assert_eq!(
+            paths(&validate_document(&document)),
+            vec!["$.midi.controls.left_stick_y.cc"]
+        );
+    }
+
+    #[test]
+    fn test_ranges_checked_at_load() {
+        let load = |value| {
+            Config::from_resolved(crate::compose::ResolvedConfig {
+                value,
+                ..Default::default()
+            })
+        };
+        let note = json!({ "midi": { "notes": { "cross": { "note": 200 } } } });
+        assert!(load(note).is_err());
+        assert!(load(json!({ "deadzone": 1.0 })).is_err());
+        let gamepad = json!({ "gamepad": { "left_stick": { "deadzone": 1.5 } } });
+        assert_eq!(
+            paths(&validate_document(&gamepad)),
+            vec!["$.gamepad.left_stick.deadzone"]
+        );
+
+        let increment = json!({ "buttons": { "cross": {
+            "vars": [{ "name": "count", "op": "increment" }]
+        } } });
+        assert!(load(increment).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_key_names() {
+        let document = json!({
+            "buttons": {
+                "cross": { "key": { "key": "Enter" } },
+                "circle": { "key": { "key": "entr" } }
+            },
+            "touchpad": { "regions": [{
+                "name": "left", "x": 0, "y": 0, "width": 100, "height": 100,
+                "action": { "key": { "key": "f13x" } }
+            }] }
+        });
+        assert_eq!(
+            paths(&validate_document(&document)),
+            vec!["$.buttons.circle.key.key", "$.touchpad.regions[0].action.key.key"]
+        );
+    }
+
+    #[test]
+    fn test_enum_names_ignore_case() {
+        let document = json!({
+            "buttons": { "cross": { "trigger": "Press", "command": "echo" } },
+            "analog": { "l2_trigger": { "curve": "CUBIC" } }
+        });
+        assert!(validate_document(&document).is_empty());
+        let config = crate::compose::ResolvedConfig {
+            value: document,
+            ..Default::default()
+        };
+        assert!(Config::from_resolved(config).is_ok());
+    }
+
+    #[test]
+    fn test_repeat_only_on_pressed_buttons() {
+        let repeat = json!({ "delay_ms": 300 });
+        let document = json!({
+            "buttons": {
+                "dpad_up": { "command": "up", "repeat": repeat },
+                "dpad_down": { "trigger": "release", "command": "down", "repeat": repeat }
+            },
+            "touchpad": { "regions": [{
+                "name": "left", "x": 0, "y": 0, "width": 100, "height": 100,
+                "action": { "command": "left", "repeat": repeat }
+            }] },
+            "analog": { "left_stick": { "on_up": { "command": "up", "repeat": repeat } } }
+        });
+        let errors = validate_document(&document);
+        assert_eq!(
+            paths(&errors),
+            vec!["$.buttons.dpad_down.repeat", "$.analog.left_stick.on_up.repeat"]
+        );
+        assert!(errors[0].message.contains("trigger `press`"));
+        assert_eq!(errors[1].key, "analog.left_stick.on_up.repeat");
+    }
+
+    #[test]
+    fn test_template_checks() {
+        assert!(check_template("echo {{left_stick_x}} {{#if cross}}x{{/if}}").is_empty());
+        assert!(check_template("{{#if vars.pen_down}}{{vars.step}}{{/if}}").is_empty());
+        assert!(check_template("{{#each items}}{{whatever}}{{/each}}").len() == 1);
+        assert_eq!(
+            check_template("echo {{left_stik_x}}"),
+            vec!["unknown template field `left_stik_x`"]
+        );
+        assert_eq!(check_template("{{frobnicate cross}}"), vec!["unknown helper `frobnicate`"]);
+        assert!(check_template("{{fixed (mul left_stick_x 100) 1}}").is_empty());
+        assert!(check_template("{{#if cross}}")[0].starts_with("template does not compile"));
+    }
+
+    #[test]
+    fn test_template_errors_have_paths() {
+        let document = json!({
+            "http": { "base_url": "http://{{host}}" },
+            "buttons": { "cross": { "http": { "path": "/x/{{roll}}" } } }
+        });
+        let errors = validate_document(&document);
+        assert_eq!(paths(&errors), vec!["$.http.base_url"]);
+    }
+
+    #[test]
+    fn test_http_request_options() {
+        let document = json!({
+            "buttons": { "cross": { "http": {
+                "method": "FETCH",
+                "path": "/move",
+                "query": { "x": "{{left_stik_x}}" },
+                "response": { "extract": { "pen_x": "position.x" } }
+            } } }
+        });
+        let errors = validate_document(&document);
+        assert_eq!(
+            paths(&errors),
+            vec!["$.buttons.cross.http.method", "$.buttons.cross.http.query.x"]
+        );
+    }
+
+    #[test]
+    fn test_query_templates_only_in_http() {
+        // A socket named `query` holds plain settings, not query templates
+        let document = json!({
+            "sockets": { "query": { "type": "udp", "address": "{{host}}:9000" } }
+        });
+        assert!(validate_document(&document).is_empty());
+    }
+
+    #[test]
+    fn test_retries_need_idempotent_method() {
+        let document = json!({ "buttons": {
+            "cross": { "http": { "method": "post", "path": "/", "retries": 2 } },
+            "circle": { "http": { "method": "put", "path": "/", "retries": 2 } },
+            "square": { "http": {
+                "path": "/", "retries": 2, "retry_non_idempotent": true
+            } }
+        } });
+        let errors = validate_document(&document);
+        assert_eq!(paths(&errors), vec!["$.buttons.cross.http.retries"]);
+    }
+
+    #[test]
+    fn test_http_method_ignores_case() {
+        for method in ["GET", "get", "Get", "Post", "pAtCh"] {
+            let document =
+                json!({ "buttons": { "cross": { "http": { "method": method, "path": "/" } } } });
+            assert!(validate_document(&document).is_empty(), "{}", method);
+        }
+
+        let method: HttpMethod = serde_json::from_value(json!("Post")).unwrap();
+        assert_eq!(method, HttpMethod::Post);
+        assert!(serde_json::from_value::<HttpMethod>(json!("FETCH")).is_err());
+    }
+
+    #[test]
+    fn test_argv_command_items_are_templates() {
+        let document = json!({
+            "buttons": { "cross": { "command": ["echo", "{{cros}}"] } }
+        });
+        let errors = validate_document(&document);
+        assert_eq!(paths(&errors), vec!["$.buttons.cross.command[1]"]);
+    }
+
+    #[test]
+    fn test_osc_templates() {
+        let document = json!({
+            "osc": {
+                "target": "127.0.0.1:9000",
+                "state_messages": [{ "address": "/pad/{{lx}}", "args": [{ "field": "left_stick_x" }] }]
+            },
+            "buttons": {
+                "cross": { "osc": [{ "address": "/btn", "args": [{ "value": "{{evnt}}" }, 1] }] }
+            }
+        });
+        let errors = validate_document(&document);
+        assert_eq!(
+            paths(&errors),
+            vec![
+                "$.buttons.cross.osc[0].args[0].value",
+                "$.osc.state_messages[0].address"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mqtt_templates() {
+        let document = json!({
+            "mqtt": {
+                "host": "localhost",
+                "state_topic": "pad/{{nme}}/state",
+                "last_will": { "topic": "pad/status", "payload": "{{not_a_template}}" }
+            },
+            "buttons": {
+                "cross": { "mqtt": { "topic": "pad/cross", "payload": "{{crss}}", "qos": 1 } }
+            }
+        });
+        let errors = validate_document(&document);
+        assert_eq!(
+            paths(&errors),
+            vec!["$.buttons.cross.mqtt.payload", "$.mqtt.state_topic"]
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Raw socket outputs
+//!
+//! Sends rendered messages over UDP, a TCP client connection, or a Unix
+//! stream or datagram socket. Text messages on stream sockets end with a
+//! newline, so a reader gets one message per line (NDJSON with a JSON
+//! template); binary messages and datagrams go out as they are. Stream
+//! sockets connect on first use and reconnect after an error, waiting
+//! `reconnect_delay_ms` between attempts; messages in between are dropped.
+//! An attempt that takes longer than `connect_timeout_ms` counts as failed.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+#[cfg(unix)]
+use tokio::net::{UnixDatagram, UnixStream};
+use tracing::{debug, info};
+
+use crate::config::{SocketConfig, SocketKind};
+
+/// Longest a write may take before the connection is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+    #[cfg(unix)]
+    UnixDatagram(UnixDatagram),
+}
+
+/// One configured socket output
+pub struct SocketSink {
+    name: String,
+    config: SocketConfig,
+    connection: Option<Connection>,
+    /// No connection attempts before this (after a failure)
+    retry_at: Option<Instant>,
+}
+
+impl SocketSink {
+    pub fn new(name: &str, config: &SocketConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            config: config.clone(),
+            connection: None,
+            retry_at: None,
+        }
+    }
+
+    pub fn config(&self) -> &SocketConfig {
+        &self.config
+    }
+
+    /// Send one message. Returns false if it was dropped while waiting to
+    /// reconnect; errors drop the connection.
+    pub async fn send(&mut self, content: &[u8], binary: bool) -> Result<bool> {
+        if self.connection.is_none() {
+            if self.retry_at.is_some_and(|at| Instant::now() < at) {
+                return Ok(false);
+            }
+            match self.connect().await {
+                Ok(connection) => {
+                    if self.retry_at.take().is_some() {
+                        info!("Socket `{}` reconnected", self.name);
+                    }
+                    self.connection = Some(connection);
+                }
+                Err(e) => {
+                    self.failed();
+                    return Err(e);
+                }
+            }
+        }
+
+        let sent = match tokio::time::timeout(WRITE_TIMEOUT, self.write(content, binary)).await {
+            Ok(sent) => sent,
+            Err(_) => Err(anyhow::anyhow!("write timed out")),
+        };
+        if let Err(e) = sent {
+            self.failed();
+            return Err(e.context(format!("Failed to send to socket `{}`", self.name)));
+        }
+        Ok(true)
+    }
+
+    fn failed(&mut self) {
+        self.connection = None;
+        self.retry_at =
+            Some(Instant::now() + Duration::from_millis(self.config.reconnect_delay_ms));
+    }
+
+    async fn connect(&self) -> Result<Connection> {
+        let timeout = Duration::from_millis(self.config.connect_timeout_ms);
+        tokio::time::timeout(timeout, self.open())
+            .await
+            .with_context(|| format!("Connecting to {} timed out", self.config.address))?
+    }
+
+    async fn open(&self) -> Result<Connection> {
+        let address = &self.config.address;
+        debug!("Connecting socket `{}` to {}", self.name, address);
+        let connection = match self.config.kind {
+            SocketKind::Udp => {
+                let target = tokio::net::lookup_host(address)
+                    .await?
+                    .next()
+                    .with_context(|| format!("No address for {}", address))?;
+                let bind = if target.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                let socket = UdpSocket::bind(bind).await?;
+                socket.connect(target).await?;
+                Connection::Udp(socket)
+            }
+            SocketKind::Tcp => {
+                let stream = TcpStream::connect(address).await?;
+                stream.set_nodelay(true)?;
+                Connection::Tcp(stream)
+            }
+            #[cfg(unix)]
+            SocketKind::Unix => Connection::Unix(UnixStream::connect(address).await?),
+            #[cfg(unix)]
+            SocketKind::UnixDatagram => {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(address)?;
+                Connection::UnixDatagram(socket)
+            }
+            #[cfg(not(unix))]
+            SocketKind::Unix | SocketKind::UnixDatagram => {
+                anyhow::bail!("Unix sockets are not supported on this platform")
+            }
+        };
+        Ok(connection)
+    }
+
+    async fn write(&mut self, content: &[u8], binary: bool) -> Result<()> {
+        let line = || {
+            let mut line = content.to_vec();
+            if !binary {
+                line.push(b'\n');
+            }
+            line
+        };
+        match self.connection.as_mut() {
+            Some(Connection::Udp(socket)) => {
+                socket.send(content).await?;
+            }
+            Some(Connection::Tcp(stream)) => stream.write_all(&line()).await?,
+            #[cfg(unix)]
+            Some(Connection::Unix(stream)) => stream.write_all(&line()).await?,
+            #[cfg(unix)]
+            Some(Connection::UnixDatagram(socket)) => {
+                socket.send(content).await?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::TcpListener;
+
+    fn config(kind: SocketKind, address: &str, reconnect_delay_ms: u64) -> SocketConfig {
+        SocketConfig {
+            kind,
+            address: address.to_string(),
+            reconnect_delay_ms,
+            connect_timeout_ms: 1000,
+            state_format: None,
+            state_interval_ms: 0,
+            binary: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_udp() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = receiver.local_addr().unwrap().to_string();
+        let mut sink = SocketSink::new("udp", &config(SocketKind::Udp, &address, 0));
+
+        assert!(sink.send(b"{\"x\":1}", false).await.unwrap());
+        let mut buf = [0u8; 64];
+        let len = receiver.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"{\"x\":1}");
+    }
+
+    #[tokio::test]
+    async fn test_tcp_lines_and_reconnect() {
+        // Reserve a port, then free it so the first attempt is refused
+        let address = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let mut sink = SocketSink::new("tcp", &config(SocketKind::Tcp, &address, 60_000));
+        assert!(sink.send(b"lost", false).await.is_err());
+        // Waiting for the reconnect delay: dropped without an attempt
+        assert!(!sink.send(b"lost", false).await.unwrap());
+
+        let listener = TcpListener::bind(&address).await.unwrap();
+        sink.retry_at = None;
+        assert!(sink.send(b"{\"a\":1}", false).await.unwrap());
+        assert!(sink.send(b"raw", true).await.unwrap());
+        assert!(sink.send(b"{\"a\":2}", false).await.unwrap());
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut lines = BufReader::new(stream).lines();
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "{\"a\":1}");
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "raw{\"a\":2}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_stream_and_datagram() {
+        use tokio::net::UnixListener;
+
+        let dir = tempfile::tempdir().unwrap();
+        let stream_path = dir.path().join("sink.sock");
+        let datagram_path = dir.path().join("sink.dgram");
+
+        let listener = UnixListener::bind(&stream_path).unwrap();
+        let receiver = UnixDatagram::bind(&datagram_path).unwrap();
+
+        let mut stream_sink = SocketSink::new(
+            "unix",
+            &config(SocketKind::Unix, stream_path.to_str().unwrap(), 0),
+        );
+        let mut datagram_sink = SocketSink::new(
+            "dgram",
+            &config(SocketKind::UnixDatagram, datagram_path.to_str().unwrap(), 0),
+        );
+        assert!(stream_sink.send(b"line", false).await.unwrap());
+        assert!(datagram_sink.send(b"packet", false).await.unwrap());
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut lines = BufReader::new(stream).lines();
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "line");
+        let mut buf = [0u8; 64];
+        let len = receiver.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"packet");
+    }
+}
@@ -6,6 +6,7 @@
 use serde::{Deserialize, Serialize};
 use spatial_core::{ComplementaryFilter, Quaternion};
 
+use crate::curve::{AxisCurves, ResponseCurve};
 use crate::dualsense::ControllerState;
 
 /// Integration modes for the controller
@@ -32,8 +33,11 @@ impl Default for SpatialMode {
 /// Configuration for spatial integration, parsed from JSON config
 #[derive(Debug, Clone)]
 pub struct IntegrationConfig {
-    /// Velocity curve: "linear", "quadratic", "cubic"
-    pub velocity_curve: VelocityCurve,
+    /// Response curve for sticks and triggers
+    pub velocity_curve: ResponseCurve,
+
+    /// Per-axis curves, overriding `velocity_curve`
+    pub curves: AxisCurves,
 
     /// Maximum linear speed in mm/s
     pub max_linear_speed: f32,
@@ -60,7 +64,8 @@ pub struct IntegrationConfig {
 impl Default for IntegrationConfig {
     fn default() -> Self {
         Self {
-            velocity_curve: VelocityCurve::Linear,
+            velocity_curve: ResponseCurve::default(),
+            curves: AxisCurves::default(),
             max_linear_speed: 200.0,
             max_angular_speed: 6.0,
             linear_damping: 0.92,
@@ -72,27 +77,6 @@ impl Default for IntegrationConfig {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub enum VelocityCurve {
-    Linear,
-    Quadratic,
-    Cubic,
-}
-
-impl VelocityCurve {
-    /// Apply the velocity curve to a normalized input (-1.0 to 1.0)
-    pub fn apply(&self, input: f32) -> f32 {
-        let sign = input.signum();
-        let magnitude = input.abs();
-        let curved = match self {
-            VelocityCurve::Linear => magnitude,
-            VelocityCurve::Quadratic => magnitude * magnitude,
-            VelocityCurve::Cubic => magnitude * magnitude * magnitude,
-        };
-        sign * curved
-    }
-}
-
 /// Spatial state tracking position, velocity, and orientation
 pub struct SpatialState {
     /// Current integration mode
@@ -161,6 +145,14 @@ impl SpatialState {
         self.orientation_filter.orientation = quat;
     }
 
+    /// Replace the integration config, keeping position and orientation
+    pub fn set_config(&mut self, config: IntegrationConfig) {
+        let orientation = self.orientation_filter.orientation;
+        self.orientation_filter = ComplementaryFilter::new(config.gyro_weight);
+        self.orientation_filter.orientation = orientation;
+        self.config = config;
+    }
+
     /// Create a snapshot copy of the spatial state (for sending to renderer)
     pub fn snapshot(&self) -> SpatialState {
         let config = self.config.clone();
@@ -242,6 +234,8 @@ impl SpatialState {
                 let ly = apply_deadzone(ly, self.config.deadzone);
                 let l2 = apply_deadzone(l2, self.config.deadzone);
                 let r2 = apply_deadzone(r2, self.config.deadzone);
+                let (lx, ly) = self.curve_left_stick(lx, ly);
+                let (l2, r2) = self.curve_triggers(l2, r2);
 
                 // Natural: X=Right, Y=Forward, Z=Up
                 let target_vel = [
@@ -256,6 +250,7 @@ impl SpatialState {
                 let (l2, r2) = state.triggers.normalized();
                 let l2 = apply_deadzone(l2, self.config.deadzone);
                 let r2 = apply_deadzone(r2, self.config.deadzone);
+                let (l2, r2) = self.curve_triggers(l2, r2);
 
                 // Natural Forward is Y+ [0, 1, 0]
                 let quat = self.orientation_filter.orientation;
@@ -306,10 +301,12 @@ impl SpatialState {
                 let (rx, ry) = state.right_stick.normalized();
                 let rx = apply_deadzone(rx, self.config.deadzone);
                 let ry = apply_deadzone(ry, self.config.deadzone);
+                let (rx, ry) = self.curve_right_stick(rx, ry);
 
                 let (lx, ly) = state.left_stick.normalized();
                 let lx = apply_deadzone(lx, self.config.deadzone);
                 let ly = apply_deadzone(ly, self.config.deadzone);
+                let (lx, ly) = self.curve_left_stick(lx, ly);
 
                 let force_weight = 0.5;
                 let combined_x = rx + lx * force_weight;
@@ -318,6 +315,7 @@ impl SpatialState {
                 let (l2, r2) = state.triggers.normalized();
                 let l2 = apply_deadzone(l2, self.config.deadzone);
                 let r2 = apply_deadzone(r2, self.config.deadzone);
+                let (l2, r2) = self.curve_triggers(l2, r2);
 
                 // Pen Z: R2 lowers (incremental), L2 raises (fast)
                 let z_vel = (r2 * 0.5 - l2 * 2.0) * self.config.max_linear_speed;
@@ -343,6 +341,8 @@ impl SpatialState {
                 let ly = apply_deadzone(ly, self.config.deadzone);
                 let l2 = apply_deadzone(l2, self.config.deadzone);
                 let r2 = apply_deadzone(r2, self.config.deadzone);
+                let (lx, ly) = self.curve_left_stick(lx, ly);
+                let (l2, r2) = self.curve_triggers(l2, r2);
 
                 let quat = self.orientation_filter.orientation;
                 let forward = quat.rotate_vec3([0.0, 1.0, 0.0]);
@@ -393,6 +393,24 @@ impl SpatialState {
     pub fn smoothed_velocity(&self) -> [f32; 3] {
         self.smoothed_velocity
     }
+
+    fn curve_left_stick(&self, x: f32, y: f32) -> (f32, f32) {
+        self.config
+            .curves
+            .left_stick((x, y), &self.config.velocity_curve)
+    }
+
+    fn curve_right_stick(&self, x: f32, y: f32) -> (f32, f32) {
+        self.config
+            .curves
+            .right_stick((x, y), &self.config.velocity_curve)
+    }
+
+    fn curve_triggers(&self, l2: f32, r2: f32) -> (f32, f32) {
+        self.config
+            .curves
+            .triggers((l2, r2), &self.config.velocity_curve)
+    }
 }
 
 /// Apply deadzone to an input value
@@ -412,17 +430,26 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_velocity_curve_linear() {
-        let curve = VelocityCurve::Linear;
-        assert!((curve.apply(0.5) - 0.5).abs() < 1e-6);
-        assert!((curve.apply(-0.5) - (-0.5)).abs() < 1e-6);
-    }
+    fn test_velocity_curve_applied() {
+        // Left stick half way to the right
+        let mut state = ControllerState::default();
+        state.left_stick.x = 192;
+        state.left_stick.y = 128;
+
+        let velocity_x = |velocity_curve| {
+            let mut spatial = SpatialState::new(IntegrationConfig {
+                velocity_curve,
+                deadzone: 0.0,
+                smoothing_alpha: 1.0,
+                ..Default::default()
+            });
+            spatial.integrate(&state, 0.01);
+            spatial.velocity[0]
+        };
 
-    #[test]
-    fn test_velocity_curve_cubic() {
-        let curve = VelocityCurve::Cubic;
-        assert!((curve.apply(0.5) - 0.125).abs() < 1e-6);
-        assert!((curve.apply(-0.5) - (-0.125)).abs() < 1e-6);
+        let linear = velocity_x(ResponseCurve::default());
+        let cubic = velocity_x(ResponseCurve::Preset(crate::curve::CurvePreset::Cubic));
+        assert!(cubic > 0.0 && cubic < linear * 0.5);
     }
 
     #[test]
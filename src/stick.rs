@@ -0,0 +1,232 @@
+//! Stick direction recognition
+//!
+//! Turns a stick position into discrete direction events, so a stick can
+//! drive menus like a D-pad. Directions are angular sectors (4 or 8 of
+//! them); separate press/release thresholds on the magnitude and an
+//! angular margin around sector edges keep a stick resting on a boundary
+//! from chattering.
+
+use crate::config::StickMapping;
+
+/// Stick direction (stick Y grows downwards)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StickDirection {
+    Right,
+    UpRight,
+    Up,
+    UpLeft,
+    Left,
+    DownLeft,
+    Down,
+    DownRight,
+}
+
+impl StickDirection {
+    /// Directions counter-clockwise from right, 45 degrees apart
+    const ALL: [StickDirection; 8] = [
+        StickDirection::Right,
+        StickDirection::UpRight,
+        StickDirection::Up,
+        StickDirection::UpLeft,
+        StickDirection::Left,
+        StickDirection::DownLeft,
+        StickDirection::Down,
+        StickDirection::DownRight,
+    ];
+
+    /// Snake-case name, matching the `on_up_right` style mapping keys
+    pub fn name(&self) -> &'static str {
+        match self {
+            StickDirection::Right => "right",
+            StickDirection::UpRight => "up_right",
+            StickDirection::Up => "up",
+            StickDirection::UpLeft => "up_left",
+            StickDirection::Left => "left",
+            StickDirection::DownLeft => "down_left",
+            StickDirection::Down => "down",
+            StickDirection::DownRight => "down_right",
+        }
+    }
+
+    /// Center angle of the direction in degrees (0 = right, 90 = up)
+    pub fn angle(&self) -> f32 {
+        let index = Self::ALL.iter().position(|d| d == self).unwrap_or(0);
+        index as f32 * 45.0
+    }
+
+    /// Direction whose sector contains `angle`
+    fn from_angle(angle: f32, diagonals: bool) -> Self {
+        let step = if diagonals { 1 } else { 2 };
+        let sector = 45.0 * step as f32;
+        let index = ((angle.rem_euclid(360.0) + sector / 2.0) / sector) as usize;
+        Self::ALL[(index * step) % 8]
+    }
+}
+
+/// Direction change reported by [`StickProcessor::update`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StickEvent {
+    /// The stick entered a direction
+    Direction(StickDirection),
+    /// The stick returned to center after being in a direction
+    Center,
+}
+
+impl StickEvent {
+    /// `event_type` of the fired action: a direction name or `center`
+    pub fn name(&self) -> &'static str {
+        match self {
+            StickEvent::Direction(direction) => direction.name(),
+            StickEvent::Center => "center",
+        }
+    }
+}
+
+/// Stick processor tuning
+#[derive(Debug, Clone, Copy)]
+pub struct StickSettings {
+    /// Magnitude that enters a direction (0.0 - 1.0)
+    pub press_threshold: f32,
+    /// Magnitude below which the stick counts as centered again
+    pub release_threshold: f32,
+    /// Recognize diagonals (8-way) instead of 4-way
+    pub diagonals: bool,
+    /// How far (degrees) past a sector edge the stick must go to switch direction
+    pub angle_hysteresis_deg: f32,
+}
+
+impl Default for StickSettings {
+    fn default() -> Self {
+        StickMapping::default().stick_settings()
+    }
+}
+
+/// Tracks one stick's direction
+#[derive(Debug, Clone, Default)]
+pub struct StickProcessor {
+    settings: StickSettings,
+    direction: Option<StickDirection>,
+}
+
+impl StickProcessor {
+    pub fn new(settings: StickSettings) -> Self {
+        Self {
+            settings,
+            direction: None,
+        }
+    }
+
+    /// Change the tuning, keeping the current direction
+    pub fn set_settings(&mut self, settings: StickSettings) {
+        self.settings = settings;
+    }
+
+    /// Current direction, if the stick is out of center
+    pub fn direction(&self) -> Option<StickDirection> {
+        self.direction
+    }
+
+    /// Feed a normalized stick position and return the direction change, if any
+    pub fn update(&mut self, x: f32, y: f32) -> Option<StickEvent> {
+        let magnitude = (x * x + y * y).sqrt();
+        let angle = (-y).atan2(x).to_degrees();
+
+        match self.direction {
+            None if magnitude >= self.settings.press_threshold => {
+                let direction = StickDirection::from_angle(angle, self.settings.diagonals);
+                self.direction = Some(direction);
+                Some(StickEvent::Direction(direction))
+            }
+            None => None,
+            Some(_) if magnitude < self.settings.release_threshold => {
+                self.direction = None;
+                Some(StickEvent::Center)
+            }
+            Some(current) => {
+                let direction = StickDirection::from_angle(angle, self.settings.diagonals);
+                if direction == current || self.within_margin(current, angle) {
+                    return None;
+                }
+                self.direction = Some(direction);
+                Some(StickEvent::Direction(direction))
+            }
+        }
+    }
+
+    /// Whether `angle` is still within the current sector plus the margin
+    fn within_margin(&self, current: StickDirection, angle: f32) -> bool {
+        let half_sector = if self.settings.diagonals { 22.5 } else { 45.0 };
+        let offset = (angle - current.angle() + 180.0).rem_euclid(360.0) - 180.0;
+        offset.abs() <= half_sector + self.settings.angle_hysteresis_deg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(angle_deg: f32, magnitude: f32) -> (f32, f32) {
+        let rad = angle_deg.to_radians();
+        // Stick Y is inverted: up is negative
+        (magnitude * rad.cos(), -magnitude * rad.sin())
+    }
+
+    fn feed(processor: &mut StickProcessor, samples: &[(f32, f32)]) -> Vec<StickEvent> {
+        samples
+            .iter()
+            .filter_map(|&(angle, magnitude)| {
+                let (x, y) = at(angle, magnitude);
+                processor.update(x, y)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_four_way_with_center() {
+        let mut p = StickProcessor::default();
+        let events = feed(&mut p, &[(0.0, 0.2), (90.0, 0.8), (80.0, 0.9), (0.0, 0.1)]);
+        assert_eq!(
+            events,
+            vec![StickEvent::Direction(StickDirection::Up), StickEvent::Center]
+        );
+    }
+
+    #[test]
+    fn test_eight_way_sectors() {
+        let mut p = StickProcessor::new(StickSettings {
+            diagonals: true,
+            ..Default::default()
+        });
+        let events = feed(&mut p, &[(45.0, 0.9), (0.0, 0.1), (225.0, 0.9)]);
+        assert_eq!(
+            events,
+            vec![
+                StickEvent::Direction(StickDirection::UpRight),
+                StickEvent::Center,
+                StickEvent::Direction(StickDirection::DownLeft),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_magnitude_hysteresis() {
+        let mut p = StickProcessor::default();
+        // Hovering between release (0.4) and press (0.5) does not chatter
+        let events = feed(&mut p, &[(0.0, 0.5), (0.0, 0.45), (0.0, 0.52), (0.0, 0.42)]);
+        assert_eq!(events, vec![StickEvent::Direction(StickDirection::Right)]);
+    }
+
+    #[test]
+    fn test_angle_hysteresis() {
+        let mut p = StickProcessor::default();
+        // Right sector ends at 45 degrees; switching needs 55
+        let events = feed(&mut p, &[(40.0, 0.9), (50.0, 0.9), (40.0, 0.9), (60.0, 0.9)]);
+        assert_eq!(
+            events,
+            vec![
+                StickEvent::Direction(StickDirection::Right),
+                StickEvent::Direction(StickDirection::Up),
+            ]
+        );
+    }
+}
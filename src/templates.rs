@@ -0,0 +1,243 @@
+//! Precompiled action templates
+//!
+//! Template strings in a config are [`Template`] values, which carry a
+//! handle derived from their source when the config is loaded. Every
+//! template in a config is compiled once, when the executor is built, and
+//! registered under its handle, so a per-event render is a lookup by a
+//! short name and does no parsing.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use handlebars::Handlebars;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::config::Config;
+use crate::helpers;
+use crate::schema;
+
+/// A template string from a config, with the handle it is compiled under
+#[derive(Clone)]
+pub struct Template {
+    source: String,
+    handle: Arc<str>,
+}
+
+impl Template {
+    pub fn new(source: impl Into<String>) -> Self {
+        let source = source.into();
+        let handle = handle_of(&source);
+        Self { source, handle }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Name the template is registered under in a [`TemplateSet`]
+    pub fn handle(&self) -> &str {
+        &self.handle
+    }
+}
+
+/// Handle of a template source; equal sources share one
+fn handle_of(source: &str) -> Arc<str> {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    format!("t{:016x}", hasher.finish()).into()
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Self::new(String::new())
+    }
+}
+
+impl Deref for Template {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.source
+    }
+}
+
+impl PartialEq for Template {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl fmt::Debug for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.source, f)
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl From<&str> for Template {
+    fn from(source: &str) -> Self {
+        Self::new(source)
+    }
+}
+
+impl From<String> for Template {
+    fn from(source: String) -> Self {
+        Self::new(source)
+    }
+}
+
+impl Serialize for Template {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.source.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Template {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+impl JsonSchema for Template {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        String::schema_name()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(gen)
+    }
+}
+
+/// Compiled templates of one config
+pub struct TemplateSet {
+    handlebars: Handlebars<'static>,
+    /// Handle to template source, to catch two sources sharing a handle
+    sources: HashMap<Arc<str>, String>,
+}
+
+impl TemplateSet {
+    /// Compile every template in `config`, reporting all that fail
+    pub fn compile(config: &Config) -> Result<Self> {
+        let document = serde_json::to_value(config).context("Failed to serialize config")?;
+        let mut handlebars = helpers::registry();
+        let mut sources: HashMap<Arc<str>, String> = HashMap::new();
+        let mut problems = Vec::new();
+
+        for (path, source) in schema::template_sources(&document) {
+            let handle = handle_of(&source);
+            if let Some(existing) = sources.get(&handle) {
+                if *existing != source {
+                    problems.push(format!("{}: template handle collision, reword it", path));
+                }
+                continue;
+            }
+            match handlebars.register_template_string(&handle, &source) {
+                Ok(()) => {
+                    sources.insert(handle, source);
+                }
+                Err(e) => problems.push(format!("{}: {}", path, e)),
+            }
+        }
+
+        if !problems.is_empty() {
+            anyhow::bail!("Invalid templates:\n  {}", problems.join("\n  "));
+        }
+
+        Ok(Self {
+            handlebars,
+            sources,
+        })
+    }
+
+    /// Whether `template` is part of this set
+    pub fn contains(&self, template: &Template) -> bool {
+        self.sources.contains_key(template.handle())
+    }
+
+    /// Number of distinct compiled templates
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Render a compiled template by handle
+    pub fn render_handle<T: Serialize>(&self, handle: &str, data: &T) -> Result<String> {
+        Ok(self.handlebars.render(handle, data)?)
+    }
+
+    /// Render a template of the compiled config. A template from outside
+    /// it is an error rather than being parsed on the fly.
+    pub fn render<T: Serialize>(&self, template: &Template, data: &T) -> Result<String> {
+        if !self.contains(template) {
+            anyhow::bail!("Template is not part of the active config: {}", template);
+        }
+        self.render_handle(template.handle(), data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config(value: serde_json::Value) -> Config {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_compiles_all_templates() {
+        let config = config(json!({
+            "buttons": {
+                "cross": { "command": "echo {{left_stick_x}}", "vars": [
+                    { "name": "n", "op": "set", "value": "{{mul right_stick_y 2}}" }
+                ] },
+                "circle": { "command": "echo {{left_stick_x}}" },
+                "square": { "http": { "path": "/x/{{roll}}", "body": "{{json cross}}" } }
+            },
+            "websocket": { "url": "ws://localhost", "state_format": "{{l2}}" }
+        }));
+        let templates = TemplateSet::compile(&config).unwrap();
+        assert_eq!(templates.len(), 5);
+
+        let data = json!({ "left_stick_x": 0.5, "right_stick_y": 2 });
+        let render = |source: &str| templates.render(&Template::from(source), &data);
+        // Equal sources share a handle, wherever they come from
+        assert_eq!(render("echo {{left_stick_x}}").unwrap(), "echo 0.5");
+        assert_eq!(render("{{mul right_stick_y 2}}").unwrap(), "4");
+        assert!(templates.contains(&Template::from("{{l2}}")));
+
+        // Templates outside the config are an error, not parsed on the fly
+        assert!(render("{{right_stick_y}}").is_err());
+    }
+
+    #[test]
+    fn test_reports_every_broken_template() {
+        let config = config(json!({
+            "buttons": {
+                "cross": { "command": "echo {{#if x}}" },
+                "circle": { "websocket": { "message": "{{oops" } }
+            }
+        }));
+        let message = format!("{:#}", TemplateSet::compile(&config).err().unwrap());
+        assert!(message.contains("$.buttons.cross.command"), "{}", message);
+        assert!(message.contains("$.buttons.circle.websocket.message"), "{}", message);
+    }
+}
@@ -0,0 +1,476 @@
+//! Touchpad gesture recognition
+//!
+//! Turns the raw finger tracks reported by the DualSense touchpad into
+//! discrete gestures (tap, two-finger tap, swipe, pinch, rotate) and
+//! evaluates rectangular regions that act as extra virtual buttons.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::TouchpadMappings;
+use crate::dualsense::{TouchFinger, Touchpad};
+
+/// Touchpad width in raw units (x: 0-1919)
+pub const TOUCHPAD_WIDTH: f32 = 1920.0;
+/// Touchpad height in raw units (y: 0-1079)
+pub const TOUCHPAD_HEIGHT: f32 = 1080.0;
+
+/// Swipe direction (touchpad Y grows downwards)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+/// Recognized touchpad gesture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    Tap,
+    TwoFingerTap,
+    Swipe(SwipeDirection),
+    PinchIn,
+    PinchOut,
+    RotateClockwise,
+    RotateCounterClockwise,
+}
+
+impl Gesture {
+    /// Gesture part of its debounce key (`touchpad_tap`) and its `event_type`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gesture::Tap => "tap",
+            Gesture::TwoFingerTap => "two_finger_tap",
+            Gesture::Swipe(SwipeDirection::Up) => "swipe_up",
+            Gesture::Swipe(SwipeDirection::Down) => "swipe_down",
+            Gesture::Swipe(SwipeDirection::Left) => "swipe_left",
+            Gesture::Swipe(SwipeDirection::Right) => "swipe_right",
+            Gesture::Swipe(SwipeDirection::UpLeft) => "swipe_up_left",
+            Gesture::Swipe(SwipeDirection::UpRight) => "swipe_up_right",
+            Gesture::Swipe(SwipeDirection::DownLeft) => "swipe_down_left",
+            Gesture::Swipe(SwipeDirection::DownRight) => "swipe_down_right",
+            Gesture::PinchIn => "pinch_in",
+            Gesture::PinchOut => "pinch_out",
+            Gesture::RotateClockwise => "rotate_cw",
+            Gesture::RotateCounterClockwise => "rotate_ccw",
+        }
+    }
+}
+
+/// Gesture recognizer tuning (distances in raw touchpad units)
+#[derive(Debug, Clone, Copy)]
+pub struct GestureSettings {
+    /// Maximum touch duration for a tap in ms
+    pub tap_max_ms: u64,
+    /// Maximum finger travel for a tap
+    pub tap_max_distance: f32,
+    /// Minimum finger travel for a swipe
+    pub swipe_min_distance: f32,
+    /// Maximum duration for a swipe in ms
+    pub swipe_max_ms: u64,
+    /// Recognize diagonal swipes (8-way) instead of 4-way
+    pub swipe_diagonals: bool,
+    /// Change in finger spacing that counts as one pinch step
+    pub pinch_threshold: f32,
+    /// Change in finger angle (degrees) that counts as one rotate step
+    pub rotate_threshold_deg: f32,
+}
+
+impl Default for GestureSettings {
+    fn default() -> Self {
+        TouchpadMappings::default().gesture_settings()
+    }
+}
+
+/// A single touch session, from first finger down to last finger up
+#[derive(Debug, Clone, Copy)]
+struct TouchSession {
+    start_ms: u64,
+    start: (f32, f32),
+    last: (f32, f32),
+    max_fingers: u8,
+    /// Farthest any finger moved from where it touched down
+    max_travel: f32,
+    /// Finger positions when the second finger touched down
+    pair_start: Option<[(f32, f32); 2]>,
+    /// Spacing/angle baseline while two fingers are down
+    pair_baseline: Option<(f32, f32)>,
+    /// Pinch or rotate fired, so the lift is not a tap/swipe
+    consumed: bool,
+}
+
+/// Touchpad gesture recognizer
+#[derive(Debug, Clone, Default)]
+pub struct GestureRecognizer {
+    settings: GestureSettings,
+    session: Option<TouchSession>,
+}
+
+impl GestureRecognizer {
+    pub fn new(settings: GestureSettings) -> Self {
+        Self {
+            settings,
+            session: None,
+        }
+    }
+
+    /// Change the tuning, keeping a touch in progress
+    pub fn set_settings(&mut self, settings: GestureSettings) {
+        self.settings = settings;
+    }
+
+    /// Feed a touchpad sample taken at `now_ms` and return completed gestures
+    pub fn update(&mut self, touchpad: &Touchpad, now_ms: u64) -> Vec<Gesture> {
+        let mut gestures = Vec::new();
+        let fingers: Vec<&TouchFinger> = [&touchpad.finger1, &touchpad.finger2]
+            .into_iter()
+            .filter(|f| f.active)
+            .collect();
+
+        if fingers.is_empty() {
+            if let Some(session) = self.session.take() {
+                if let Some(gesture) = self.classify_release(&session, now_ms) {
+                    gestures.push(gesture);
+                }
+            }
+            return gestures;
+        }
+
+        let primary = (fingers[0].x as f32, fingers[0].y as f32);
+        let session = self.session.get_or_insert(TouchSession {
+            start_ms: now_ms,
+            start: primary,
+            last: primary,
+            max_fingers: 0,
+            max_travel: 0.0,
+            pair_start: None,
+            pair_baseline: None,
+            consumed: false,
+        });
+
+        session.max_fingers = session.max_fingers.max(fingers.len() as u8);
+        if fingers.len() == 1 {
+            session.last = primary;
+            session.max_travel = session.max_travel.max(distance(session.start, primary));
+            session.pair_start = None;
+            session.pair_baseline = None;
+            return gestures;
+        }
+
+        // Two fingers: track travel for taps, spacing and angle for pinch/rotate
+        let a = (fingers[0].x as f32, fingers[0].y as f32);
+        let b = (fingers[1].x as f32, fingers[1].y as f32);
+        let [start_a, start_b] = *session.pair_start.get_or_insert([a, b]);
+        session.max_travel = session
+            .max_travel
+            .max(distance(start_a, a))
+            .max(distance(start_b, b));
+        let spacing = distance(a, b);
+        let angle = (b.1 - a.1).atan2(b.0 - a.0).to_degrees();
+
+        let Some((base_spacing, base_angle)) = session.pair_baseline else {
+            session.pair_baseline = Some((spacing, angle));
+            return gestures;
+        };
+
+        let spacing_delta = spacing - base_spacing;
+        let angle_delta = wrap_degrees(angle - base_angle);
+
+        if spacing_delta.abs() >= self.settings.pinch_threshold {
+            gestures.push(if spacing_delta > 0.0 {
+                Gesture::PinchOut
+            } else {
+                Gesture::PinchIn
+            });
+            session.pair_baseline = Some((spacing, angle));
+            session.consumed = true;
+        } else if angle_delta.abs() >= self.settings.rotate_threshold_deg {
+            // Y grows downwards, so a positive angle delta is clockwise
+            gestures.push(if angle_delta > 0.0 {
+                Gesture::RotateClockwise
+            } else {
+                Gesture::RotateCounterClockwise
+            });
+            session.pair_baseline = Some((spacing, angle));
+            session.consumed = true;
+        }
+
+        gestures
+    }
+
+    fn classify_release(&self, session: &TouchSession, now_ms: u64) -> Option<Gesture> {
+        if session.consumed {
+            return None;
+        }
+
+        let duration = now_ms.saturating_sub(session.start_ms);
+        let displacement = distance(session.start, session.last);
+
+        let is_tap = duration <= self.settings.tap_max_ms
+            && session.max_travel <= self.settings.tap_max_distance;
+        if session.max_fingers >= 2 {
+            return is_tap.then_some(Gesture::TwoFingerTap);
+        }
+        if is_tap {
+            return Some(Gesture::Tap);
+        }
+
+        if displacement >= self.settings.swipe_min_distance
+            && duration <= self.settings.swipe_max_ms
+        {
+            let dx = session.last.0 - session.start.0;
+            let dy = session.last.1 - session.start.1;
+            return Some(Gesture::Swipe(swipe_direction(
+                dx,
+                dy,
+                self.settings.swipe_diagonals,
+            )));
+        }
+
+        None
+    }
+}
+
+/// Rectangular touchpad region in normalized coordinates (0.0 - 1.0)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TouchRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl TouchRect {
+    /// Whether a finger lies inside this rectangle
+    pub fn contains(&self, finger: &TouchFinger) -> bool {
+        let fx = finger.x as f32 / TOUCHPAD_WIDTH;
+        let fy = finger.y as f32 / TOUCHPAD_HEIGHT;
+        fx >= self.x && fx < self.x + self.width && fy >= self.y && fy < self.y + self.height
+    }
+
+    /// Whether the region is "pressed" given the touchpad state.
+    /// With `require_click`, the touchpad button must also be down.
+    pub fn is_pressed(&self, touchpad: &Touchpad, clicked: bool, require_click: bool) -> bool {
+        if require_click && !clicked {
+            return false;
+        }
+        [&touchpad.finger1, &touchpad.finger2]
+            .into_iter()
+            .any(|f| f.active && self.contains(f))
+    }
+}
+
+fn swipe_direction(dx: f32, dy: f32, diagonals: bool) -> SwipeDirection {
+    // Screen-style angle: 0 = right, 90 = down
+    let angle = dy.atan2(dx).to_degrees().rem_euclid(360.0);
+    if diagonals {
+        let sector = ((angle + 22.5) / 45.0) as u32 % 8;
+        match sector {
+            0 => SwipeDirection::Right,
+            1 => SwipeDirection::DownRight,
+            2 => SwipeDirection::Down,
+            3 => SwipeDirection::DownLeft,
+            4 => SwipeDirection::Left,
+            5 => SwipeDirection::UpLeft,
+            6 => SwipeDirection::Up,
+            _ => SwipeDirection::UpRight,
+        }
+    } else {
+        let sector = ((angle + 45.0) / 90.0) as u32 % 4;
+        match sector {
+            0 => SwipeDirection::Right,
+            1 => SwipeDirection::Down,
+            2 => SwipeDirection::Left,
+            _ => SwipeDirection::Up,
+        }
+    }
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
+
+fn wrap_degrees(deg: f32) -> f32 {
+    (deg + 180.0).rem_euclid(360.0) - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finger(x: u16, y: u16) -> TouchFinger {
+        TouchFinger {
+            active: true,
+            id: 0,
+            x,
+            y,
+        }
+    }
+
+    fn pad(f1: Option<(u16, u16)>, f2: Option<(u16, u16)>) -> Touchpad {
+        Touchpad {
+            finger1: f1.map(|(x, y)| finger(x, y)).unwrap_or_default(),
+            finger2: f2.map(|(x, y)| finger(x, y)).unwrap_or_default(),
+        }
+    }
+
+    /// Recorded sample: (time_ms, finger1, finger2)
+    type Sample = (u64, Option<(u16, u16)>, Option<(u16, u16)>);
+
+    /// Replay a recorded finger track
+    fn replay(recognizer: &mut GestureRecognizer, track: &[Sample]) -> Vec<Gesture> {
+        track
+            .iter()
+            .flat_map(|&(t, f1, f2)| recognizer.update(&pad(f1, f2), t))
+            .collect()
+    }
+
+    #[test]
+    fn test_tap() {
+        let mut r = GestureRecognizer::default();
+        let gestures = replay(
+            &mut r,
+            &[
+                (0, Some((900, 500)), None),
+                (40, Some((905, 502)), None),
+                (80, Some((908, 505)), None),
+                (120, None, None),
+            ],
+        );
+        assert_eq!(gestures, vec![Gesture::Tap]);
+    }
+
+    #[test]
+    fn test_long_press_is_not_tap() {
+        let mut r = GestureRecognizer::default();
+        let gestures = replay(
+            &mut r,
+            &[(0, Some((900, 500)), None), (800, Some((900, 500)), None), (820, None, None)],
+        );
+        assert!(gestures.is_empty());
+    }
+
+    #[test]
+    fn test_two_finger_tap() {
+        let mut r = GestureRecognizer::default();
+        let gestures = replay(
+            &mut r,
+            &[
+                (0, Some((600, 500)), None),
+                (20, Some((600, 500)), Some((1200, 500))),
+                (90, Some((602, 500)), Some((1201, 502))),
+                (130, None, None),
+            ],
+        );
+        assert_eq!(gestures, vec![Gesture::TwoFingerTap]);
+
+        // Both fingers sliding together is not a tap
+        let gestures = replay(
+            &mut r,
+            &[
+                (1000, Some((600, 500)), Some((1200, 500))),
+                (1060, Some((700, 500)), Some((1300, 500))),
+                (1120, None, None),
+            ],
+        );
+        assert!(gestures.is_empty());
+    }
+
+    #[test]
+    fn test_swipe_four_way() {
+        let mut r = GestureRecognizer::default();
+        let gestures = replay(
+            &mut r,
+            &[
+                (0, Some((300, 540)), None),
+                (100, Some((700, 550)), None),
+                (200, Some((1300, 560)), None),
+                (250, None, None),
+            ],
+        );
+        assert_eq!(gestures, vec![Gesture::Swipe(SwipeDirection::Right)]);
+
+        let gestures = replay(
+            &mut r,
+            &[
+                (1000, Some((960, 1000)), None),
+                (1150, Some((970, 300)), None),
+                (1200, None, None),
+            ],
+        );
+        assert_eq!(gestures, vec![Gesture::Swipe(SwipeDirection::Up)]);
+    }
+
+    #[test]
+    fn test_swipe_eight_way() {
+        let mut r = GestureRecognizer::new(GestureSettings {
+            swipe_diagonals: true,
+            ..Default::default()
+        });
+        let gestures = replay(
+            &mut r,
+            &[
+                (0, Some((1500, 900)), None),
+                (150, Some((1000, 400)), None),
+                (200, None, None),
+            ],
+        );
+        assert_eq!(gestures, vec![Gesture::Swipe(SwipeDirection::UpLeft)]);
+    }
+
+    #[test]
+    fn test_pinch_out_then_in() {
+        let mut r = GestureRecognizer::default();
+        let gestures = replay(
+            &mut r,
+            &[
+                (0, Some((800, 540)), Some((1100, 540))),
+                (50, Some((700, 540)), Some((1200, 540))),
+                (100, Some((600, 540)), Some((1300, 540))),
+                (150, Some((800, 540)), Some((1100, 540))),
+                (200, None, None),
+            ],
+        );
+        // Each threshold step of spacing change emits one pinch
+        assert_eq!(
+            gestures,
+            vec![Gesture::PinchOut, Gesture::PinchOut, Gesture::PinchIn]
+        );
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut r = GestureRecognizer::default();
+        // Second finger orbits the first clockwise (Y grows downwards)
+        let gestures = replay(
+            &mut r,
+            &[
+                (0, Some((960, 540)), Some((1260, 540))),
+                (50, Some((960, 540)), Some((1220, 690))),
+                (100, None, None),
+            ],
+        );
+        assert_eq!(gestures, vec![Gesture::RotateClockwise]);
+    }
+
+    #[test]
+    fn test_region_pressed() {
+        let left_half = TouchRect {
+            x: 0.0,
+            y: 0.0,
+            width: 0.5,
+            height: 1.0,
+        };
+        let touch_left = pad(Some((200, 500)), None);
+        let touch_right = pad(Some((1700, 500)), None);
+
+        assert!(left_half.is_pressed(&touch_left, true, true));
+        assert!(!left_half.is_pressed(&touch_left, false, true));
+        assert!(left_half.is_pressed(&touch_left, false, false));
+        assert!(!left_half.is_pressed(&touch_right, true, true));
+    }
+}
@@ -0,0 +1,229 @@
+//! Multi-stage trigger recognition
+//!
+//! Turns an analog trigger value into press/release events and named
+//! zones (e.g. a light, medium and full pull). Zones are bands starting at
+//! their threshold; leaving a press or zone needs the value to drop a
+//! hysteresis margin below where it was entered, so a trigger held on a
+//! boundary does not chatter.
+
+use crate::dualsense::TriggerEffect;
+
+/// Event reported by [`TriggerProcessor::update`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEvent {
+    /// The value crossed the press threshold
+    Press,
+    /// The value fell back below the release threshold
+    Release,
+    /// The value entered the zone at this index
+    ZoneEnter(usize),
+    /// The value left the zone at this index
+    ZoneLeave(usize),
+}
+
+impl TriggerEvent {
+    /// `event_type` of press and release actions; zone events are named
+    /// by `TriggerMapping::event_name`
+    pub fn name(&self) -> &'static str {
+        match self {
+            TriggerEvent::Press => "press",
+            TriggerEvent::Release => "release",
+            TriggerEvent::ZoneEnter(_) => "zone_enter",
+            TriggerEvent::ZoneLeave(_) => "zone_leave",
+        }
+    }
+}
+
+/// Trigger processor tuning
+#[derive(Debug, Clone, Default)]
+pub struct TriggerSettings {
+    /// Value that counts as pressed (0.0 - 1.0)
+    pub press_threshold: f32,
+    /// Value below which a press is released
+    pub release_threshold: f32,
+    /// Start of each zone, ascending
+    pub zones: Vec<f32>,
+    /// How far below a zone's start the value must drop to leave it
+    pub hysteresis: f32,
+}
+
+/// Tracks one trigger's press and zone
+#[derive(Debug, Clone, Default)]
+pub struct TriggerProcessor {
+    settings: TriggerSettings,
+    pressed: bool,
+    zone: Option<usize>,
+}
+
+impl TriggerProcessor {
+    pub fn new(settings: TriggerSettings) -> Self {
+        Self {
+            settings,
+            pressed: false,
+            zone: None,
+        }
+    }
+
+    /// Change the tuning, keeping the press and zone state (a zone that no
+    /// longer exists is left on the next update)
+    pub fn set_settings(&mut self, settings: TriggerSettings) {
+        self.settings = settings;
+    }
+
+    /// Index of the zone the trigger is in
+    pub fn zone(&self) -> Option<usize> {
+        self.zone
+    }
+
+    /// Feed a normalized trigger value and return what changed
+    pub fn update(&mut self, value: f32) -> Vec<TriggerEvent> {
+        let mut events = Vec::new();
+
+        if !self.pressed && value >= self.settings.press_threshold {
+            self.pressed = true;
+            events.push(TriggerEvent::Press);
+        } else if self.pressed && value < self.settings.release_threshold {
+            self.pressed = false;
+            events.push(TriggerEvent::Release);
+        }
+
+        let zone = self.zone_for(value);
+        if zone != self.zone {
+            if let Some(old) = self.zone {
+                events.push(TriggerEvent::ZoneLeave(old));
+            }
+            if let Some(new) = zone {
+                events.push(TriggerEvent::ZoneEnter(new));
+            }
+            self.zone = zone;
+        }
+
+        events
+    }
+
+    /// Zone for `value`, staying in the current zone within the hysteresis margin
+    fn zone_for(&self, value: f32) -> Option<usize> {
+        let zones = &self.settings.zones;
+        let entered = zones.iter().rposition(|&start| value >= start);
+
+        match self.zone {
+            // Moving down: keep the current zone until clearly below it
+            Some(current)
+                if entered.is_none_or(|e| e < current)
+                    && zones
+                        .get(current)
+                        .is_some_and(|&start| value >= start - self.settings.hysteresis) =>
+            {
+                Some(current)
+            }
+            _ => entered,
+        }
+    }
+
+    /// Adaptive trigger effect with a resistance detent at the start of the
+    /// next zone (off once the last zone is reached)
+    pub fn detent_effect(&self, force: u8) -> TriggerEffect {
+        let next = self.zone.map_or(0, |zone| zone + 1);
+        match self.settings.zones.get(next) {
+            Some(&start) => {
+                let position = (start.clamp(0.0, 1.0) * 255.0) as u8;
+                TriggerEffect::section(position, position.saturating_add(DETENT_WIDTH), force)
+            }
+            None => TriggerEffect::default(),
+        }
+    }
+}
+
+/// Width of a detent in raw trigger units
+const DETENT_WIDTH: u8 = 20;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dualsense::TriggerEffectMode;
+
+    fn zoned() -> TriggerProcessor {
+        TriggerProcessor::new(TriggerSettings {
+            press_threshold: 0.5,
+            release_threshold: 0.45,
+            zones: vec![0.2, 0.6, 0.95],
+            hysteresis: 0.05,
+        })
+    }
+
+    #[test]
+    fn test_press_and_release() {
+        let mut p = zoned();
+        assert_eq!(p.update(0.1), vec![]);
+        assert_eq!(p.update(0.5), vec![TriggerEvent::Press, TriggerEvent::ZoneEnter(0)]);
+        // Within hysteresis: still pressed
+        assert_eq!(p.update(0.47), vec![]);
+        assert_eq!(p.update(0.3), vec![TriggerEvent::Release]);
+    }
+
+    #[test]
+    fn test_zones_with_hysteresis() {
+        let mut p = zoned();
+        let events: Vec<_> = [0.25, 0.61, 0.58, 0.61, 0.54, 0.97, 0.0]
+            .iter()
+            .flat_map(|&v| p.update(v))
+            .filter(|e| matches!(e, TriggerEvent::ZoneEnter(_) | TriggerEvent::ZoneLeave(_)))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                TriggerEvent::ZoneEnter(0),
+                TriggerEvent::ZoneLeave(0),
+                TriggerEvent::ZoneEnter(1),
+                // 0.58 and 0.61 stay in "medium", 0.54 drops to "light"
+                TriggerEvent::ZoneLeave(1),
+                TriggerEvent::ZoneEnter(0),
+                TriggerEvent::ZoneLeave(0),
+                TriggerEvent::ZoneEnter(2),
+                TriggerEvent::ZoneLeave(2),
+            ]
+        );
+        assert_eq!(p.zone(), None);
+    }
+
+    #[test]
+    fn test_detent_follows_zone() {
+        let mut p = zoned();
+        let effect = p.detent_effect(180);
+        assert_eq!(effect.mode, TriggerEffectMode::SectionResistance);
+        assert_eq!(effect.start_position, 51);
+
+        p.update(0.7);
+        assert_eq!(p.detent_effect(180).start_position, 242);
+
+        p.update(1.0);
+        assert_eq!(p.detent_effect(180).mode, TriggerEffectMode::Off);
+    }
+
+    #[test]
+    fn test_retune_keeps_press() {
+        let mut p = zoned();
+        p.update(0.7);
+        p.set_settings(TriggerSettings {
+            press_threshold: 0.8,
+            release_threshold: 0.6,
+            ..Default::default()
+        });
+        // Still pressed: no second press, and the new release point applies
+        assert_eq!(p.update(0.65), vec![TriggerEvent::ZoneLeave(1)]);
+        assert_eq!(p.update(0.5), vec![TriggerEvent::Release]);
+    }
+
+    #[test]
+    fn test_zones_sorted_at_load() {
+        let mapping: crate::config::TriggerMapping = serde_json::from_value(serde_json::json!({
+            "zones": [
+                { "name": "full", "threshold": 0.95 },
+                { "name": "light", "threshold": 0.2 }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(mapping.trigger_settings().zones, vec![0.2, 0.95]);
+        assert_eq!(mapping.event_name(TriggerEvent::ZoneEnter(0)), "light_enter");
+    }
+}
@@ -0,0 +1,393 @@
+//! Virtual input devices through Linux uinput
+//!
+//! Events are plain Linux input codes, written in frames: each
+//! [`EventSink::emit`] call is followed by a sync, so a receiver sees the
+//! frame's events together. [`UinputDevice`] is the real sink; tests
+//! record frames instead, so nothing here needs `/dev/uinput` access.
+
+use anyhow::Result;
+
+/// Linux input event codes (`linux/input-event-codes.h`)
+pub mod codes {
+    pub const BTN_LEFT: u16 = 0x110;
+    pub const BTN_RIGHT: u16 = 0x111;
+    pub const BTN_MIDDLE: u16 = 0x112;
+    pub const BTN_SIDE: u16 = 0x113;
+    pub const BTN_EXTRA: u16 = 0x114;
+
+    pub const REL_X: u16 = 0x00;
+    pub const REL_Y: u16 = 0x01;
+    pub const REL_HWHEEL: u16 = 0x06;
+    pub const REL_WHEEL: u16 = 0x08;
+
+    pub const KEY_LEFTCTRL: u16 = 29;
+    pub const KEY_LEFTSHIFT: u16 = 42;
+    pub const KEY_LEFTALT: u16 = 56;
+    pub const KEY_LEFTMETA: u16 = 125;
+
+    pub const BTN_SOUTH: u16 = 0x130;
+    pub const BTN_EAST: u16 = 0x131;
+    pub const BTN_NORTH: u16 = 0x133;
+    pub const BTN_WEST: u16 = 0x134;
+    pub const BTN_TL: u16 = 0x136;
+    pub const BTN_TR: u16 = 0x137;
+    pub const BTN_SELECT: u16 = 0x13a;
+    pub const BTN_START: u16 = 0x13b;
+    pub const BTN_MODE: u16 = 0x13c;
+    pub const BTN_THUMBL: u16 = 0x13d;
+    pub const BTN_THUMBR: u16 = 0x13e;
+
+    pub const ABS_X: u16 = 0x00;
+    pub const ABS_Y: u16 = 0x01;
+    pub const ABS_Z: u16 = 0x02;
+    pub const ABS_RX: u16 = 0x03;
+    pub const ABS_RY: u16 = 0x04;
+    pub const ABS_RZ: u16 = 0x05;
+    pub const ABS_HAT0X: u16 = 0x10;
+    pub const ABS_HAT0Y: u16 = 0x11;
+}
+
+/// Keyboard keys by config name
+const KEYS: &[(&str, u16)] = &[
+    ("esc", 1),
+    ("escape", 1),
+    ("1", 2),
+    ("2", 3),
+    ("3", 4),
+    ("4", 5),
+    ("5", 6),
+    ("6", 7),
+    ("7", 8),
+    ("8", 9),
+    ("9", 10),
+    ("0", 11),
+    ("minus", 12),
+    ("equal", 13),
+    ("backspace", 14),
+    ("tab", 15),
+    ("q", 16),
+    ("w", 17),
+    ("e", 18),
+    ("r", 19),
+    ("t", 20),
+    ("y", 21),
+    ("u", 22),
+    ("i", 23),
+    ("o", 24),
+    ("p", 25),
+    ("leftbrace", 26),
+    ("rightbrace", 27),
+    ("enter", 28),
+    ("ctrl", 29),
+    ("leftctrl", 29),
+    ("a", 30),
+    ("s", 31),
+    ("d", 32),
+    ("f", 33),
+    ("g", 34),
+    ("h", 35),
+    ("j", 36),
+    ("k", 37),
+    ("l", 38),
+    ("semicolon", 39),
+    ("apostrophe", 40),
+    ("grave", 41),
+    ("shift", 42),
+    ("leftshift", 42),
+    ("backslash", 43),
+    ("z", 44),
+    ("x", 45),
+    ("c", 46),
+    ("v", 47),
+    ("b", 48),
+    ("n", 49),
+    ("m", 50),
+    ("comma", 51),
+    ("dot", 52),
+    ("period", 52),
+    ("slash", 53),
+    ("rightshift", 54),
+    ("alt", 56),
+    ("leftalt", 56),
+    ("space", 57),
+    ("capslock", 58),
+    ("f1", 59),
+    ("f2", 60),
+    ("f3", 61),
+    ("f4", 62),
+    ("f5", 63),
+    ("f6", 64),
+    ("f7", 65),
+    ("f8", 66),
+    ("f9", 67),
+    ("f10", 68),
+    ("numlock", 69),
+    ("scrolllock", 70),
+    ("f11", 87),
+    ("f12", 88),
+    ("rightctrl", 97),
+    ("print", 99),
+    ("rightalt", 100),
+    ("home", 102),
+    ("up", 103),
+    ("pageup", 104),
+    ("left", 105),
+    ("right", 106),
+    ("end", 107),
+    ("down", 108),
+    ("pagedown", 109),
+    ("insert", 110),
+    ("delete", 111),
+    ("mute", 113),
+    ("volumedown", 114),
+    ("volumeup", 115),
+    ("pause", 119),
+    ("meta", 125),
+    ("super", 125),
+    ("leftmeta", 125),
+    ("rightmeta", 126),
+    ("menu", 127),
+    ("nextsong", 163),
+    ("playpause", 164),
+    ("previoussong", 165),
+    ("stopcd", 166),
+];
+
+/// Code of a key name (`a`, `enter`, `f5`, `volumeup`; case-insensitive)
+pub fn key_code(name: &str) -> Option<u16> {
+    let name = name.to_ascii_lowercase();
+    KEYS.iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, code)| *code)
+}
+
+/// One input event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    /// Key or button, pressed or released
+    Key { code: u16, pressed: bool },
+    /// Relative axis movement (pointer, wheel)
+    Relative { code: u16, value: i32 },
+    /// Absolute axis position (sticks, triggers, hat)
+    Absolute { code: u16, value: i32 },
+}
+
+/// Events written together, followed by a sync
+pub type Frame = Vec<InputEvent>;
+
+/// Where input events go
+pub trait EventSink: Send {
+    /// Write one frame
+    fn emit(&mut self, frame: &[InputEvent]) -> Result<()>;
+}
+
+/// Helpers for tests of the devices built on [`EventSink`]
+#[cfg(test)]
+pub mod testing {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// Stands in for the uinput device; clones share what was emitted
+    #[derive(Clone, Default)]
+    pub struct Recorder {
+        frames: Arc<Mutex<Vec<Frame>>>,
+    }
+
+    impl Recorder {
+        /// Frames emitted so far
+        pub fn frames(&self) -> Vec<Frame> {
+            self.frames.lock().unwrap().clone()
+        }
+    }
+
+    impl EventSink for Recorder {
+        fn emit(&mut self, frame: &[InputEvent]) -> Result<()> {
+            self.frames.lock().unwrap().push(frame.to_vec());
+            Ok(())
+        }
+    }
+
+    /// Config section from JSON
+    pub fn from_json<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+}
+
+/// What a virtual device can send
+#[derive(Debug, Clone, Default)]
+pub struct DeviceSpec {
+    pub name: String,
+    /// USB vendor, product and version to report; unset leaves them 0
+    pub id: Option<(u16, u16, u16)>,
+    pub keys: Vec<u16>,
+    pub relative: Vec<u16>,
+    pub absolute: Vec<AbsAxis>,
+}
+
+/// Range of an absolute axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbsAxis {
+    pub code: u16,
+    pub min: i32,
+    pub max: i32,
+    /// Noise the receiver may filter out
+    pub fuzz: i32,
+    /// Deadzone the receiver may apply
+    pub flat: i32,
+}
+
+impl DeviceSpec {
+    /// Keyboard with every named key, plus a five-button wheel mouse
+    pub fn keyboard_mouse(name: &str) -> Self {
+        let mut keys: Vec<u16> = KEYS.iter().map(|(_, code)| *code).collect();
+        keys.extend([
+            codes::BTN_LEFT,
+            codes::BTN_RIGHT,
+            codes::BTN_MIDDLE,
+            codes::BTN_SIDE,
+            codes::BTN_EXTRA,
+        ]);
+        keys.sort_unstable();
+        keys.dedup();
+        Self {
+            name: name.to_string(),
+            keys,
+            relative: vec![
+                codes::REL_X,
+                codes::REL_Y,
+                codes::REL_HWHEEL,
+                codes::REL_WHEEL,
+            ],
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use linux::UinputDevice;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use anyhow::{Context, Result};
+    use evdev::uinput::{VirtualDevice, VirtualDeviceBuilder};
+    use evdev::{
+        AbsInfo, AbsoluteAxisType, AttributeSet, BusType, EventType, InputId, Key,
+        RelativeAxisType, UinputAbsSetup,
+    };
+
+    use super::{DeviceSpec, EventSink, InputEvent};
+
+    /// A device created through `/dev/uinput`; removed when dropped
+    pub struct UinputDevice {
+        device: VirtualDevice,
+    }
+
+    impl UinputDevice {
+        pub fn create(spec: &DeviceSpec) -> Result<Self> {
+            let mut keys = AttributeSet::<Key>::new();
+            for code in &spec.keys {
+                keys.insert(Key::new(*code));
+            }
+            let mut relative = AttributeSet::<RelativeAxisType>::new();
+            for code in &spec.relative {
+                relative.insert(RelativeAxisType(*code));
+            }
+
+            let build = || -> std::io::Result<VirtualDevice> {
+                let mut builder = VirtualDeviceBuilder::new()?.name(&spec.name);
+                if let Some((vendor, product, version)) = spec.id {
+                    builder =
+                        builder.input_id(InputId::new(BusType::BUS_USB, vendor, product, version));
+                }
+                if !spec.keys.is_empty() {
+                    builder = builder.with_keys(&keys)?;
+                }
+                if !spec.relative.is_empty() {
+                    builder = builder.with_relative_axes(&relative)?;
+                }
+                for axis in &spec.absolute {
+                    let info = AbsInfo::new(0, axis.min, axis.max, axis.fuzz, axis.flat, 0);
+                    builder = builder.with_absolute_axis(&UinputAbsSetup::new(
+                        AbsoluteAxisType(axis.code),
+                        info,
+                    ))?;
+                }
+                builder.build()
+            };
+            let device = build().with_context(|| {
+                format!(
+                    "Failed to create virtual device `{}` (needs write access to /dev/uinput)",
+                    spec.name
+                )
+            })?;
+            Ok(Self { device })
+        }
+    }
+
+    impl EventSink for UinputDevice {
+        fn emit(&mut self, frame: &[InputEvent]) -> Result<()> {
+            let events: Vec<evdev::InputEvent> = frame
+                .iter()
+                .map(|event| match *event {
+                    InputEvent::Key { code, pressed } => {
+                        evdev::InputEvent::new(EventType::KEY, code, pressed as i32)
+                    }
+                    InputEvent::Relative { code, value } => {
+                        evdev::InputEvent::new(EventType::RELATIVE, code, value)
+                    }
+                    InputEvent::Absolute { code, value } => {
+                        evdev::InputEvent::new(EventType::ABSOLUTE, code, value)
+                    }
+                })
+                .collect();
+            // Appends the SYN_REPORT
+            self.device
+                .emit(&events)
+                .context("Failed to write input events")
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub struct UinputDevice;
+
+#[cfg(not(target_os = "linux"))]
+impl UinputDevice {
+    pub fn create(_spec: &DeviceSpec) -> Result<Self> {
+        anyhow::bail!("Virtual input devices need Linux (uinput)")
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl EventSink for UinputDevice {
+    fn emit(&mut self, _frame: &[InputEvent]) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_codes() {
+        assert_eq!(key_code("a"), Some(30));
+        assert_eq!(key_code("Enter"), Some(28));
+        assert_eq!(key_code("ctrl"), Some(codes::KEY_LEFTCTRL));
+        assert_eq!(key_code("f12"), Some(88));
+        assert_eq!(key_code("nope"), None);
+    }
+
+    #[test]
+    fn test_keyboard_mouse_spec() {
+        let spec = DeviceSpec::keyboard_mouse("pad");
+        assert!(spec.keys.contains(&30));
+        assert!(spec.keys.contains(&codes::BTN_LEFT));
+        // Aliases don't repeat a code
+        let mut keys = spec.keys.clone();
+        keys.dedup();
+        assert_eq!(keys, spec.keys);
+        assert!(spec.relative.contains(&codes::REL_WHEEL));
+    }
+}
@@ -0,0 +1,358 @@
+//! Runtime variables
+//!
+//! A small store of named JSON values that actions can set, increment,
+//! decrement, toggle or cycle. Values are exposed to templates as
+//! `{{vars.name}}`, can gate actions through `when` conditions and can
+//! optionally be persisted to a JSON file between runs. Changes are
+//! written at most once per [`PERSIST_DELAY`] and on shutdown, so a fast
+//! stream of updates doesn't turn into a stream of file writes.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+use crate::config::{VarCondition, VarOp, VarOpKind, VarValue};
+use crate::templates::Template;
+
+/// Longest a change waits before it is written to the persistence file
+pub const PERSIST_DELAY: Duration = Duration::from_secs(1);
+
+/// Named variable values
+#[derive(Debug, Clone, Default)]
+pub struct VarStore {
+    values: BTreeMap<String, Value>,
+    persist: Option<PathBuf>,
+    /// First change not yet written to `persist`
+    unsaved_since: Option<Instant>,
+}
+
+impl VarStore {
+    /// Create a store from initial values, overlaid with persisted values
+    pub fn new(initial: &BTreeMap<String, Value>, persist: Option<PathBuf>) -> Self {
+        let mut store = Self {
+            values: initial.clone(),
+            persist,
+            unsaved_since: None,
+        };
+        if let Some(path) = &store.persist {
+            match load_values(path) {
+                Ok(saved) => store.values.extend(saved),
+                Err(e) => tracing::debug!("No persisted variables loaded: {:#}", e),
+            }
+        }
+        store
+    }
+
+    /// Adopt new settings after a config reload, keeping current values.
+    /// Pending changes are written to the old file first.
+    pub fn reconfigure(
+        &mut self,
+        initial: &BTreeMap<String, Value>,
+        persist: Option<PathBuf>,
+    ) -> Result<()> {
+        let flushed = self.flush();
+        for (name, value) in initial {
+            self.values.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+        self.persist = persist;
+        flushed
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    /// All values as a JSON object (for template contexts)
+    pub fn snapshot(&self) -> Map<String, Value> {
+        self.values
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// Apply an operation. `set` string values are passed through `render`
+    /// so they can use templates.
+    pub fn apply(
+        &mut self,
+        op: &VarOp,
+        render: impl Fn(&Template) -> Result<String>,
+    ) -> Result<()> {
+        let current = self.values.get(&op.name).cloned().unwrap_or(Value::Null);
+
+        let next = match op.op {
+            VarOpKind::Set => match &op.value {
+                Some(VarValue::Template(template)) => parse_rendered(&render(template)?),
+                Some(VarValue::Value(value)) => value.clone(),
+                None => Value::Null,
+            },
+            VarOpKind::Increment => step(&current, op.by, op),
+            VarOpKind::Decrement => step(&current, -op.by, op),
+            VarOpKind::Toggle => Value::Bool(!is_truthy(&current)),
+            VarOpKind::Cycle => {
+                let Some(first) = op.values.first() else {
+                    anyhow::bail!("`cycle` on `{}` needs a non-empty `values` list", op.name);
+                };
+                op.values
+                    .iter()
+                    .position(|v| *v == current)
+                    .and_then(|i| op.values.get((i + 1) % op.values.len()))
+                    .unwrap_or(first)
+                    .clone()
+            }
+        };
+
+        tracing::debug!("var {} = {}", op.name, next);
+        self.values.insert(op.name.clone(), next);
+        self.changed();
+        Ok(())
+    }
+
+    /// Store a value from outside an action (command output, HTTP response)
+    pub fn set(&mut self, name: &str, value: Value) {
+        tracing::debug!("var {} = {}", name, value);
+        self.values.insert(name.to_string(), value);
+        self.changed();
+    }
+
+    /// Whether a condition holds for the current values
+    pub fn matches(&self, condition: &VarCondition) -> bool {
+        let value = self.values.get(&condition.var).unwrap_or(&Value::Null);
+        let number = value.as_f64();
+        let compare = |bound: Option<f64>, ok: fn(f64, f64) -> bool| match bound {
+            Some(bound) => number.is_some_and(|n| ok(n, bound)),
+            None => true,
+        };
+
+        let any_check = condition.eq.is_some()
+            || condition.ne.is_some()
+            || condition.gt.is_some()
+            || condition.gte.is_some()
+            || condition.lt.is_some()
+            || condition.lte.is_some();
+
+        (any_check || is_truthy(value))
+            && condition.eq.as_ref().is_none_or(|eq| values_equal(value, eq))
+            && condition.ne.as_ref().is_none_or(|ne| !values_equal(value, ne))
+            && compare(condition.gt, |n, b| n > b)
+            && compare(condition.gte, |n, b| n >= b)
+            && compare(condition.lt, |n, b| n < b)
+            && compare(condition.lte, |n, b| n <= b)
+    }
+
+    fn changed(&mut self) {
+        if self.persist.is_some() && self.unsaved_since.is_none() {
+            self.unsaved_since = Some(Instant::now());
+        }
+    }
+
+    /// Time left until unsaved changes should be written (zero when
+    /// overdue), or `None` when everything is saved
+    pub fn flush_in(&self) -> Option<Duration> {
+        self.unsaved_since
+            .map(|since| PERSIST_DELAY.saturating_sub(since.elapsed()))
+    }
+
+    /// Write unsaved changes to the persistence file, if configured
+    pub fn flush(&mut self) -> Result<()> {
+        if self.unsaved_since.take().is_none() {
+            return Ok(());
+        }
+        let Some(path) = &self.persist else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Write-then-rename so a crash never leaves a truncated file
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&self.values)?)
+            .with_context(|| format!("Failed to write variables: {}", tmp.display()))?;
+        fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to write variables: {}", path.display()))?;
+        Ok(())
+    }
+}
+
+fn load_values(path: &Path) -> Result<BTreeMap<String, Value>> {
+    let content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn step(current: &Value, by: f64, op: &VarOp) -> Value {
+    let mut next = current.as_f64().unwrap_or(0.0) + by;
+
+    match (op.min, op.max) {
+        (Some(min), Some(max)) if op.wrap && max > min => {
+            let span = max - min;
+            let integral = [by, min, max].iter().all(|n| n.fract() == 0.0);
+            if integral {
+                // Whole steps count both ends: 0..=3 has four values
+                if next > max {
+                    next = min + (next - max - 1.0).rem_euclid(span + 1.0);
+                } else if next < min {
+                    next = max - (min - next - 1.0).rem_euclid(span + 1.0);
+                }
+            } else if next > max || next < min {
+                next = min + (next - min).rem_euclid(span);
+            }
+        }
+        (min, max) => {
+            if let Some(max) = max {
+                next = next.min(max);
+            }
+            if let Some(min) = min {
+                next = next.max(min);
+            }
+        }
+    }
+
+    number_value(next)
+}
+
+/// Store whole numbers as integers so templates print `3`, not `3.0`
+fn number_value(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::from(n as i64)
+    } else {
+        Value::from(n)
+    }
+}
+
+/// Rendered `set` values keep their JSON type when they parse as a scalar
+pub fn parse_rendered(rendered: &str) -> Value {
+    match serde_json::from_str::<Value>(rendered.trim()) {
+        Ok(value @ (Value::Bool(_) | Value::Number(_) | Value::Null)) => value,
+        _ => Value::String(rendered.to_string()),
+    }
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => x == y,
+        _ => a == b,
+    }
+}
+
+/// Handlebars-style truthiness
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn op(value: Value) -> VarOp {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn no_render(template: &Template) -> Result<String> {
+        Ok(template.to_string())
+    }
+
+    #[test]
+    fn test_operations() {
+        let mut store = VarStore::default();
+
+        store.apply(&op(json!({ "name": "pen", "op": "toggle" })), no_render).unwrap();
+        assert_eq!(store.get("pen"), Some(&json!(true)));
+        store.apply(&op(json!({ "name": "pen", "op": "toggle" })), no_render).unwrap();
+        assert_eq!(store.get("pen"), Some(&json!(false)));
+
+        let inc = op(json!({ "name": "step", "op": "inc", "by": 2, "max": 5 }));
+        for _ in 0..4 {
+            store.apply(&inc, no_render).unwrap();
+        }
+        assert_eq!(store.get("step"), Some(&json!(5)));
+        store.apply(&op(json!({ "name": "step", "op": "dec", "by": 0.5 })), no_render).unwrap();
+        assert_eq!(store.get("step"), Some(&json!(4.5)));
+
+        let cycle = op(json!({ "name": "mode", "op": "cycle", "values": ["a", "b", "c"] }));
+        let seen: Vec<Value> = (0..4)
+            .map(|_| {
+                store.apply(&cycle, no_render).unwrap();
+                store.get("mode").unwrap().clone()
+            })
+            .collect();
+        assert_eq!(seen, vec![json!("a"), json!("b"), json!("c"), json!("a")]);
+
+        let set = op(json!({ "name": "x", "op": "set", "value": "{{n}}" }));
+        store.apply(&set, |_| Ok("42".to_string())).unwrap();
+        assert_eq!(store.get("x"), Some(&json!(42)));
+    }
+
+    #[test]
+    fn test_wrapping_counter() {
+        let mut store = VarStore::default();
+        let inc = op(json!({ "name": "slot", "op": "inc", "min": 1, "max": 3, "wrap": true }));
+        let seen: Vec<Value> = (0..4)
+            .map(|_| {
+                store.apply(&inc, no_render).unwrap();
+                store.get("slot").unwrap().clone()
+            })
+            .collect();
+        assert_eq!(seen, vec![json!(1), json!(2), json!(3), json!(1)]);
+    }
+
+    #[test]
+    fn test_fractional_wrap_stays_in_range() {
+        let initial = [("level".to_string(), json!(1))].into_iter().collect();
+        let mut store = VarStore::new(&initial, None);
+        let step = |kind: &str| {
+            op(json!({ "name": "level", "op": kind, "by": 0.5, "min": 0, "max": 1, "wrap": true }))
+        };
+        let (inc, dec) = (step("inc"), step("dec"));
+
+        store.apply(&inc, no_render).unwrap();
+        assert_eq!(store.get("level"), Some(&json!(0.5)));
+        store.apply(&dec, no_render).unwrap();
+        store.apply(&dec, no_render).unwrap();
+        assert_eq!(store.get("level"), Some(&json!(0.5)));
+    }
+
+    #[test]
+    fn test_conditions() {
+        let initial = [("pen".to_string(), json!(true)), ("step".to_string(), json!(3))]
+            .into_iter()
+            .collect();
+        let store = VarStore::new(&initial, None);
+        let cond = |v: Value| -> VarCondition { serde_json::from_value(v).unwrap() };
+
+        assert!(store.matches(&cond(json!({ "var": "pen" }))));
+        assert!(!store.matches(&cond(json!({ "var": "missing" }))));
+        assert!(store.matches(&cond(json!({ "var": "pen", "eq": true }))));
+        assert!(store.matches(&cond(json!({ "var": "step", "gt": 2, "lte": 3 }))));
+        assert!(!store.matches(&cond(json!({ "var": "step", "ne": 3.0 }))));
+    }
+
+    #[test]
+    fn test_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vars.json");
+
+        let initial = [("count".to_string(), json!(0))].into_iter().collect();
+        let mut store = VarStore::new(&initial, Some(path.clone()));
+        store.apply(&op(json!({ "name": "count", "op": "inc" })), no_render).unwrap();
+        // Written on flush, not per change
+        assert!(store.flush_in().is_some());
+        assert!(!path.exists());
+        store.flush().unwrap();
+        assert_eq!(store.flush_in(), None);
+
+        let reloaded = VarStore::new(&initial, Some(path.clone()));
+        assert_eq!(reloaded.get("count"), Some(&json!(1)));
+    }
+}
@@ -0,0 +1,146 @@
+//! Config file watching for hot reload
+//!
+//! Polls modification times of every file a config was resolved from
+//! (plus the directory listing when a config directory is used). A change
+//! is reported once the files have stopped changing for a short settle
+//! period, so editors that write in several steps trigger one reload.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::compose;
+
+/// Default time between file checks
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Default time files must be unchanged before a reload
+pub const DEFAULT_SETTLE: Duration = Duration::from_millis(200);
+
+type Snapshot = Vec<(PathBuf, Option<SystemTime>)>;
+
+/// Watches config files for changes
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    root: PathBuf,
+    files: Vec<PathBuf>,
+    snapshot: Snapshot,
+    pending_since: Option<Instant>,
+    last_check: Option<Instant>,
+    poll_interval: Duration,
+    settle: Duration,
+}
+
+impl ConfigWatcher {
+    /// Watch `root` (a file or directory) and the files it was resolved from
+    pub fn new(root: PathBuf, files: Vec<PathBuf>) -> Self {
+        Self::with_timing(root, files, DEFAULT_POLL_INTERVAL, DEFAULT_SETTLE)
+    }
+
+    pub fn with_timing(
+        root: PathBuf,
+        files: Vec<PathBuf>,
+        poll_interval: Duration,
+        settle: Duration,
+    ) -> Self {
+        let mut watcher = Self {
+            root,
+            files,
+            snapshot: Vec::new(),
+            pending_since: None,
+            last_check: None,
+            poll_interval,
+            settle,
+        };
+        watcher.snapshot = watcher.take_snapshot();
+        watcher
+    }
+
+    /// Replace the watched files (after a reload changed `extends`/`include`)
+    pub fn set_files(&mut self, files: Vec<PathBuf>) {
+        self.files = files;
+        self.snapshot = self.take_snapshot();
+        self.pending_since = None;
+    }
+
+    /// Check for changes; returns true once a change has settled
+    pub fn poll(&mut self) -> bool {
+        let now = Instant::now();
+        if let Some(last) = self.last_check {
+            if now.duration_since(last) < self.poll_interval {
+                return false;
+            }
+        }
+        self.last_check = Some(now);
+
+        let current = self.take_snapshot();
+        if current != self.snapshot {
+            self.snapshot = current;
+            self.pending_since = Some(now);
+            return false;
+        }
+
+        match self.pending_since {
+            Some(since) if now.duration_since(since) >= self.settle => {
+                self.pending_since = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn take_snapshot(&self) -> Snapshot {
+        let mut paths = self.files.clone();
+        if self.root.is_dir() {
+            paths.extend(compose::dir_files(&self.root).unwrap_or_default());
+        }
+        paths.sort();
+        paths.dedup();
+
+        paths
+            .into_iter()
+            .map(|path| {
+                let modified = modified_time(&path);
+                (path, modified)
+            })
+            .collect()
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_change_reported_after_settle() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = scratch.path().to_path_buf();
+        let file = dir.join("config.json");
+        fs::write(&file, "{}").unwrap();
+
+        let mut watcher =
+            ConfigWatcher::with_timing(dir.clone(), vec![file.clone()], Duration::ZERO, Duration::ZERO);
+        assert!(!watcher.poll());
+
+        let later = SystemTime::now() + Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        // First poll sees the change, the next one (unchanged) reports it
+        assert!(!watcher.poll());
+        assert!(watcher.poll());
+        assert!(!watcher.poll());
+
+        // New files in a watched directory count as changes
+        fs::write(dir.join("extra.json"), "{}").unwrap();
+        assert!(!watcher.poll());
+        assert!(watcher.poll());
+    }
+}